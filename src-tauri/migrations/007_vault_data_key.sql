@@ -0,0 +1,6 @@
+-- Envelope encryption for the vault
+-- wrapped_key: project data key encrypted with a key derived from the master password
+--              format: versioned `$pmenc$` envelope recording the Argon2 salt and params
+-- Entries encrypted with the data key store '$datakey' in vault_entries.salt
+
+ALTER TABLE vault_master ADD COLUMN wrapped_key TEXT;
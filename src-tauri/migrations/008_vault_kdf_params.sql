@@ -0,0 +1,6 @@
+-- Per-vault Argon2id cost parameters
+-- kdf_params: JSON {"m_cost": KiB, "t_cost": iterations, "p_cost": lanes}
+--             NULL means the Argon2 defaults (m=19456, t=2, p=1)
+-- The master hash and wrapped data key are upgraded to these params on the next successful unlock
+
+ALTER TABLE vault_master ADD COLUMN kdf_params TEXT;
@@ -0,0 +1,5 @@
+-- TOTP (2FA) secret for vault entries
+-- encrypted_totp: otpauth:// URI or base32 secret, encrypted with the project data key
+--                 as a versioned envelope ($pmenc$...), NULL when the entry has no 2FA
+
+ALTER TABLE vault_entries ADD COLUMN encrypted_totp TEXT;
@@ -0,0 +1,8 @@
+-- SSH key pairs stored as vault entries (category 'ssh')
+-- encrypted_value holds the OpenSSH private key; the public half is not secret and
+-- stays readable while the vault is locked
+-- ssh_public_key:  "ssh-ed25519 AAAA... comment" line for authorized_keys / deploy keys
+-- ssh_fingerprint: "SHA256:..." as printed by ssh-keygen -l
+
+ALTER TABLE vault_entries ADD COLUMN ssh_public_key TEXT;
+ALTER TABLE vault_entries ADD COLUMN ssh_fingerprint TEXT;
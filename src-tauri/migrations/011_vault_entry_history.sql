@@ -0,0 +1,23 @@
+-- Previous encrypted values of vault entries, archived on every update that changes
+-- the value or notes. Rows are copied verbatim, so each version is decrypted with
+-- the same key as the entry was at the time.
+CREATE TABLE IF NOT EXISTS vault_entry_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id INTEGER NOT NULL,
+    project_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    encrypted_value TEXT NOT NULL,
+    encrypted_notes TEXT,
+    encrypted_totp TEXT,
+    salt TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    archived_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (entry_id, version),
+    FOREIGN KEY (entry_id) REFERENCES vault_entries(id) ON DELETE CASCADE,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_entry_history_entry_id ON vault_entry_history(entry_id);
+
+-- Number of versions kept per entry; 0 disables history
+ALTER TABLE vault_master ADD COLUMN history_retention INTEGER DEFAULT 10;
@@ -0,0 +1,19 @@
+-- Cached results of analyze_project, keyed by the normalized project path.
+-- The summary is the JSON returned to the frontend; per-file line counts let the
+-- next analysis recount only files whose size or modification time changed.
+CREATE TABLE IF NOT EXISTS project_analysis (
+    path TEXT PRIMARY KEY,
+    summary TEXT NOT NULL,
+    analyzed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS project_analysis_files (
+    project_path TEXT NOT NULL,
+    rel_path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    modified INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    lines INTEGER NOT NULL,
+    code INTEGER NOT NULL,
+    PRIMARY KEY (project_path, rel_path)
+);
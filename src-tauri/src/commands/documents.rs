@@ -22,7 +22,7 @@ fn get_documents_base_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
 }
 
 /// Get the directory for a specific document
-fn get_document_dir(app: &tauri::AppHandle, doc_id: i64) -> Result<PathBuf, String> {
+pub(crate) fn get_document_dir(app: &tauri::AppHandle, doc_id: i64) -> Result<PathBuf, String> {
     let base_dir = get_documents_base_dir(app)?;
     Ok(base_dir.join(format!("doc-{}", doc_id)))
 }
@@ -45,7 +45,7 @@ fn get_docvaults_base_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
 }
 
 /// Get the vault directory for a specific project
-fn get_docvault_dir(app: &tauri::AppHandle, project_id: i64) -> Result<PathBuf, String> {
+pub(crate) fn get_docvault_dir(app: &tauri::AppHandle, project_id: i64) -> Result<PathBuf, String> {
     let base_dir = get_docvaults_base_dir(app)?;
     Ok(base_dir.join(project_id.to_string()))
 }
@@ -1,4 +1,8 @@
-use git2::{BranchType, Commit, DiffOptions, Oid, Repository, StatusOptions};
+use git2::build::CheckoutBuilder;
+use git2::{
+    Branch, BranchType, CheckoutNotificationType, Commit, DiffOptions, ErrorCode, Oid, Repository,
+    StashApplyOptions, StatusOptions,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,13 +43,40 @@ pub struct GitFileChange {
     pub status: String,
     pub additions: usize,
     pub deletions: usize,
+    /// 统一 diff 格式的补丁，仅 `git_stash_show` 返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+}
+
+/// 当前 HEAD 相对上游分支的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitHeadState {
+    /// 在分支上且上游分支存在，ahead/behind 有效
+    Tracking,
+    /// 分支没有配置上游
+    NoUpstream,
+    /// 配置了上游，但远程分支引用已不存在（例如远程已删除并被 prune）
+    UpstreamGone,
+    /// HEAD 指向某个提交而不是分支
+    Detached,
+    /// 分支还没有任何提交
+    Unborn,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GitStatus {
     pub branch: String,
-    pub ahead: usize,
-    pub behind: usize,
+    pub head_state: GitHeadState,
+    /// 仅在 `Tracking` 状态下有值
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    /// 上游分支简称，如 `origin/main`
+    pub upstream: Option<String>,
+    /// 上游分支完整引用名，如 `refs/remotes/origin/main`
+    pub upstream_ref: Option<String>,
+    /// 最近一次 fetch 的时间（FETCH_HEAD 的修改时间，Unix 秒）
+    pub last_fetch: Option<i64>,
     pub files: Vec<GitStatusFile>,
 }
 
@@ -97,22 +128,7 @@ pub fn git_get_branches(path: String) -> Result<Vec<GitBranch>, String> {
 
     for branch_result in local_branches {
         let (branch, _) = branch_result.map_err(|e| e.message().to_string())?;
-        if let Some(name) = branch.name().map_err(|e| e.message().to_string())? {
-            let upstream = match branch.upstream() {
-                Ok(upstream_branch) => match upstream_branch.name() {
-                    Ok(Some(upstream_name)) => Some(upstream_name.to_string()),
-                    _ => None,
-                },
-                Err(_) => None,
-            };
-
-            branches.push(GitBranch {
-                name: name.to_string(),
-                is_head: branch.is_head(),
-                is_remote: false,
-                upstream,
-            });
-        }
+        branches.extend(branch_info(&branch, BranchType::Local)?);
     }
 
     // 远程分支
@@ -122,19 +138,39 @@ pub fn git_get_branches(path: String) -> Result<Vec<GitBranch>, String> {
 
     for branch_result in remote_branches {
         let (branch, _) = branch_result.map_err(|e| e.message().to_string())?;
-        if let Some(name) = branch.name().map_err(|e| e.message().to_string())? {
-            branches.push(GitBranch {
-                name: name.to_string(),
-                is_head: false,
-                is_remote: true,
-                upstream: None,
-            });
-        }
+        branches.extend(branch_info(&branch, BranchType::Remote)?);
     }
 
     Ok(branches)
 }
 
+// 分支名非 UTF-8 时返回 None
+pub(crate) fn branch_info(branch: &Branch, branch_type: BranchType) -> Result<Option<GitBranch>, String> {
+    let Some(name) = branch.name().map_err(|e| e.message().to_string())? else {
+        return Ok(None);
+    };
+
+    let is_remote = branch_type == BranchType::Remote;
+    let upstream = if is_remote {
+        None
+    } else {
+        match branch.upstream() {
+            Ok(upstream_branch) => match upstream_branch.name() {
+                Ok(Some(upstream_name)) => Some(upstream_name.to_string()),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    };
+
+    Ok(Some(GitBranch {
+        name: name.to_string(),
+        is_head: !is_remote && branch.is_head(),
+        is_remote,
+        upstream,
+    }))
+}
+
 // 获取提交历史
 #[tauri::command]
 pub fn git_get_commits(path: String, limit: usize) -> Result<Vec<GitCommit>, String> {
@@ -213,10 +249,6 @@ pub fn git_get_commit_detail(path: String, hash: String) -> Result<GitCommitDeta
 
 // 获取提交的文件变更
 fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileChange>, String> {
-    use std::collections::HashMap;
-
-    let mut files = Vec::new();
-
     let tree = commit.tree().map_err(|e| e.message().to_string())?;
     let parent_tree = if commit.parent_count() > 0 {
         Some(
@@ -235,6 +267,18 @@ fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileCha
         .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_opts))
         .map_err(|e| e.message().to_string())?;
 
+    diff_file_changes(&diff, false)
+}
+
+// 统计 diff 中每个文件的状态和增删行数；`with_patch` 时附带每个文件的补丁文本
+pub(crate) fn diff_file_changes(
+    diff: &git2::Diff,
+    with_patch: bool,
+) -> Result<Vec<GitFileChange>, String> {
+    use std::collections::HashMap;
+
+    let mut files = Vec::new();
+
     diff.foreach(
         &mut |delta, _| {
             let status = match delta.status() {
@@ -253,6 +297,7 @@ fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileCha
                         status: status.to_string(),
                         additions: 0,
                         deletions: 0,
+                        patch: None,
                     });
                 }
             }
@@ -268,6 +313,7 @@ fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileCha
     // 获取每个文件的增删行数
     // 使用 HashMap 来存储每个文件的统计信息
     let mut file_stats: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut patches: HashMap<String, String> = HashMap::new();
 
     diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
         if let Some(path) = delta.new_file().path() {
@@ -279,6 +325,15 @@ fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileCha
                     '-' => entry.1 += 1, // deletions
                     _ => {}
                 }
+
+                if with_patch {
+                    let patch = patches.entry(path_str.to_string()).or_default();
+                    // 内容行的前缀（+/-/空格）不包含在 content 中
+                    if matches!(line.origin(), '+' | '-' | ' ') {
+                        patch.push(line.origin());
+                    }
+                    patch.push_str(&String::from_utf8_lossy(line.content()));
+                }
             }
         }
         true
@@ -291,56 +346,70 @@ fn get_commit_files(repo: &Repository, commit: &Commit) -> Result<Vec<GitFileCha
             file.additions = *additions;
             file.deletions = *deletions;
         }
+        file.patch = patches.remove(&file.filename);
     }
 
     Ok(files)
 }
 
-// 切换分支
-#[tauri::command]
-pub fn git_checkout_branch(path: String, branch_name: String) -> Result<(), String> {
-    let repo = Repository::open(&path).map_err(|e| e.message().to_string())?;
-
-    // 检查工作区是否干净
-    let mut opts = StatusOptions::new();
-    opts.include_untracked(true)
-        .recurse_untracked_dirs(true)
-        .exclude_submodules(true);
+// 应用 stash@{index}，返回冲突路径；无冲突且 `drop` 为真时删除该 stash
+pub(crate) fn apply_stash(
+    repo: &mut Repository,
+    index: usize,
+    reinstate_index: bool,
+    drop: bool,
+) -> Result<Vec<String>, String> {
+    let mut conflicts = Vec::new();
+    let result = {
+        let mut checkout = CheckoutBuilder::new();
+        checkout
+            .notify_on(CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    conflicts.push(path.to_string_lossy().to_string());
+                }
+                true
+            });
+        let mut options = StashApplyOptions::new();
+        options.checkout_options(checkout);
+        if reinstate_index {
+            options.reinstantiate_index();
+        }
+        repo.stash_apply(index, Some(&mut options))
+    };
 
-    let statuses = repo
-        .statuses(Some(&mut opts))
-        .map_err(|e| e.message().to_string())?;
-    if !statuses.is_empty() {
-        return Err("有未提交的更改，请先提交或暂存".to_string());
+    match result {
+        Ok(()) => {}
+        // 工作区改动会被覆盖，或恢复暂存区时冲突：什么都没有改变
+        Err(e) if e.code() == ErrorCode::Conflict => {
+            if conflicts.is_empty() {
+                return Err(e.message().to_string());
+            }
+            return Ok(conflicts);
+        }
+        Err(e) => return Err(e.message().to_string()),
     }
 
-    // 查找分支
-    let branch = repo
-        .find_branch(&branch_name, BranchType::Local)
-        .map_err(|e| e.message().to_string())?;
-
-    let tree = branch
-        .get()
-        .peel_to_tree()
-        .map_err(|e| e.message().to_string())?;
-
-    // 检出分支
-    repo.checkout_tree(tree.as_object(), None)
-        .map_err(|e| e.message().to_string())?;
-
-    repo.set_head(&format!("refs/heads/{}", branch_name))
+    // 合并冲突写入了索引和工作区
+    let index_conflicts = repo
+        .index()
+        .map(|index| conflicted_paths(&index))
         .map_err(|e| e.message().to_string())?;
+    if !index_conflicts.is_empty() {
+        return Ok(index_conflicts);
+    }
 
-    Ok(())
+    if drop {
+        repo.stash_drop(index).map_err(|e| e.message().to_string())?;
+    }
+    Ok(Vec::new())
 }
 
 // 获取仓库状态
 #[tauri::command]
 pub fn git_get_status(path: String) -> Result<GitStatus, String> {
     let repo = Repository::open(&path).map_err(|e| e.message().to_string())?;
-
-    let head = repo.head().map_err(|e| e.message().to_string())?;
-    let branch = head.shorthand().unwrap_or("HEAD").to_string();
+    let tracking = get_tracking(&repo)?;
 
     // 配置状态选项，排除被忽略的文件
     let mut opts = StatusOptions::new();
@@ -382,15 +451,126 @@ pub fn git_get_status(path: String) -> Result<GitStatus, String> {
         });
     }
 
-    // TODO: 计算 ahead/behind（需要比较本地和远程分支）
     Ok(GitStatus {
-        branch,
-        ahead: 0,
-        behind: 0,
+        branch: tracking.branch,
+        head_state: tracking.state,
+        ahead: tracking.ahead_behind.map(|(ahead, _)| ahead),
+        behind: tracking.ahead_behind.map(|(_, behind)| behind),
+        upstream: tracking.upstream,
+        upstream_ref: tracking.upstream_ref,
+        last_fetch: last_fetch_time(&repo),
         files,
     })
 }
 
+struct Tracking {
+    branch: String,
+    state: GitHeadState,
+    upstream: Option<String>,
+    upstream_ref: Option<String>,
+    ahead_behind: Option<(usize, usize)>,
+}
+
+// 解析当前分支及其上游，并计算 ahead/behind
+fn get_tracking(repo: &Repository) -> Result<Tracking, String> {
+    let head = match repo.head() {
+        Ok(head) => Some(head),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.message().to_string()),
+    };
+
+    if repo.head_detached().unwrap_or(false) {
+        return Ok(Tracking {
+            branch: "HEAD".to_string(),
+            state: GitHeadState::Detached,
+            upstream: None,
+            upstream_ref: None,
+            ahead_behind: None,
+        });
+    }
+
+    // 未出生的分支没有 HEAD 引用对象，只能从符号引用中读取分支名
+    let branch_ref = match &head {
+        Some(head) => head.name().unwrap_or("HEAD").to_string(),
+        None => repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|r| r.symbolic_target().map(str::to_string))
+            .unwrap_or_else(|| "HEAD".to_string()),
+    };
+    let branch = branch_ref
+        .strip_prefix("refs/heads/")
+        .unwrap_or(&branch_ref)
+        .to_string();
+
+    // 上游配置（branch.<name>.remote / merge）存在时即可得到引用名，不要求远程分支存在
+    let upstream_ref = repo
+        .branch_upstream_name(&branch_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string));
+    let upstream_target = upstream_ref.as_deref().and_then(|name| {
+        repo.find_reference(name).ok().and_then(|r| r.target())
+    });
+    let upstream = upstream_ref.as_deref().map(|name| {
+        name.strip_prefix("refs/remotes/").unwrap_or(name).to_string()
+    });
+
+    let (state, ahead_behind) = match (&head, &upstream_ref, upstream_target) {
+        (None, _, _) => (GitHeadState::Unborn, None),
+        (Some(_), None, _) => (GitHeadState::NoUpstream, None),
+        (Some(_), Some(_), None) => (GitHeadState::UpstreamGone, None),
+        (Some(head), Some(_), Some(upstream_oid)) => {
+            let local_oid = head
+                .target()
+                .ok_or_else(|| "HEAD does not point to a commit".to_string())?;
+            let counts = repo
+                .graph_ahead_behind(local_oid, upstream_oid)
+                .map_err(|e| e.message().to_string())?;
+            (GitHeadState::Tracking, Some(counts))
+        }
+    };
+
+    Ok(Tracking {
+        branch,
+        state,
+        upstream,
+        upstream_ref,
+        ahead_behind,
+    })
+}
+
+// 索引中存在冲突的路径（去重，按出现顺序）
+pub(crate) fn conflicted_paths(index: &git2::Index) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    let Ok(conflicts) = index.conflicts() else {
+        return paths;
+    };
+    for conflict in conflicts.flatten() {
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+        if let Some(entry) = entry {
+            let path = String::from_utf8_lossy(&entry.path).to_string();
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+// FETCH_HEAD 位于公共 git 目录（工作树共享），其修改时间即最近一次 fetch 的时间
+fn last_fetch_time(repo: &Repository) -> Option<i64> {
+    // 链接工作树的 git 目录中有 commondir 文件指向主仓库的 git 目录
+    let git_dir = repo.path();
+    let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+        .map(|dir| git_dir.join(dir.trim()))
+        .unwrap_or_else(|_| git_dir.to_path_buf());
+    let modified = std::fs::metadata(common_dir.join("FETCH_HEAD"))
+        .and_then(|m| m.modified())
+        .ok()?;
+    let secs = modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(secs as i64)
+}
+
 // 获取远程仓库
 #[tauri::command]
 pub fn git_get_remotes(path: String) -> Result<Vec<GitRemote>, String> {
@@ -496,3 +676,112 @@ pub fn git_commit(path: String, message: String) -> Result<String, String> {
 
     Ok(commit_id.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, init_with_remote, temp_dir};
+    use std::fs;
+
+
+    /// Push the current branch to `origin` and make it the upstream
+    fn push_upstream(repo: &Repository) -> String {
+        let head = repo.head().unwrap();
+        let branch = head.shorthand().unwrap().to_string();
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        repo.find_remote("origin")
+            .unwrap()
+            .push(&[refspec.as_str()], None)
+            .unwrap();
+        // push 不会更新远程跟踪分支，这里通过 fetch 建立
+        repo.find_remote("origin")
+            .unwrap()
+            .fetch(&[] as &[&str], None, None)
+            .unwrap();
+        repo.find_branch(&branch, BranchType::Local)
+            .unwrap()
+            .set_upstream(Some(&format!("origin/{}", branch)))
+            .unwrap();
+        branch
+    }
+
+    #[test]
+    fn test_get_tracking_unborn_branch() {
+        let dir = temp_dir("git", "unborn");
+        let repo = init_with_remote(&dir);
+        let initial = repo
+            .find_reference("HEAD")
+            .unwrap()
+            .symbolic_target()
+            .unwrap()
+            .trim_start_matches("refs/heads/")
+            .to_string();
+
+        let tracking = get_tracking(&repo).unwrap();
+        assert_eq!(tracking.state, GitHeadState::Unborn);
+        assert_eq!(tracking.branch, initial);
+        assert_eq!(tracking.upstream, None);
+        assert_eq!(tracking.ahead_behind, None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_tracking_no_upstream_and_detached() {
+        let dir = temp_dir("git", "no-upstream");
+        let repo = init_with_remote(&dir);
+        let first = commit_file(&repo, "a.txt", "a\n");
+        commit_file(&repo, "b.txt", "b\n");
+
+        let tracking = get_tracking(&repo).unwrap();
+        assert_eq!(tracking.state, GitHeadState::NoUpstream);
+        assert_eq!(tracking.branch, repo.head().unwrap().shorthand().unwrap());
+        assert_eq!(tracking.upstream_ref, None);
+        assert_eq!(tracking.ahead_behind, None);
+
+        repo.set_head_detached(first).unwrap();
+        let tracking = get_tracking(&repo).unwrap();
+        assert_eq!(tracking.state, GitHeadState::Detached);
+        assert_eq!(tracking.branch, "HEAD");
+        assert_eq!(tracking.upstream, None);
+        assert_eq!(tracking.ahead_behind, None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_tracking_ahead_behind_and_upstream_gone() {
+        let dir = temp_dir("git", "tracking");
+        let repo = init_with_remote(&dir);
+        commit_file(&repo, "a.txt", "a\n");
+        let branch = push_upstream(&repo);
+        let upstream_ref = format!("refs/remotes/origin/{}", branch);
+
+        let tracking = get_tracking(&repo).unwrap();
+        assert_eq!(tracking.state, GitHeadState::Tracking);
+        assert_eq!(tracking.branch, branch);
+        assert_eq!(tracking.upstream.as_deref(), Some(format!("origin/{}", branch).as_str()));
+        assert_eq!(tracking.upstream_ref.as_deref(), Some(upstream_ref.as_str()));
+        assert_eq!(tracking.ahead_behind, Some((0, 0)));
+
+        commit_file(&repo, "b.txt", "b\n");
+        assert_eq!(get_tracking(&repo).unwrap().ahead_behind, Some((1, 0)));
+
+        // 推送后回退本地分支：落后上游一个提交
+        push_upstream(&repo);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let parent = head.parent(0).unwrap();
+        repo.reset(parent.as_object(), git2::ResetType::Hard, None)
+            .unwrap();
+        assert_eq!(get_tracking(&repo).unwrap().ahead_behind, Some((0, 1)));
+
+        // 远程分支被删除并 prune 后，上游配置仍在
+        repo.find_reference(&upstream_ref).unwrap().delete().unwrap();
+        let tracking = get_tracking(&repo).unwrap();
+        assert_eq!(tracking.state, GitHeadState::UpstreamGone);
+        assert_eq!(tracking.upstream.as_deref(), Some(format!("origin/{}", branch).as_str()));
+        assert_eq!(tracking.ahead_behind, None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
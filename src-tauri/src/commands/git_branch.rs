@@ -0,0 +1,723 @@
+// Branch management: create / delete / rename / upstream, and checkout of local or
+// remote branches with the `CheckoutMode` handling of local changes
+
+use crate::commands::git::{GitBranch, apply_stash, branch_info};
+use git2::build::CheckoutBuilder;
+use git2::{
+    Branch, BranchType, CheckoutNotificationType, Commit, ErrorCode, Oid, Repository, StashFlags,
+    StatusOptions,
+};
+use serde::{Deserialize, Serialize};
+
+/// 工作区有改动时 `git_checkout_branch` 的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutMode {
+    /// 已跟踪文件有任何改动都拒绝切换；未跟踪文件只在会被覆盖时阻止
+    #[default]
+    Strict,
+    /// 带着与目标分支不冲突的改动切换（safe checkout）
+    Carry,
+    /// 暂存改动、切换分支，再恢复暂存的改动
+    AutoStash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitCheckoutStatus {
+    /// 已切换到目标分支
+    CheckedOut,
+    /// 没有切换，`conflicts` 中的路径阻止了检出
+    Blocked,
+    /// 已切换，但恢复自动暂存的改动时产生冲突；暂存项保留在 stash 中
+    StashConflicts,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitCheckoutResult {
+    pub branch: String,
+    pub status: GitCheckoutStatus,
+    pub conflicts: Vec<String>,
+    /// AutoStash 模式下是否创建过 stash
+    pub stashed: bool,
+}
+
+impl GitCheckoutResult {
+    fn new(branch: &str, status: GitCheckoutStatus, conflicts: Vec<String>, stashed: bool) -> Self {
+        Self {
+            branch: branch.to_string(),
+            status,
+            conflicts,
+            stashed,
+        }
+    }
+}
+
+fn open_repo(path: &str) -> Result<Repository, String> {
+    Repository::open(path).map_err(|e| e.message().to_string())
+}
+
+fn validate_branch_name(name: &str) -> Result<(), String> {
+    let valid = Branch::name_is_valid(name).map_err(|e| e.message().to_string())?;
+    if !valid || name == "HEAD" {
+        return Err(format!("无效的分支名: {}", name));
+    }
+    Ok(())
+}
+
+fn find_local<'r>(repo: &'r Repository, name: &str) -> Result<Branch<'r>, String> {
+    repo.find_branch(name, BranchType::Local)
+        .map_err(|_| format!("本地分支 {} 不存在", name))
+}
+
+fn local_info(branch: &Branch) -> Result<GitBranch, String> {
+    branch_info(branch, BranchType::Local)?.ok_or_else(|| "Invalid branch name".to_string())
+}
+
+/// Any revision `git rev-parse` understands: hash, branch, tag, `HEAD~2`, ...
+fn resolve_commit<'r>(repo: &'r Repository, rev: Option<&str>) -> Result<Commit<'r>, String> {
+    let rev = rev.map(str::trim).filter(|r| !r.is_empty()).unwrap_or("HEAD");
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("无法解析 {}: {}", rev, e.message()))
+}
+
+/// Whether the branch tip is contained in its upstream, or in HEAD when it has none
+/// (the same rule `git branch -d` uses)
+fn is_merged(repo: &Repository, branch: &Branch) -> Result<bool, String> {
+    let tip = branch
+        .get()
+        .target()
+        .ok_or_else(|| "Branch does not point to a commit".to_string())?;
+    let base = match branch.upstream() {
+        Ok(upstream) => upstream.get().target(),
+        Err(_) => repo.head().ok().and_then(|head| head.target()),
+    };
+    let Some(base) = base else {
+        return Ok(false);
+    };
+
+    Ok(base == tip
+        || repo
+            .graph_descendant_of(base, tip)
+            .map_err(|e| e.message().to_string())?)
+}
+
+// 已跟踪文件中有改动（暂存或未暂存）的路径
+fn tracked_changes(repo: &Repository) -> Result<Vec<String>, String> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(false).exclude_submodules(true);
+
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect())
+}
+
+// 试运行 safe checkout，收集会被覆盖的本地改动或未跟踪文件
+fn checkout_conflicts(repo: &Repository, target: &git2::Object) -> Result<Vec<String>, String> {
+    let mut conflicts = Vec::new();
+    let result = {
+        let mut builder = CheckoutBuilder::new();
+        builder
+            .safe()
+            .dry_run()
+            .notify_on(CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    conflicts.push(path.to_string_lossy().to_string());
+                }
+                true
+            });
+        repo.checkout_tree(target, Some(&mut builder))
+    };
+
+    match result {
+        Ok(()) => Ok(conflicts),
+        Err(e) if e.code() == ErrorCode::Conflict => Ok(conflicts),
+        Err(e) => Err(e.message().to_string()),
+    }
+}
+
+fn switch_to(repo: &Repository, target: &git2::Object, branch_ref: &str) -> Result<(), String> {
+    repo.checkout_tree(target, Some(CheckoutBuilder::new().safe()))
+        .map_err(|e| e.message().to_string())?;
+    repo.set_head(branch_ref).map_err(|e| e.message().to_string())
+}
+
+// 没有冲突时切换到目标提交；有冲突时不做任何改动，返回冲突路径
+fn try_switch(repo: &Repository, target_id: Oid, branch_ref: &str) -> Result<Vec<String>, String> {
+    let target = repo
+        .find_object(target_id, None)
+        .map_err(|e| e.message().to_string())?;
+    let conflicts = checkout_conflicts(repo, &target)?;
+    if conflicts.is_empty() {
+        switch_to(repo, &target, branch_ref)?;
+    }
+    Ok(conflicts)
+}
+
+fn kept_in_stash(error: &str) -> String {
+    format!("{}；自动暂存的改动保留在 stash@{{0}} 中", error)
+}
+
+// 留在原分支上，把自动暂存的改动原样放回
+fn restore_auto_stash(repo: &mut Repository) -> Result<(), String> {
+    match apply_stash(repo, 0, true, true) {
+        Ok(conflicts) if conflicts.is_empty() => Ok(()),
+        Ok(conflicts) => Err(kept_in_stash(&format!(
+            "恢复自动暂存的改动时冲突: {}",
+            conflicts.join(", ")
+        ))),
+        Err(e) => Err(kept_in_stash(&format!("恢复自动暂存的改动失败: {}", e))),
+    }
+}
+
+fn checkout_local_branch(
+    repo: &mut Repository,
+    branch_name: &str,
+    mode: CheckoutMode,
+) -> Result<GitCheckoutResult, String> {
+    use GitCheckoutStatus::{Blocked, CheckedOut, StashConflicts};
+
+    // 查找分支
+    let branch = repo
+        .find_branch(branch_name, BranchType::Local)
+        .map_err(|e| e.message().to_string())?;
+    let branch_ref = branch
+        .get()
+        .name()
+        .ok_or_else(|| "Invalid branch name".to_string())?
+        .to_string();
+    let target_id = branch
+        .get()
+        .peel_to_commit()
+        .map_err(|e| e.message().to_string())?
+        .id();
+    drop(branch);
+
+    let changes = tracked_changes(repo)?;
+    if mode == CheckoutMode::Strict && !changes.is_empty() {
+        return Ok(GitCheckoutResult::new(branch_name, Blocked, changes, false));
+    }
+
+    let stashed = mode == CheckoutMode::AutoStash && !changes.is_empty();
+    if stashed {
+        let signature = repo.signature().map_err(|e| e.message().to_string())?;
+        repo.stash_save(
+            &signature,
+            &format!("auto-stash before checkout {}", branch_name),
+            Some(StashFlags::DEFAULT),
+        )
+        .map_err(|e| e.message().to_string())?;
+    }
+
+    // 暂存之后的任何失败都要先把改动放回去，不能让它们只留在 stash 里
+    let conflicts = match try_switch(repo, target_id, &branch_ref) {
+        Ok(conflicts) => conflicts,
+        Err(e) => {
+            if stashed {
+                restore_auto_stash(repo).map_err(|restore| format!("{}；{}", e, restore))?;
+            }
+            return Err(e);
+        }
+    };
+    if !conflicts.is_empty() {
+        if stashed {
+            restore_auto_stash(repo)?;
+        }
+        return Ok(GitCheckoutResult::new(branch_name, Blocked, conflicts, stashed));
+    }
+
+    if stashed {
+        let conflicts = apply_stash(repo, 0, false, true).map_err(|e| kept_in_stash(&e))?;
+        if !conflicts.is_empty() {
+            return Ok(GitCheckoutResult::new(branch_name, StashConflicts, conflicts, true));
+        }
+    }
+
+    Ok(GitCheckoutResult::new(branch_name, CheckedOut, Vec::new(), stashed))
+}
+
+/// Create a local branch from `remote_branch` (e.g. `origin/feature`), track it and
+/// check it out. An existing local branch already tracking it is simply checked out.
+fn checkout_remote_branch(
+    repo: &mut Repository,
+    remote_branch: &str,
+    local_name: Option<&str>,
+    mode: CheckoutMode,
+) -> Result<GitCheckoutResult, String> {
+    let remote = repo
+        .find_branch(remote_branch, BranchType::Remote)
+        .map_err(|_| format!("远程分支 {} 不存在", remote_branch))?;
+    let remote_ref = remote
+        .get()
+        .name()
+        .ok_or_else(|| "Invalid branch name".to_string())?;
+    let remote_name = repo
+        .branch_remote_name(remote_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .ok_or_else(|| format!("无法确定 {} 所属的远程仓库", remote_branch))?;
+
+    let local_name = local_name
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .or_else(|| remote_branch.strip_prefix(&format!("{}/", remote_name)))
+        .unwrap_or(remote_branch)
+        .to_string();
+    validate_branch_name(&local_name)?;
+
+    // 同名本地分支存在时：是否正跟踪这个远程分支
+    let existing_tracks = repo
+        .find_branch(&local_name, BranchType::Local)
+        .ok()
+        .map(|existing| {
+            existing
+                .upstream()
+                .ok()
+                .and_then(|u| u.name().ok().flatten().map(str::to_string))
+                .is_some_and(|u| u == remote_branch)
+        });
+    match existing_tracks {
+        Some(false) => {
+            return Err(format!(
+                "本地分支 {} 已存在，且未跟踪 {}",
+                local_name, remote_branch
+            ));
+        }
+        Some(true) => {
+            drop(remote);
+            return checkout_local_branch(repo, &local_name, mode);
+        }
+        None => {}
+    }
+
+    let commit = remote
+        .get()
+        .peel_to_commit()
+        .map_err(|e| e.message().to_string())?;
+    let mut branch = repo
+        .branch(&local_name, &commit, false)
+        .map_err(|e| e.message().to_string())?;
+    if let Err(e) = branch.set_upstream(Some(remote_branch)) {
+        let _ = branch.delete();
+        return Err(e.message().to_string());
+    }
+    drop((branch, commit, remote));
+
+    // 没能切换过去时删除刚创建的分支，不留下半成品
+    let result = checkout_local_branch(repo, &local_name, mode);
+    let switched = result
+        .as_ref()
+        .is_ok_and(|r| r.status != GitCheckoutStatus::Blocked);
+    if !switched && let Ok(mut branch) = repo.find_branch(&local_name, BranchType::Local) {
+        let _ = branch.delete();
+    }
+    result
+}
+
+// 切换分支；远程分支（如 origin/feature）会创建同名本地跟踪分支后再切换
+#[tauri::command]
+pub fn git_checkout_branch(
+    path: String,
+    branch_name: String,
+    mode: Option<CheckoutMode>,
+) -> Result<GitCheckoutResult, String> {
+    let mut repo = open_repo(&path)?;
+    let mode = mode.unwrap_or_default();
+
+    if repo.find_branch(&branch_name, BranchType::Local).is_err()
+        && repo.find_branch(&branch_name, BranchType::Remote).is_ok()
+    {
+        return checkout_remote_branch(&mut repo, &branch_name, None, mode);
+    }
+
+    checkout_local_branch(&mut repo, &branch_name, mode)
+}
+
+// 从任意提交或引用创建分支
+#[tauri::command]
+pub fn git_create_branch(
+    path: String,
+    name: String,
+    start_point: Option<String>,
+    checkout: Option<bool>,
+) -> Result<GitBranch, String> {
+    let mut repo = open_repo(&path)?;
+    validate_branch_name(&name)?;
+    if repo.find_branch(&name, BranchType::Local).is_ok() {
+        return Err(format!("分支 {} 已存在", name));
+    }
+
+    let commit = resolve_commit(&repo, start_point.as_deref())?;
+    repo.branch(&name, &commit, false)
+        .map_err(|e| e.message().to_string())?;
+    drop(commit);
+
+    // 与 `git switch -c` 一样带着不冲突的改动切换过去
+    if checkout.unwrap_or(false) {
+        let result = checkout_local_branch(&mut repo, &name, CheckoutMode::Carry);
+        let error = match result {
+            Ok(r) if r.status == GitCheckoutStatus::Blocked => {
+                Some(format!("以下文件与新分支冲突: {}", r.conflicts.join(", ")))
+            }
+            Ok(_) => None,
+            Err(e) => Some(e),
+        };
+        if let Some(error) = error {
+            let _ = find_local(&repo, &name)?.delete();
+            return Err(error);
+        }
+    }
+
+    local_info(&find_local(&repo, &name)?)
+}
+
+// 删除本地分支；未合并的分支需要 force
+#[tauri::command]
+pub fn git_delete_branch(path: String, name: String, force: Option<bool>) -> Result<(), String> {
+    let repo = open_repo(&path)?;
+    let mut branch = find_local(&repo, &name)?;
+
+    if branch.is_head() {
+        return Err(format!("不能删除当前所在的分支 {}", name));
+    }
+    if !force.unwrap_or(false) && !is_merged(&repo, &branch)? {
+        return Err(format!("分支 {} 尚未合并，如确定要删除请使用强制删除", name));
+    }
+
+    branch.delete().map_err(|e| e.message().to_string())
+}
+
+// 重命名本地分支；上游配置随分支一起迁移
+#[tauri::command]
+pub fn git_rename_branch(
+    path: String,
+    old_name: String,
+    new_name: String,
+    force: Option<bool>,
+) -> Result<GitBranch, String> {
+    let repo = open_repo(&path)?;
+    validate_branch_name(&new_name)?;
+    let mut branch = find_local(&repo, &old_name)?;
+
+    let renamed = branch
+        .rename(&new_name, force.unwrap_or(false))
+        .map_err(|e| e.message().to_string())?;
+    local_info(&renamed)
+}
+
+// 设置上游分支（如 origin/main），传 None 取消跟踪
+#[tauri::command]
+pub fn git_set_upstream(
+    path: String,
+    name: String,
+    upstream: Option<String>,
+) -> Result<GitBranch, String> {
+    let repo = open_repo(&path)?;
+    let mut branch = find_local(&repo, &name)?;
+
+    let upstream = upstream.filter(|u| !u.trim().is_empty());
+    if let Some(upstream) = &upstream
+        && repo.find_branch(upstream, BranchType::Remote).is_err()
+    {
+        return Err(format!("远程分支 {} 不存在", upstream));
+    }
+
+    branch
+        .set_upstream(upstream.as_deref())
+        .map_err(|e| e.message().to_string())?;
+    local_info(&find_local(&repo, &name)?)
+}
+
+// 检出远程分支为新的本地跟踪分支
+#[tauri::command]
+pub fn git_checkout_remote_branch(
+    path: String,
+    remote_branch: String,
+    local_name: Option<String>,
+    mode: Option<CheckoutMode>,
+) -> Result<GitCheckoutResult, String> {
+    let mut repo = open_repo(&path)?;
+    checkout_remote_branch(
+        &mut repo,
+        &remote_branch,
+        local_name.as_deref(),
+        mode.unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, init_with_remote, signature, temp_dir};
+    use std::fs;
+    use std::path::PathBuf;
+
+
+    /// Commit `name` onto `refs/heads/<branch>` without touching the work tree;
+    /// a missing branch starts from HEAD
+    fn commit_to_branch(repo: &Repository, branch: &str, name: &str, content: &str) -> Oid {
+        let parent = match repo.find_branch(branch, BranchType::Local) {
+            Ok(b) => b.get().peel_to_commit().unwrap(),
+            Err(_) => repo.head().unwrap().peel_to_commit().unwrap(),
+        };
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        let mut builder = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+        builder.insert(name, blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let refname = format!("refs/heads/{}", branch);
+        let sig = signature();
+        repo.commit(Some(&refname), &sig, &sig, name, &tree, &[&parent])
+            .unwrap()
+    }
+
+    /// Work repository whose `origin` (a bare repository) has the initial branch and
+    /// `feature`; locally only `origin/feature` exists
+    fn setup(name: &str) -> (PathBuf, Repository) {
+        let dir = temp_dir("git-branch", name);
+        let repo = init_with_remote(&dir);
+        commit_file(&repo, "a.txt", "a\n");
+        commit_to_branch(&repo, "feature", "feature.txt", "feature\n");
+
+        let main = current(&repo);
+        let specs = [
+            format!("refs/heads/{0}:refs/heads/{0}", main),
+            "refs/heads/feature:refs/heads/feature".to_string(),
+        ];
+        let mut remote = repo.find_remote("origin").unwrap();
+        remote.push(&specs, None).unwrap();
+        remote.fetch(&[] as &[&str], None, None).unwrap();
+        drop(remote);
+        repo.find_branch("feature", BranchType::Local)
+            .unwrap()
+            .delete()
+            .unwrap();
+        (dir, repo)
+    }
+
+    fn current(repo: &Repository) -> String {
+        repo.head().unwrap().shorthand().unwrap().to_string()
+    }
+
+    fn workdir(repo: &Repository) -> String {
+        repo.workdir().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_checkout_branch_from_remote_name() {
+        let (dir, repo) = setup("checkout-remote");
+
+        // 传入 origin/feature 时创建跟踪它的本地分支 feature
+        let result = git_checkout_branch(workdir(&repo), "origin/feature".to_string(), None)
+            .unwrap();
+        assert_eq!(result.status, GitCheckoutStatus::CheckedOut);
+        assert_eq!(result.branch, "feature");
+        assert_eq!(current(&repo), "feature");
+        let feature = find_local(&repo, "feature").unwrap();
+        assert_eq!(local_info(&feature).unwrap().upstream.as_deref(), Some("origin/feature"));
+        assert!(repo.workdir().unwrap().join("feature.txt").exists());
+
+        // 再次检出时复用已跟踪的本地分支
+        let main = repo.branches(Some(BranchType::Local)).unwrap().flatten().find_map(|(b, _)| {
+            let name = b.name().ok().flatten()?.to_string();
+            (name != "feature").then_some(name)
+        });
+        git_checkout_branch(workdir(&repo), main.unwrap(), None).unwrap();
+        let result = git_checkout_branch(workdir(&repo), "origin/feature".to_string(), None)
+            .unwrap();
+        assert_eq!(result.status, GitCheckoutStatus::CheckedOut);
+        assert_eq!(current(&repo), "feature");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkout_remote_branch_removes_branch_when_blocked() {
+        let (dir, repo) = setup("checkout-blocked");
+        let main = current(&repo);
+        // 未跟踪文件会被远程分支中的同名文件覆盖
+        let local_file = repo.workdir().unwrap().join("feature.txt");
+        fs::write(&local_file, "local\n").unwrap();
+
+        let result = git_checkout_remote_branch(
+            workdir(&repo),
+            "origin/feature".to_string(),
+            None,
+            Some(CheckoutMode::Carry),
+        )
+        .unwrap();
+        assert_eq!(result.status, GitCheckoutStatus::Blocked);
+        assert_eq!(result.conflicts, vec!["feature.txt"]);
+        assert!(repo.find_branch("feature", BranchType::Local).is_err());
+        assert_eq!(current(&repo), main);
+        assert_eq!(fs::read_to_string(&local_file).unwrap(), "local\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_is_merged_and_delete_branch() {
+        let (dir, repo) = setup("merged");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("merged", &head, false).unwrap();
+        commit_to_branch(&repo, "ahead", "b.txt", "b\n");
+        // 有上游时以上游为准，即使 HEAD 不包含该分支
+        let remote = repo.find_branch("origin/feature", BranchType::Remote).unwrap();
+        let mut tracking = repo
+            .branch("tracking", &remote.get().peel_to_commit().unwrap(), false)
+            .unwrap();
+        tracking.set_upstream(Some("origin/feature")).unwrap();
+
+        assert!(is_merged(&repo, &find_local(&repo, "merged").unwrap()).unwrap());
+        assert!(!is_merged(&repo, &find_local(&repo, "ahead").unwrap()).unwrap());
+        assert!(is_merged(&repo, &find_local(&repo, "tracking").unwrap()).unwrap());
+
+        let path = workdir(&repo);
+        let err = git_delete_branch(path.clone(), "ahead".to_string(), None).unwrap_err();
+        assert!(err.contains("尚未合并"), "{}", err);
+        assert!(git_delete_branch(path.clone(), current(&repo), Some(true)).is_err());
+        git_delete_branch(path.clone(), "ahead".to_string(), Some(true)).unwrap();
+        git_delete_branch(path, "merged".to_string(), None).unwrap();
+        assert!(repo.find_branch("ahead", BranchType::Local).is_err());
+        assert!(repo.find_branch("merged", BranchType::Local).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_keeps_upstream_and_unset_upstream() {
+        let (dir, repo) = setup("upstream");
+        let path = workdir(&repo);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("topic", &head, false).unwrap();
+
+        let set = |upstream: Option<&str>| {
+            git_set_upstream(path.clone(), "topic".to_string(), upstream.map(str::to_string))
+        };
+        let info = set(Some("origin/feature")).unwrap();
+        assert_eq!(info.upstream.as_deref(), Some("origin/feature"));
+        assert!(set(Some("origin/nope")).is_err());
+
+        let renamed =
+            git_rename_branch(path.clone(), "topic".to_string(), "renamed".to_string(), None)
+                .unwrap();
+        assert_eq!(renamed.name, "renamed");
+        assert_eq!(renamed.upstream.as_deref(), Some("origin/feature"));
+        let config = repo.config().unwrap().snapshot().unwrap();
+        assert!(config.get_str("branch.topic.remote").is_err());
+        assert_eq!(config.get_str("branch.renamed.merge").unwrap(), "refs/heads/feature");
+
+        let info = git_set_upstream(path, "renamed".to_string(), None).unwrap();
+        assert_eq!(info.upstream, None);
+        let config = repo.config().unwrap().snapshot().unwrap();
+        assert!(config.get_str("branch.renamed.remote").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn stash_count(repo: &mut Repository) -> usize {
+        let mut count = 0;
+        repo.stash_foreach(|_, _, _| {
+            count += 1;
+            true
+        })
+        .unwrap();
+        count
+    }
+
+    fn checkout(repo: &Repository, branch: &str, mode: CheckoutMode) -> GitCheckoutResult {
+        git_checkout_branch(workdir(repo), branch.to_string(), Some(mode)).unwrap()
+    }
+
+    #[test]
+    fn test_checkout_strict_blocks_tracked_changes() {
+        let (dir, repo) = setup("strict");
+        let main = current(&repo);
+        commit_to_branch(&repo, "side", "b.txt", "b\n");
+        let a = repo.workdir().unwrap().join("a.txt");
+        fs::write(&a, "changed\n").unwrap();
+
+        // 目标分支不涉及 a.txt，严格模式仍然拒绝
+        let result = checkout(&repo, "side", CheckoutMode::Strict);
+        assert_eq!(result.status, GitCheckoutStatus::Blocked);
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert!(!result.stashed);
+        assert_eq!(current(&repo), main);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "changed\n");
+
+        // 只有不冲突的未跟踪文件时可以切换
+        fs::write(&a, "a\n").unwrap();
+        fs::write(repo.workdir().unwrap().join("notes.txt"), "untracked\n").unwrap();
+        let result = checkout(&repo, "side", CheckoutMode::Strict);
+        assert_eq!(result.status, GitCheckoutStatus::CheckedOut);
+        assert_eq!(current(&repo), "side");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkout_carry_blocks_conflicting_file() {
+        let (dir, repo) = setup("carry");
+        let main = current(&repo);
+        commit_to_branch(&repo, "other", "a.txt", "other\n");
+        commit_to_branch(&repo, "side", "b.txt", "b\n");
+        let a = repo.workdir().unwrap().join("a.txt");
+        fs::write(&a, "changed\n").unwrap();
+
+        let result = checkout(&repo, "other", CheckoutMode::Carry);
+        assert_eq!(result.status, GitCheckoutStatus::Blocked);
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert_eq!(current(&repo), main);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "changed\n");
+
+        // 不冲突的改动随分支带走
+        let result = checkout(&repo, "side", CheckoutMode::Carry);
+        assert_eq!(result.status, GitCheckoutStatus::CheckedOut);
+        assert!(!result.stashed);
+        assert_eq!(current(&repo), "side");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "changed\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkout_auto_stash_restores_changes() {
+        let (dir, mut repo) = setup("autostash");
+        commit_to_branch(&repo, "side", "b.txt", "b\n");
+        let a = repo.workdir().unwrap().join("a.txt");
+        fs::write(&a, "changed\n").unwrap();
+
+        let result = checkout(&repo, "side", CheckoutMode::AutoStash);
+        assert_eq!(result.status, GitCheckoutStatus::CheckedOut);
+        assert!(result.stashed);
+        assert_eq!(current(&repo), "side");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "changed\n");
+        assert!(repo.workdir().unwrap().join("b.txt").exists());
+        assert_eq!(stash_count(&mut repo), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_checkout_auto_stash_conflict_keeps_stash() {
+        let (dir, mut repo) = setup("autostash-conflict");
+        commit_to_branch(&repo, "other", "a.txt", "other\n");
+        let a = repo.workdir().unwrap().join("a.txt");
+        fs::write(&a, "changed\n").unwrap();
+
+        // 切换成功，但恢复改动时与目标分支冲突
+        let result = checkout(&repo, "other", CheckoutMode::AutoStash);
+        assert_eq!(result.status, GitCheckoutStatus::StashConflicts);
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert!(result.stashed);
+        assert_eq!(current(&repo), "other");
+        assert_eq!(stash_count(&mut repo), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
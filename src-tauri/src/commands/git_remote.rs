@@ -0,0 +1,1012 @@
+// Remote git operations: fetch / pull / push through git2 `RemoteCallbacks`
+// Credentials come from the SSH agent, vault `ssh` entries, ~/.ssh key files and
+// HTTPS tokens stored as `git_token` vault entries whose `url` points at the git host
+
+use crate::commands::git::conflicted_paths;
+use crate::commands::handover::ProgressTracker;
+use crate::commands::projects::find_project_by_path;
+use crate::commands::vault::{
+    VAULT_ENTRY_COLUMNS, VaultEntry, VaultSessions, vault_entry_from_row,
+};
+use crate::db;
+use git2::build::CheckoutBuilder;
+use git2::{
+    AnnotatedCommit, AutotagOption, BranchType, Config, Cred, CredentialType, ErrorCode,
+    FetchOptions, PushOptions, Rebase, RemoteCallbacks, Repository, Signature, StatusOptions,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use tauri::Manager;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Event carrying `ExportProgress` payloads while objects are transferred
+pub const GIT_PROGRESS_EVENT: &str = "git-progress";
+
+const DEFAULT_REMOTE: &str = "origin";
+
+/// Vault category of HTTPS tokens; other entries are never sent to a git host,
+/// even when they carry a `url`
+pub const GIT_TOKEN_CATEGORY: &str = "git_token";
+
+/// Key files tried after the agent and the vault, in ssh's own order of preference
+const DEFAULT_KEY_FILES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Receives `(phase, current, total)` from the transfer callbacks
+type ProgressFn<'p> = &'p mut dyn FnMut(&'static str, usize, usize);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitFetchResult {
+    pub remote: String,
+    pub received_objects: usize,
+    pub received_bytes: usize,
+    /// Remote-tracking branches and tags moved by the fetch
+    pub updated_refs: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullMode {
+    /// Only move the branch forward; diverged history is an error
+    #[default]
+    FastForward,
+    /// Replay local commits on top of the upstream branch
+    Rebase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitPullOutcome {
+    UpToDate,
+    FastForward,
+    Rebased,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitPullResult {
+    pub fetch: GitFetchResult,
+    pub outcome: GitPullOutcome,
+    /// Upstream commits the branch did not have before
+    pub new_commits: usize,
+    /// Local commits replayed by a rebase pull
+    pub rebased_commits: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitPushResult {
+    pub remote: String,
+    pub branch: String,
+    pub refspec: String,
+    pub upstream_set: bool,
+}
+
+/// HTTPS token from a vault entry; `param_key` doubles as the user name
+struct VaultToken {
+    host: String,
+    username: Option<String>,
+    secret: Zeroizing<String>,
+}
+
+/// Secrets read from the project's vault while it is unlocked
+#[derive(Default)]
+pub(crate) struct GitCredentials {
+    tokens: Vec<VaultToken>,
+    ssh_keys: Vec<Zeroizing<String>>,
+}
+
+impl GitCredentials {
+    /// Keep a decrypted entry as an SSH key or HTTPS token according to its category
+    fn add(&mut self, entry: &VaultEntry, value: Zeroizing<String>) {
+        if entry.category == "ssh" {
+            self.ssh_keys.push(value);
+        } else if entry.category == GIT_TOKEN_CATEGORY
+            && let Some(host) = entry.url.as_deref().and_then(url_host)
+        {
+            self.tokens.push(VaultToken {
+                host: host.to_string(),
+                username: entry.param_key.clone().filter(|k| !k.trim().is_empty()),
+                secret: value,
+            });
+        }
+    }
+}
+
+/// Position in the credential fallback chain. libgit2 calls the callback again
+/// after every rejected credential, so each source is offered once.
+#[derive(Default)]
+struct CredentialAttempts {
+    username: bool,
+    agent: bool,
+    vault_key: usize,
+    key_file: usize,
+    token: usize,
+    helper: bool,
+    default: bool,
+}
+
+// ---------------- 凭据 ----------------
+
+/// Host of `https://user@host:port/path`, `ssh://git@host/path` or scp-like `git@host:path`
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// Vault tokens are only ever sent over HTTPS
+fn is_https(url: &str) -> bool {
+    url.split_once("://")
+        .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case("https"))
+}
+
+fn default_key_files() -> Vec<PathBuf> {
+    let Some(ssh_dir) = dirs::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    DEFAULT_KEY_FILES
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn next_credential(
+    attempts: &mut CredentialAttempts,
+    credentials: &GitCredentials,
+    config: Option<&Config>,
+    url: &str,
+    username_from_url: Option<&str>,
+    allowed: CredentialType,
+) -> Result<Cred, git2::Error> {
+    let user = username_from_url.unwrap_or("git");
+
+    // SSH 先询问用户名
+    if allowed.contains(CredentialType::USERNAME) && !attempts.username {
+        attempts.username = true;
+        return Cred::username(user);
+    }
+
+    if allowed.contains(CredentialType::SSH_KEY) {
+        if !attempts.agent {
+            attempts.agent = true;
+            return Cred::ssh_key_from_agent(user);
+        }
+        if let Some(key) = credentials.ssh_keys.get(attempts.vault_key) {
+            attempts.vault_key += 1;
+            return Cred::ssh_key_from_memory(user, None, key, None);
+        }
+        if let Some(path) = default_key_files().get(attempts.key_file) {
+            attempts.key_file += 1;
+            return Cred::ssh_key(user, None, path, None);
+        }
+    }
+
+    if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+        // http:// 会以明文发送令牌，只交给凭据助手处理
+        let host = url_host(url).filter(|_| is_https(url));
+        while let Some(host) = host
+            && let Some(token) = credentials.tokens.get(attempts.token)
+        {
+            attempts.token += 1;
+            if host.eq_ignore_ascii_case(&token.host) {
+                let username = username_from_url
+                    .or(token.username.as_deref())
+                    .unwrap_or("git");
+                return Cred::userpass_plaintext(username, &token.secret);
+            }
+        }
+        if !attempts.helper {
+            attempts.helper = true;
+            if let Some(config) = config
+                && let Ok(cred) = Cred::credential_helper(config, url, username_from_url)
+            {
+                return Ok(cred);
+            }
+        }
+    }
+
+    if allowed.contains(CredentialType::DEFAULT) && !attempts.default {
+        attempts.default = true;
+        return Cred::default();
+    }
+
+    Err(git2::Error::from_str(&format!("没有可用的凭据: {}", url)))
+}
+
+/// Decrypt the `ssh` and `git_token` entries of an unlocked project vault.
+/// A locked vault simply contributes nothing; agent and key files still work.
+async fn load_credentials(
+    app: &tauri::AppHandle,
+    pool: &SqlitePool,
+    project_id: i64,
+) -> Result<GitCredentials, String> {
+    let sessions = app.state::<VaultSessions>();
+    let mut credentials = GitCredentials::default();
+    if !sessions.is_unlocked(project_id) {
+        return Ok(credentials);
+    }
+
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM vault_entries WHERE project_id = ? AND category IN ('ssh', ?)",
+        VAULT_ENTRY_COLUMNS
+    ))
+    .bind(project_id)
+    .bind(GIT_TOKEN_CATEGORY)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database query failed: {}", e))?;
+
+    for row in &rows {
+        let entry = vault_entry_from_row(row)?;
+        let Ok((value, mut notes)) = sessions.decrypt_unlocked_entry(&entry) else {
+            continue;
+        };
+        notes.zeroize();
+        credentials.add(&entry, Zeroizing::new(value));
+    }
+
+    Ok(credentials)
+}
+
+/// Credentials and default remote of the project registered at `path`, if any
+struct RemoteContext {
+    credentials: GitCredentials,
+    default_remote: Option<String>,
+}
+
+async fn remote_context(app: &tauri::AppHandle, path: &str) -> Result<RemoteContext, String> {
+    let pool = db::pool(app).await?;
+    let Some(project) = find_project_by_path(&pool, path).await? else {
+        return Ok(RemoteContext {
+            credentials: GitCredentials::default(),
+            default_remote: None,
+        });
+    };
+
+    Ok(RemoteContext {
+        credentials: load_credentials(app, &pool, project.id).await?,
+        default_remote: Some(project.settings.default_git_remote),
+    })
+}
+
+// ---------------- 远程操作 ----------------
+
+/// Requested remote, else the current branch's upstream remote, else the project default
+fn resolve_remote(
+    repo: &Repository,
+    requested: Option<String>,
+    default_remote: Option<&str>,
+) -> String {
+    if let Some(remote) = requested.filter(|r| !r.trim().is_empty()) {
+        return remote;
+    }
+    if let Ok(head) = repo.head()
+        && let Some(name) = head.name()
+        && let Ok(buf) = repo.branch_upstream_remote(name)
+        && let Some(remote) = buf.as_str()
+    {
+        return remote.to_string();
+    }
+    default_remote.unwrap_or(DEFAULT_REMOTE).to_string()
+}
+
+fn remote_callbacks<'a, 'p: 'a>(
+    config: Option<&'a Config>,
+    credentials: &'a GitCredentials,
+    attempts: &'a RefCell<CredentialAttempts>,
+    progress: &'a RefCell<ProgressFn<'p>>,
+) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        next_credential(
+            &mut attempts.borrow_mut(),
+            credentials,
+            config,
+            url,
+            username,
+            allowed,
+        )
+    });
+    callbacks.transfer_progress(move |stats| {
+        let mut progress = progress.borrow_mut();
+        if stats.received_objects() < stats.total_objects() {
+            progress(
+                "正在接收对象",
+                stats.received_objects(),
+                stats.total_objects(),
+            );
+        } else {
+            progress("正在处理差异", stats.indexed_deltas(), stats.total_deltas());
+        }
+        true
+    });
+    callbacks.push_transfer_progress(move |current, total, _bytes| {
+        (progress.borrow_mut())("正在推送对象", current, total);
+    });
+    callbacks
+}
+
+pub(crate) fn fetch_remote(
+    repo: &Repository,
+    remote_name: &str,
+    credentials: &GitCredentials,
+    progress: ProgressFn,
+) -> Result<GitFetchResult, String> {
+    let mut remote = repo
+        .find_remote(remote_name)
+        .map_err(|e| format!("远程仓库 {} 不存在: {}", remote_name, e.message()))?;
+
+    let config = repo.config().ok();
+    let attempts = RefCell::new(CredentialAttempts::default());
+    let progress = RefCell::new(progress);
+    let updated_refs = RefCell::new(Vec::new());
+
+    let mut callbacks = remote_callbacks(config.as_ref(), credentials, &attempts, &progress);
+    callbacks.update_tips(|name, _old, _new| {
+        updated_refs.borrow_mut().push(name.to_string());
+        true
+    });
+
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks)
+        .download_tags(AutotagOption::Auto);
+    // 空 refspec 使用远程配置的 fetch 规则，并写入 FETCH_HEAD
+    remote
+        .fetch::<&str>(&[], Some(&mut options), None)
+        .map_err(|e| e.message().to_string())?;
+    drop(options);
+
+    let stats = remote.stats();
+    Ok(GitFetchResult {
+        remote: remote_name.to_string(),
+        received_objects: stats.received_objects(),
+        received_bytes: stats.received_bytes(),
+        updated_refs: updated_refs.into_inner(),
+    })
+}
+
+// 与 git_checkout_branch 相同的检查，未跟踪文件不影响变基
+fn ensure_clean(repo: &Repository) -> Result<(), String> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(false).exclude_submodules(true);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    if !statuses.is_empty() {
+        return Err("有未提交的更改，请先提交或暂存".to_string());
+    }
+    Ok(())
+}
+
+/// Commit the current rebase step; `Ok(false)` when the patch is already upstream
+fn commit_rebase_step(
+    repo: &Repository,
+    rebase: &mut Rebase,
+    signature: &Signature,
+) -> Result<bool, String> {
+    let index = repo.index().map_err(|e| e.message().to_string())?;
+    if index.has_conflicts() {
+        return Err(format!(
+            "变基时发生冲突，已恢复到拉取前的状态: {}",
+            conflicted_paths(&index).join(", ")
+        ));
+    }
+    match rebase.commit(None, signature, None) {
+        Ok(_) => Ok(true),
+        Err(e) if e.code() == ErrorCode::Applied => Ok(false),
+        Err(e) => Err(e.message().to_string()),
+    }
+}
+
+/// Replay HEAD's commits onto `upstream`; any failure aborts the rebase
+fn rebase_onto(repo: &Repository, upstream: &AnnotatedCommit) -> Result<usize, String> {
+    let signature = repo.signature().map_err(|e| e.message().to_string())?;
+    let mut rebase = repo
+        .rebase(None, Some(upstream), None, None)
+        .map_err(|e| e.message().to_string())?;
+
+    let mut rebased = 0;
+    while let Some(operation) = rebase.next() {
+        let step = match operation {
+            Ok(_) => commit_rebase_step(repo, &mut rebase, &signature),
+            Err(e) => Err(e.message().to_string()),
+        };
+        match step {
+            Ok(true) => rebased += 1,
+            Ok(false) => {}
+            Err(e) => {
+                let _ = rebase.abort();
+                return Err(e);
+            }
+        }
+    }
+
+    rebase
+        .finish(Some(&signature))
+        .map_err(|e| e.message().to_string())?;
+    Ok(rebased)
+}
+
+pub(crate) fn pull_branch(
+    repo: &Repository,
+    remote_name: &str,
+    mode: PullMode,
+    credentials: &GitCredentials,
+    progress: ProgressFn,
+) -> Result<GitPullResult, String> {
+    if repo.head_detached().unwrap_or(false) {
+        return Err("HEAD 处于分离状态，无法拉取".to_string());
+    }
+    // 未出生的分支也能从符号引用拿到分支名
+    let head_ref = repo
+        .find_reference("HEAD")
+        .ok()
+        .and_then(|r| r.symbolic_target().map(str::to_string))
+        .ok_or_else(|| "无法确定当前分支".to_string())?;
+    let branch = head_ref.strip_prefix("refs/heads/").unwrap_or(&head_ref);
+
+    let fetch = fetch_remote(repo, remote_name, credentials, progress)?;
+
+    let upstream_ref = repo
+        .branch_upstream_name(&head_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("refs/remotes/{}/{}", remote_name, branch));
+    let upstream = repo
+        .find_reference(&upstream_ref)
+        .map_err(|_| format!("远程分支 {} 不存在", upstream_ref))?;
+    let upstream_commit = repo
+        .reference_to_annotated_commit(&upstream)
+        .map_err(|e| e.message().to_string())?;
+    let target = upstream_commit.id();
+
+    let (analysis, _) = repo
+        .merge_analysis(&[&upstream_commit])
+        .map_err(|e| e.message().to_string())?;
+
+    let mut result = GitPullResult {
+        fetch,
+        outcome: GitPullOutcome::UpToDate,
+        new_commits: 0,
+        rebased_commits: 0,
+    };
+    if analysis.is_up_to_date() {
+        return Ok(result);
+    }
+
+    if analysis.is_unborn() {
+        // 空分支：直接指向上游并检出
+        repo.reference(
+            &head_ref,
+            target,
+            false,
+            &format!("pull: from {}", upstream_ref),
+        )
+        .map_err(|e| e.message().to_string())?;
+        repo.checkout_head(Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| e.message().to_string())?;
+
+        let mut revwalk = repo.revwalk().map_err(|e| e.message().to_string())?;
+        revwalk.push(target).map_err(|e| e.message().to_string())?;
+        result.outcome = GitPullOutcome::FastForward;
+        result.new_commits = revwalk.count();
+        return Ok(result);
+    }
+
+    let local = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .ok_or_else(|| "HEAD does not point to a commit".to_string())?;
+    let (_, behind) = repo
+        .graph_ahead_behind(local, target)
+        .map_err(|e| e.message().to_string())?;
+    result.new_commits = behind;
+
+    if analysis.is_fast_forward() {
+        let commit = repo
+            .find_commit(target)
+            .map_err(|e| e.message().to_string())?;
+        // safe 模式在本地修改会被覆盖时报错，而不是丢弃修改
+        repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| e.message().to_string())?;
+        repo.find_reference(&head_ref)
+            .and_then(|mut r| {
+                r.set_target(target, &format!("pull: fast-forward to {}", upstream_ref))
+            })
+            .map_err(|e| e.message().to_string())?;
+        result.outcome = GitPullOutcome::FastForward;
+        return Ok(result);
+    }
+
+    match mode {
+        PullMode::FastForward => {
+            Err("本地分支与远程已分叉，无法快进，请使用变基方式拉取".to_string())
+        }
+        PullMode::Rebase => {
+            ensure_clean(repo)?;
+            result.rebased_commits = rebase_onto(repo, &upstream_commit)?;
+            result.outcome = GitPullOutcome::Rebased;
+            Ok(result)
+        }
+    }
+}
+
+pub(crate) fn push_branch(
+    repo: &Repository,
+    remote_name: &str,
+    branch: Option<&str>,
+    force: bool,
+    set_upstream: bool,
+    credentials: &GitCredentials,
+    progress: ProgressFn,
+) -> Result<GitPushResult, String> {
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => {
+            let head = repo.head().map_err(|e| e.message().to_string())?;
+            if !head.is_branch() {
+                return Err("HEAD 处于分离状态，请指定要推送的分支".to_string());
+            }
+            head.shorthand().unwrap_or("HEAD").to_string()
+        }
+    };
+    let mut local = repo
+        .find_branch(&branch, BranchType::Local)
+        .map_err(|e| e.message().to_string())?;
+    let local_ref = local
+        .get()
+        .name()
+        .ok_or_else(|| "Invalid branch name".to_string())?
+        .to_string();
+
+    let config = repo.config().ok();
+
+    // 已跟踪同一远程时推送到上游分支，否则推送到同名分支
+    let dest = repo
+        .branch_upstream_remote(&local_ref)
+        .ok()
+        .filter(|remote| remote.as_str() == Some(remote_name))
+        .and_then(|_| {
+            config
+                .as_ref()?
+                .get_string(&format!("branch.{}.merge", branch))
+                .ok()
+        })
+        .unwrap_or_else(|| local_ref.clone());
+    let refspec = format!("{}{}:{}", if force { "+" } else { "" }, local_ref, dest);
+
+    let mut remote = repo
+        .find_remote(remote_name)
+        .map_err(|e| format!("远程仓库 {} 不存在: {}", remote_name, e.message()))?;
+
+    let attempts = RefCell::new(CredentialAttempts::default());
+    let progress = RefCell::new(progress);
+    let rejected = RefCell::new(Vec::new());
+
+    let mut callbacks = remote_callbacks(config.as_ref(), credentials, &attempts, &progress);
+    callbacks.push_update_reference(|refname, status| {
+        if let Some(message) = status {
+            rejected
+                .borrow_mut()
+                .push(format!("{}: {}", refname, message));
+        }
+        Ok(())
+    });
+
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    remote
+        .push(&[refspec.as_str()], Some(&mut options))
+        .map_err(|e| e.message().to_string())?;
+    drop(options);
+
+    let rejected = rejected.into_inner();
+    if !rejected.is_empty() {
+        return Err(format!("推送被拒绝: {}", rejected.join("; ")));
+    }
+
+    if set_upstream {
+        // 推送成功后远程跟踪分支已更新，可以直接设为上游
+        let short = dest.strip_prefix("refs/heads/").unwrap_or(&dest);
+        local
+            .set_upstream(Some(&format!("{}/{}", remote_name, short)))
+            .map_err(|e| e.message().to_string())?;
+    }
+
+    Ok(GitPushResult {
+        remote: remote_name.to_string(),
+        branch,
+        refspec,
+        upstream_set: set_upstream,
+    })
+}
+
+// ---------------- 命令 ----------------
+
+#[tauri::command]
+pub async fn git_fetch(
+    app: tauri::AppHandle,
+    path: String,
+    remote: Option<String>,
+) -> Result<GitFetchResult, String> {
+    let context = remote_context(&app, &path).await?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = Repository::open(&path).map_err(|e| e.message().to_string())?;
+        let remote = resolve_remote(&repo, remote, context.default_remote.as_deref());
+
+        let mut tracker = ProgressTracker::with_event(&app, GIT_PROGRESS_EVENT, "正在获取", 0);
+        let result = fetch_remote(
+            &repo,
+            &remote,
+            &context.credentials,
+            &mut |action, current, total| tracker.update(action, current, total),
+        );
+        tracker.finish(&result, "获取完成", "获取失败");
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn git_pull(
+    app: tauri::AppHandle,
+    path: String,
+    mode: Option<PullMode>,
+    remote: Option<String>,
+) -> Result<GitPullResult, String> {
+    let context = remote_context(&app, &path).await?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = Repository::open(&path).map_err(|e| e.message().to_string())?;
+        let remote = resolve_remote(&repo, remote, context.default_remote.as_deref());
+
+        let mut tracker = ProgressTracker::with_event(&app, GIT_PROGRESS_EVENT, "正在拉取", 0);
+        let result = pull_branch(
+            &repo,
+            &remote,
+            mode.unwrap_or_default(),
+            &context.credentials,
+            &mut |action, current, total| tracker.update(action, current, total),
+        );
+        tracker.finish(&result, "拉取完成", "拉取失败");
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn git_push(
+    app: tauri::AppHandle,
+    path: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    set_upstream: Option<bool>,
+    force: Option<bool>,
+) -> Result<GitPushResult, String> {
+    let context = remote_context(&app, &path).await?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = Repository::open(&path).map_err(|e| e.message().to_string())?;
+        let remote = resolve_remote(&repo, remote, context.default_remote.as_deref());
+
+        let mut tracker = ProgressTracker::with_event(&app, GIT_PROGRESS_EVENT, "正在推送", 0);
+        let result = push_branch(
+            &repo,
+            &remote,
+            branch.as_deref(),
+            force.unwrap_or(false),
+            set_upstream.unwrap_or(false),
+            &context.credentials,
+            &mut |action, current, total| tracker.update(action, current, total),
+        );
+        tracker.finish(&result, "推送完成", "推送失败");
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, init_repo, temp_dir};
+    use std::fs;
+
+
+    fn branch_name(repo: &Repository) -> String {
+        repo.head().unwrap().shorthand().unwrap().to_string()
+    }
+
+    /// Bare "server" plus two working clones wired to it through a `file://` remote
+    fn setup(name: &str) -> (PathBuf, Repository, Repository) {
+        let dir = temp_dir("git-remote", name);
+        let bare = dir.join("remote.git");
+        Repository::init_bare(&bare).unwrap();
+
+        let first = init_repo(&dir.join("first"), Some(&bare));
+        commit_file(&first, "a.txt", "a\n");
+        let branch = branch_name(&first);
+        push_branch(
+            &first,
+            "origin",
+            Some(&branch),
+            false,
+            true,
+            &GitCredentials::default(),
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+
+        let second = init_repo(&dir.join("second"), Some(&bare));
+        (dir, first, second)
+    }
+
+    #[test]
+    fn push_then_pull_into_empty_clone() {
+        let (dir, first, second) = setup("clone");
+        let credentials = GitCredentials::default();
+        let branch = branch_name(&first);
+        assert!(
+            first
+                .branch_upstream_name(&format!("refs/heads/{}", branch))
+                .is_ok()
+        );
+
+        // 第二个仓库的 HEAD 与第一个仓库使用同一个初始分支名
+        let result = pull_branch(
+            &second,
+            "origin",
+            PullMode::FastForward,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(result.outcome, GitPullOutcome::FastForward);
+        assert_eq!(result.new_commits, 1);
+        assert!(second.workdir().unwrap().join("a.txt").exists());
+
+        commit_file(&first, "b.txt", "b\n");
+        push_branch(
+            &first,
+            "origin",
+            None,
+            false,
+            false,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+
+        let mut phases = Vec::new();
+        let result = pull_branch(
+            &second,
+            "origin",
+            PullMode::FastForward,
+            &credentials,
+            &mut |phase, _, _| phases.push(phase),
+        )
+        .unwrap();
+        assert_eq!(result.outcome, GitPullOutcome::FastForward);
+        assert_eq!(result.new_commits, 1);
+        assert!(!result.fetch.updated_refs.is_empty());
+        assert!(!phases.is_empty());
+        assert!(second.workdir().unwrap().join("b.txt").exists());
+
+        let result = pull_branch(
+            &second,
+            "origin",
+            PullMode::FastForward,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(result.outcome, GitPullOutcome::UpToDate);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn diverged_pull_needs_rebase() {
+        let (dir, first, second) = setup("rebase");
+        let credentials = GitCredentials::default();
+        pull_branch(
+            &second,
+            "origin",
+            PullMode::FastForward,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+
+        commit_file(&first, "b.txt", "b\n");
+        push_branch(
+            &first,
+            "origin",
+            None,
+            false,
+            false,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        commit_file(&second, "c.txt", "c\n");
+
+        let err = pull_branch(
+            &second,
+            "origin",
+            PullMode::FastForward,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap_err();
+        assert!(err.contains("无法快进"));
+
+        let result = pull_branch(
+            &second,
+            "origin",
+            PullMode::Rebase,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(result.outcome, GitPullOutcome::Rebased);
+        assert_eq!(result.rebased_commits, 1);
+        let workdir = second.workdir().unwrap();
+        assert!(workdir.join("b.txt").exists() && workdir.join("c.txt").exists());
+
+        // 变基后的分支可以快进推送
+        let branch = branch_name(&second);
+        push_branch(
+            &second,
+            "origin",
+            Some(&branch),
+            false,
+            false,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn non_fast_forward_push_is_rejected_unless_forced() {
+        let (dir, first, second) = setup("force");
+        let credentials = GitCredentials::default();
+        let branch = branch_name(&first);
+        commit_file(&second, "other.txt", "other\n");
+
+        let err = push_branch(
+            &second,
+            "origin",
+            Some(&branch),
+            false,
+            false,
+            &credentials,
+            &mut |_, _, _| {},
+        );
+        assert!(err.is_err());
+
+        let result = push_branch(
+            &second,
+            "origin",
+            Some(&branch),
+            true,
+            false,
+            &credentials,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        assert!(result.refspec.starts_with('+'));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_remote_hosts() {
+        assert_eq!(
+            url_host("https://github.com/owner/repo.git"),
+            Some("github.com")
+        );
+        assert_eq!(
+            url_host("https://user@git.example.com:8443/repo"),
+            Some("git.example.com")
+        );
+        assert_eq!(
+            url_host("ssh://git@gitlab.com/owner/repo.git"),
+            Some("gitlab.com")
+        );
+        assert_eq!(
+            url_host("git@github.com:owner/repo.git"),
+            Some("github.com")
+        );
+        assert_eq!(url_host("github.com"), Some("github.com"));
+        assert_eq!(url_host("file:///tmp/repo.git"), None);
+    }
+
+    #[test]
+    fn vault_tokens_only_over_https() {
+        let credentials = GitCredentials {
+            tokens: vec![VaultToken {
+                host: "git.example.com".to_string(),
+                username: None,
+                secret: Zeroizing::new("token".to_string()),
+            }],
+            ssh_keys: Vec::new(),
+        };
+        let next = |url: &str, attempts: &mut CredentialAttempts| {
+            next_credential(
+                attempts,
+                &credentials,
+                None,
+                url,
+                None,
+                CredentialType::USER_PASS_PLAINTEXT,
+            )
+        };
+
+        let mut attempts = CredentialAttempts::default();
+        assert!(next("HTTPS://git.example.com/repo.git", &mut attempts).is_ok());
+        assert_eq!(attempts.token, 1);
+        // 令牌只提供一次
+        assert!(next("https://git.example.com/repo.git", &mut attempts).is_err());
+
+        for url in ["http://git.example.com/repo.git", "git.example.com/repo.git"] {
+            let mut attempts = CredentialAttempts::default();
+            assert!(next(url, &mut attempts).is_err());
+            assert_eq!(attempts.token, 0);
+        }
+
+        let mut attempts = CredentialAttempts::default();
+        assert!(next("https://other.example.com/repo.git", &mut attempts).is_err());
+    }
+
+    fn vault_entry(category: &str, url: Option<&str>) -> VaultEntry {
+        VaultEntry {
+            id: 1,
+            project_id: 1,
+            title: "entry".to_string(),
+            param_key: Some("deploy".to_string()),
+            encrypted_value: String::new(),
+            encrypted_notes: None,
+            url: url.map(str::to_string),
+            category: category.to_string(),
+            salt: String::new(),
+            nonce: String::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            encrypted_totp: None,
+            ssh_public_key: None,
+            ssh_fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn only_git_token_entries_become_tokens() {
+        let mut credentials = GitCredentials::default();
+        let secret = || Zeroizing::new("secret".to_string());
+        // 带 url 的普通条目（例如网站密码）不能被当作 git 令牌发送
+        credentials.add(&vault_entry("general", Some("https://git.example.com")), secret());
+        credentials.add(&vault_entry("api_key", Some("https://git.example.com")), secret());
+        credentials.add(&vault_entry(GIT_TOKEN_CATEGORY, None), secret());
+        assert!(credentials.tokens.is_empty());
+
+        credentials.add(
+            &vault_entry(GIT_TOKEN_CATEGORY, Some("https://git.example.com/owner")),
+            secret(),
+        );
+        credentials.add(&vault_entry("ssh", None), secret());
+        assert_eq!(credentials.tokens.len(), 1);
+        assert_eq!(credentials.tokens[0].host, "git.example.com");
+        assert_eq!(credentials.tokens[0].username.as_deref(), Some("deploy"));
+        assert_eq!(credentials.ssh_keys.len(), 1);
+    }
+}
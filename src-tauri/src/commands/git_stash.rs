@@ -0,0 +1,322 @@
+// Stash commands: save / list / apply / pop / drop / show
+
+use crate::commands::git::{GitCommitDetail, apply_stash, diff_file_changes};
+use git2::{DiffOptions, ErrorCode, Oid, Repository, StashFlags};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitStashEntry {
+    /// Position in the stash list, `stash@{index}`
+    pub index: usize,
+    pub hash: String,
+    pub message: String,
+    /// Branch the stash was created on, parsed from "WIP on <branch>: ..." / "On <branch>: ..."
+    pub branch: Option<String>,
+    pub date: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitStashApplyResult {
+    pub index: usize,
+    /// Paths left in conflict; when non-empty the stash was kept even for pop
+    pub conflicts: Vec<String>,
+    pub dropped: bool,
+}
+
+fn open_repo(path: &str) -> Result<Repository, String> {
+    Repository::open(path).map_err(|e| e.message().to_string())
+}
+
+fn stash_branch(message: &str) -> Option<String> {
+    let rest = message
+        .strip_prefix("WIP on ")
+        .or_else(|| message.strip_prefix("On "))?;
+    let (branch, _) = rest.split_once(':')?;
+    Some(branch.to_string())
+}
+
+fn list_stashes(repo: &mut Repository) -> Result<Vec<(usize, String, Oid)>, String> {
+    let mut stashes = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        stashes.push((index, message.to_string(), *oid));
+        true
+    })
+    .map_err(|e| e.message().to_string())?;
+    Ok(stashes)
+}
+
+fn stash_entry(repo: &Repository, index: usize, message: String, oid: Oid) -> GitStashEntry {
+    let date = repo
+        .find_commit(oid)
+        .map(|commit| commit.time().seconds())
+        .unwrap_or(0);
+    GitStashEntry {
+        index,
+        hash: oid.to_string(),
+        branch: stash_branch(&message),
+        message,
+        date,
+    }
+}
+
+fn find_stash(repo: &mut Repository, index: usize) -> Result<(String, Oid), String> {
+    list_stashes(repo)?
+        .into_iter()
+        .find(|(i, _, _)| *i == index)
+        .map(|(_, message, oid)| (message, oid))
+        .ok_or_else(|| format!("stash@{{{}}} 不存在", index))
+}
+
+// 暂存工作区改动
+#[tauri::command]
+pub fn git_stash_save(
+    path: String,
+    message: Option<String>,
+    include_untracked: Option<bool>,
+    keep_index: Option<bool>,
+) -> Result<GitStashEntry, String> {
+    let mut repo = open_repo(&path)?;
+    let signature = repo.signature().map_err(|e| e.message().to_string())?;
+
+    let mut flags = StashFlags::DEFAULT;
+    if include_untracked.unwrap_or(false) {
+        flags |= StashFlags::INCLUDE_UNTRACKED;
+    }
+    if keep_index.unwrap_or(false) {
+        flags |= StashFlags::KEEP_INDEX;
+    }
+
+    let message = message.filter(|m| !m.trim().is_empty());
+    let oid = repo
+        .stash_save2(&signature, message.as_deref(), Some(flags))
+        .map_err(|e| match e.code() {
+            ErrorCode::NotFound => "没有可暂存的更改".to_string(),
+            _ => e.message().to_string(),
+        })?;
+
+    let (message, _) = find_stash(&mut repo, 0)?;
+    Ok(stash_entry(&repo, 0, message, oid))
+}
+
+// 列出所有 stash，最新的在前
+#[tauri::command]
+pub fn git_stash_list(path: String) -> Result<Vec<GitStashEntry>, String> {
+    let mut repo = open_repo(&path)?;
+    let stashes = list_stashes(&mut repo)?;
+    Ok(stashes
+        .into_iter()
+        .map(|(index, message, oid)| stash_entry(&repo, index, message, oid))
+        .collect())
+}
+
+// 应用 stash；有冲突时返回冲突路径
+#[tauri::command]
+pub fn git_stash_apply(
+    path: String,
+    index: usize,
+    reinstate_index: Option<bool>,
+) -> Result<GitStashApplyResult, String> {
+    let mut repo = open_repo(&path)?;
+    let conflicts = apply_stash(&mut repo, index, reinstate_index.unwrap_or(false), false)?;
+    Ok(GitStashApplyResult {
+        index,
+        conflicts,
+        dropped: false,
+    })
+}
+
+// 应用并删除 stash；有冲突时保留 stash
+#[tauri::command]
+pub fn git_stash_pop(
+    path: String,
+    index: usize,
+    reinstate_index: Option<bool>,
+) -> Result<GitStashApplyResult, String> {
+    let mut repo = open_repo(&path)?;
+    let conflicts = apply_stash(&mut repo, index, reinstate_index.unwrap_or(false), true)?;
+    Ok(GitStashApplyResult {
+        index,
+        dropped: conflicts.is_empty(),
+        conflicts,
+    })
+}
+
+// 删除 stash
+#[tauri::command]
+pub fn git_stash_drop(path: String, index: usize) -> Result<(), String> {
+    let mut repo = open_repo(&path)?;
+    find_stash(&mut repo, index)?;
+    repo.stash_drop(index).map_err(|e| e.message().to_string())
+}
+
+// 以提交详情的形式查看 stash：相对创建时 HEAD 的改动，包含暂存的未跟踪文件
+#[tauri::command]
+pub fn git_stash_show(path: String, index: usize) -> Result<GitCommitDetail, String> {
+    let mut repo = open_repo(&path)?;
+    let (message, oid) = find_stash(&mut repo, index)?;
+    let stash = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+
+    // stash 提交的父提交依次为：创建时的 HEAD、索引快照、（可选）未跟踪文件快照
+    let base = stash.parent(0).map_err(|e| e.message().to_string())?;
+    let base_tree = base.tree().map_err(|e| e.message().to_string())?;
+    let stash_tree = stash.tree().map_err(|e| e.message().to_string())?;
+
+    let mut diff_opts = DiffOptions::new();
+    let diff = repo
+        .diff_tree_to_tree(Some(&base_tree), Some(&stash_tree), Some(&mut diff_opts))
+        .map_err(|e| e.message().to_string())?;
+    let mut files = diff_file_changes(&diff, true)?;
+
+    if let Ok(untracked) = stash.parent(2) {
+        let untracked_tree = untracked.tree().map_err(|e| e.message().to_string())?;
+        let diff = repo
+            .diff_tree_to_tree(None, Some(&untracked_tree), Some(&mut DiffOptions::new()))
+            .map_err(|e| e.message().to_string())?;
+        files.extend(diff_file_changes(&diff, true)?);
+    }
+
+    Ok(GitCommitDetail {
+        hash: oid.to_string(),
+        short_hash: format!("stash@{{{}}}", index),
+        author: stash.author().name().unwrap_or("Unknown").to_string(),
+        email: stash.author().email().unwrap_or("").to_string(),
+        date: stash.time().seconds(),
+        subject: message,
+        body: String::new(),
+        parents: stash.parent_ids().map(|id| id.to_string()).collect(),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit_file, init_repo, temp_dir};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+
+    fn stage(repo: &Repository, name: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Repository with `a.txt` and `b.txt` committed, in its own temp directory
+    fn setup(name: &str) -> (PathBuf, Repository) {
+        let dir = temp_dir("git-stash", name);
+        let repo = init_repo(&dir, None);
+        commit_file(&repo, "a.txt", "a\n");
+        commit_file(&repo, "b.txt", "b\n");
+        (dir, repo)
+    }
+
+    fn path_of(dir: &Path) -> String {
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn read(dir: &Path, name: &str) -> String {
+        fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn test_stash_save_include_untracked_and_keep_index() {
+        let (dir, repo) = setup("save");
+        let path = path_of(&dir);
+        let err = git_stash_save(path.clone(), None, None, None).unwrap_err();
+        assert_eq!(err, "没有可暂存的更改");
+
+        fs::write(dir.join("a.txt"), "staged\n").unwrap();
+        stage(&repo, "a.txt");
+        fs::write(dir.join("b.txt"), "unstaged\n").unwrap();
+        fs::write(dir.join("new.txt"), "new\n").unwrap();
+
+        let entry = git_stash_save(path.clone(), Some("wip".into()), Some(true), Some(true))
+            .unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        assert_eq!(entry.index, 0);
+        assert_eq!(entry.message, format!("On {}: wip", branch));
+        assert_eq!(entry.branch.as_deref(), Some(branch.as_str()));
+
+        // keep-index 保留已暂存的改动，其余改动和未跟踪文件进入 stash
+        assert_eq!(read(&dir, "a.txt"), "staged\n");
+        assert_eq!(read(&dir, "b.txt"), "b\n");
+        assert!(!dir.join("new.txt").exists());
+        let stash = repo.find_commit(Oid::from_str(&entry.hash).unwrap()).unwrap();
+        assert_eq!(stash.parent_count(), 3);
+
+        let list = git_stash_list(path).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].hash, entry.hash);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stash_show_includes_untracked_files() {
+        let (dir, _repo) = setup("show");
+        let path = path_of(&dir);
+        fs::write(dir.join("b.txt"), "b\nmore\n").unwrap();
+        fs::write(dir.join("new.txt"), "new\n").unwrap();
+        git_stash_save(path.clone(), None, Some(true), None).unwrap();
+
+        let detail = git_stash_show(path.clone(), 0).unwrap();
+        assert_eq!(detail.short_hash, "stash@{0}");
+        assert!(detail.subject.starts_with("WIP on "), "{}", detail.subject);
+        assert_eq!(detail.parents.len(), 3);
+        let files: Vec<(&str, &str, usize, usize)> = detail
+            .files
+            .iter()
+            .map(|f| (f.filename.as_str(), f.status.as_str(), f.additions, f.deletions))
+            .collect();
+        assert_eq!(files, vec![("b.txt", "modified", 1, 0), ("new.txt", "added", 1, 0)]);
+        let patch = detail.files[1].patch.as_deref().unwrap();
+        assert!(patch.contains("+new"), "{}", patch);
+
+        assert!(git_stash_show(path, 1).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stash_pop_without_conflict() {
+        let (dir, _repo) = setup("pop");
+        let path = path_of(&dir);
+        fs::write(dir.join("a.txt"), "changed\n").unwrap();
+        git_stash_save(path.clone(), None, None, None).unwrap();
+        assert_eq!(read(&dir, "a.txt"), "a\n");
+
+        let result = git_stash_pop(path.clone(), 0, None).unwrap();
+        assert!(result.dropped);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(read(&dir, "a.txt"), "changed\n");
+        assert!(git_stash_list(path).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stash_pop_conflict_keeps_stash() {
+        let (dir, repo) = setup("pop-conflict");
+        let path = path_of(&dir);
+        fs::write(dir.join("a.txt"), "stashed\n").unwrap();
+        git_stash_save(path.clone(), None, None, None).unwrap();
+
+        // 工作区中同一文件有未提交的改动：什么都不改变
+        fs::write(dir.join("a.txt"), "dirty\n").unwrap();
+        let result = git_stash_pop(path.clone(), 0, None).unwrap();
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert!(!result.dropped);
+        assert_eq!(read(&dir, "a.txt"), "dirty\n");
+
+        // 提交后再弹出：合并冲突写入索引，stash 保留
+        commit_file(&repo, "a.txt", "committed\n");
+        let result = git_stash_pop(path.clone(), 0, None).unwrap();
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert!(!result.dropped);
+        assert!(read(&dir, "a.txt").contains("<<<<<<<"));
+        assert_eq!(git_stash_list(path).unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,17 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::fs;
-use std::io::Write;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use tauri::Emitter;
-use zip::{ZipWriter, write::FileOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::{Emitter, Manager};
+use zip::{ZipArchive, ZipWriter, write::FileOptions};
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use crate::commands::project_analysis::{ProjectAnalysis, cached_analysis};
+use crate::commands::project_settings::ProjectSettings;
+use crate::crypto::KdfParams;
+use crate::db;
+use zeroize::{Zeroize, Zeroizing};
 
 // 进度事件结构
 #[derive(Clone, Serialize)]
@@ -14,11 +20,15 @@ pub struct ExportProgress {
     pub message: String,
     pub current: usize,
     pub total: usize,
+    /// 终止事件表示操作失败；此时 `message` 携带错误信息
+    pub failed: bool,
 }
 
 // 进度追踪器
-struct ProgressTracker<'a> {
+pub(crate) struct ProgressTracker<'a> {
     app: &'a tauri::AppHandle,
+    event: &'static str,
+    action: &'static str,
     current: usize,
     total: usize,
     last_emitted: usize,
@@ -26,8 +36,19 @@ struct ProgressTracker<'a> {
 
 impl<'a> ProgressTracker<'a> {
     fn new(app: &'a tauri::AppHandle, total: usize) -> Self {
+        Self::with_event(app, "export-progress", "正在打包文件", total)
+    }
+
+    pub(crate) fn with_event(
+        app: &'a tauri::AppHandle,
+        event: &'static str,
+        action: &'static str,
+        total: usize,
+    ) -> Self {
         Self {
             app,
+            event,
+            action,
             current: 0,
             total,
             last_emitted: 0,
@@ -61,22 +82,53 @@ impl<'a> ProgressTracker<'a> {
             0
         };
 
-        let _ = self.app.emit("export-progress", ExportProgress {
+        let _ = self.app.emit(self.event, ExportProgress {
             progress,
-            message: format!("正在打包文件 ({}/{})", self.current, self.total),
+            message: format!("{} ({}/{})", self.action, self.current, self.total),
             current: self.current,
             total: self.total,
+            failed: false,
         });
     }
 
-    fn complete(&self) {
-        let _ = self.app.emit("export-progress", ExportProgress {
+    /// 直接设置进度（如 git 传输回调给出的绝对数量）；阶段变化或百分比变化时发送事件
+    pub(crate) fn update(&mut self, action: &'static str, current: usize, total: usize) {
+        let phase_changed = action != self.action || total != self.total;
+        self.action = action;
+        self.total = total;
+        self.current = current;
+        if phase_changed {
+            self.last_emitted = 0;
+        }
+
+        let percent = |n: usize| (n * 100).checked_div(total).unwrap_or(0);
+        if phase_changed || percent(current) > percent(self.last_emitted) {
+            self.emit();
+            self.last_emitted = current;
+        }
+    }
+
+    pub(crate) fn complete(&self, message: &str) {
+        self.terminate(message, false);
+    }
+
+    fn terminate(&self, message: &str, failed: bool) {
+        let _ = self.app.emit(self.event, ExportProgress {
             progress: 100,
-            message: "导出完成！".to_string(),
+            message: message.to_string(),
             current: self.total,
             total: self.total,
+            failed,
         });
     }
+
+    /// 以成功或失败结束进度，失败时同样发送终止事件，前端据此收起进度条
+    pub(crate) fn finish<T>(&self, result: &Result<T, String>, done: &str, failed: &str) {
+        match result {
+            Ok(_) => self.complete(done),
+            Err(e) => self.terminate(&format!("{}: {}", failed, e), true),
+        }
+    }
 }
 
 // ---------------- 数据结构 ----------------
@@ -88,7 +140,11 @@ pub struct Project {
     pub description: Option<String>,
     pub created_at: String,
     pub last_accessed: DateTime<Utc>,
-    pub settings: Option<String>,
+    /// Typed settings object; packages from older versions hold a JSON string
+    pub settings: Option<serde_json::Value>,
+    /// Cached `analyze_project` summary at export time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<ProjectAnalysis>,
 }
 
 
@@ -133,20 +189,23 @@ pub struct VaultImportEntry {
 
 // ---------------- 辅助工具函数 ----------------
 
-async fn decrypt_vault_export(
+fn decrypt_vault_export(
+    sessions: &crate::commands::vault::VaultSessions,
     export: VaultExport,
 ) -> Result<Vec<crate::commands::vault::DecryptedVaultEntry>, String> {
     let master = export.masters.clone();
     let mut out = Vec::new();
 
     for e in export.entries {
-        let (value, notes) = crate::commands::vault::vault_decrypt_entry(
-            e.encrypted_value,
-            e.nonce,
-            e.encrypted_notes,
-            e.salt,
-            master.clone(),
-        ).await?;
+        let (value, notes) = crate::commands::vault::decrypt_stored_entry(
+            sessions,
+            e.project_id,
+            &e.encrypted_value,
+            &e.nonce,
+            e.encrypted_notes.as_deref(),
+            &e.salt,
+            &master,
+        )?;
 
         out.push(crate::commands::vault::DecryptedVaultEntry {
             id: e.id,
@@ -212,41 +271,60 @@ fn sanitize_filename(name: &str) -> String {
     sanitized
 }
 
-// 统计目录中的文件数量
-fn count_files_in_directory(dir: &Path, ignore: bool) -> usize {
-    const IGNORE_DIRS: &[&str] = &[".git", "target", "node_modules", ".idea", ".vscode", ".DS_Store"];
+pub(crate) const IGNORE_DIRS: &[&str] = &[".git", "target", "node_modules", ".idea", ".vscode", ".DS_Store"];
 
-    if !dir.exists() { return 0; }
+// 内置插件目录（ignore 为 true 时）以及项目设置中的忽略规则
+fn is_ignored(name: &str, ignore: bool, settings: Option<&ProjectSettings>) -> bool {
+    (ignore && IGNORE_DIRS.contains(&name))
+        || settings.is_some_and(|s| s.is_handover_ignored(name))
+}
+
+/// 递归遍历目录中的文件，跳过符号链接和被忽略的条目。
+/// `skip(path, is_dir)` 可以额外排除路径，例如 `.gitignore` 中的规则
+pub(crate) fn walk_files(
+    dir: &Path,
+    ignore: bool,
+    settings: Option<&ProjectSettings>,
+    skip: &dyn Fn(&Path, bool) -> bool,
+    on_file: &mut dyn FnMut(&fs::DirEntry),
+) {
+    if !dir.exists() { return; }
 
-    let mut count = 0;
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let name = entry.file_name().to_string_lossy().into_owned();
-            if ignore && IGNORE_DIRS.iter().any(|i| *i == name) { continue; }
+            if is_ignored(&name, ignore, settings) { continue; }
 
             if let Ok(file_type) = entry.file_type() {
                 if file_type.is_symlink() { continue; }
+                let path = entry.path();
+                if skip(&path, file_type.is_dir()) { continue; }
                 if file_type.is_dir() {
-                    count += count_files_in_directory(&entry.path(), ignore);
+                    walk_files(&path, ignore, settings, skip, on_file);
                 } else if file_type.is_file() {
-                    count += 1;
+                    on_file(&entry);
                 }
             }
         }
     }
+}
+
+// 统计目录中的文件数量
+fn count_files_in_directory(dir: &Path, ignore: bool, settings: Option<&ProjectSettings>) -> usize {
+    let mut count = 0;
+    walk_files(dir, ignore, settings, &|_, _| false, &mut |_| count += 1);
     count
 }
 
-fn add_directory_to_zip(
-    zip: &mut ZipWriter<fs::File>,
+fn add_directory_to_zip<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
     dir: &Path,
     zip_base: &str,
     options: FileOptions,
     ignore: bool,
+    settings: Option<&ProjectSettings>,
     mut tracker: Option<&mut ProgressTracker>,
 ) -> Result<(), String> {
-    const IGNORE_DIRS: &[&str] = &[".git", "target", "node_modules", ".idea", ".vscode", ".DS_Store"];
-
     if !dir.exists() { return Ok(()); }
 
     // 收集所有条目以便在循环中使用可变引用
@@ -259,14 +337,14 @@ fn add_directory_to_zip(
         let path = entry.path();
         let name = entry.file_name().to_string_lossy().into_owned();
 
-        if ignore && IGNORE_DIRS.iter().any(|i| *i == name) { continue; }
+        if is_ignored(&name, ignore, settings) { continue; }
         let file_type = entry.file_type().map_err(|e| e.to_string())?;
         if file_type.is_symlink() { continue; }
 
         let zip_path = format!("{}/{}", zip_base.trim_end_matches('/'), name);
 
         if file_type.is_dir() {
-            add_directory_to_zip(zip, &path, &zip_path, options, ignore, tracker.as_deref_mut())?;
+            add_directory_to_zip(zip, &path, &zip_path, options, ignore, settings, tracker.as_deref_mut())?;
         } else if file_type.is_file() {
             let mut f = fs::File::open(&path).map_err(|e| e.to_string())?;
             zip.start_file(&zip_path, options).map_err(|e| e.to_string())?;
@@ -281,39 +359,260 @@ fn add_directory_to_zip(
     Ok(())
 }
 
+/// 交接包中的各部分内容，由 `write_handover_zip` 依次写入
+struct HandoverContents<'a> {
+    project_json: &'a str,
+    project_root: Option<&'a Path>,
+    folder_name: String,
+    ignore_dirs: bool,
+    settings: Option<&'a ProjectSettings>,
+    docvault_dir: Option<&'a Path>,
+    /// vault/info.json 和 vault/vault.md 的内容
+    vault_files: Option<(&'a str, &'a str)>,
+}
+
+fn write_handover_zip<W: Write + Seek>(
+    writer: W,
+    contents: &HandoverContents,
+    tracker: &mut ProgressTracker,
+) -> Result<W, String> {
+    let mut zip = ZipWriter::new(writer);
+    let zip_options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("info.json", zip_options).map_err(|e| e.to_string())?;
+    zip.write_all(contents.project_json.as_bytes()).map_err(|e| e.to_string())?;
+    tracker.increment();
+
+    // 项目源码
+    if let Some(project_root) = contents.project_root {
+        let zip_project_path = format!("project/{}", contents.folder_name);
+        add_directory_to_zip(
+            &mut zip,
+            project_root,
+            &zip_project_path,
+            zip_options,
+            contents.ignore_dirs,
+            contents.settings,
+            Some(&mut *tracker),
+        )?;
+    }
+
+    // 文档库
+    if let Some(docvault_dir) = contents.docvault_dir {
+        add_directory_to_zip(&mut zip, docvault_dir, "docs", zip_options, false, None, Some(&mut *tracker))?;
+    }
+
+    // 保险箱
+    if let Some((vault_json, vault_md)) = contents.vault_files {
+        zip.start_file("vault/info.json", zip_options).map_err(|e| e.to_string())?;
+        zip.write_all(vault_json.as_bytes()).map_err(|e| e.to_string())?;
+        tracker.increment();
+
+        zip.start_file("vault/vault.md", zip_options).map_err(|e| e.to_string())?;
+        zip.write_all(vault_md.as_bytes()).map_err(|e| e.to_string())?;
+        tracker.increment();
+    }
+
+    zip.finish().map_err(|e| e.to_string())
+}
+
+/// ZipWriter 写完一个文件后会回到它的本地文件头补写 CRC 和大小，所以要求 Seek。
+/// 这里把只能顺序写入的加密流包装成 Seek：当前文件开头的部分（含文件头）暂存在内存中，
+/// 超出 `SPOOL_MEMORY_LIMIT` 的数据用一次性密钥加密后暂存到临时文件，
+/// ZipWriter 补写完文件头回到末尾时，再把它们依次交给加密流
+struct SpoolWriter<W: Write> {
+    inner: W,
+    /// 已交给 inner 的字节数，这之前的位置不能再改写
+    committed: u64,
+    pending: Zeroizing<Vec<u8>>,
+    spill: Option<Spill>,
+    pos: u64,
+}
+
+/// 内存中为每个文件保留的字节数，足够容纳本地文件头（文件名最长 64 KiB）
+const SPOOL_MEMORY_LIMIT: usize = 4 * crate::crypto::stream::CHUNK_SIZE;
+
+const SPILL_CONTEXT: &[u8] = b"pomo-handover-spool";
+
+/// 加密暂存的临时文件，drop 时删除
+struct SpillPath(PathBuf);
+
+impl Drop for SpillPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// 当前文件超出内存部分的数据，用只存在于内存中的密钥加密
+struct Spill {
+    path: SpillPath,
+    key: Zeroizing<[u8; crate::crypto::DATA_KEY_LEN]>,
+    writer: crate::crypto::EncryptWriter<fs::File>,
+    len: u64,
+}
+
+impl Spill {
+    fn create() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = SpillPath(std::env::temp_dir().join(format!(
+            "pomo-spool-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        )));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path.0)?;
+        let key = crate::crypto::generate_data_key();
+        let writer = crate::crypto::EncryptWriter::with_key(file, &key, SPILL_CONTEXT)
+            .map_err(io::Error::other)?;
+        Ok(Self { path, key, writer, len: 0 })
+    }
+
+    /// 解密暂存的数据写入 `out`，然后删除临时文件
+    fn drain<O: Write>(self, out: &mut O) -> io::Result<()> {
+        let (mut file, _) = self.writer.finish().map_err(io::Error::other)?;
+        file.seek(SeekFrom::Start(0))?;
+        crate::crypto::decrypt_stream_with_key(BufReader::new(file), out, &self.key, SPILL_CONTEXT)
+            .map_err(io::Error::other)?;
+        drop(self.path);
+        Ok(())
+    }
+}
+
+impl<W: Write> SpoolWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            committed: 0,
+            pending: Zeroizing::new(Vec::new()),
+            spill: None,
+            pos: 0,
+        }
+    }
+
+    fn end(&self) -> u64 {
+        self.committed + self.pending.len() as u64 + self.spill.as_ref().map_or(0, |s| s.len)
+    }
+
+    fn commit(&mut self) -> io::Result<()> {
+        let end = self.end();
+        self.inner.write_all(&self.pending)?;
+        if let Some(spill) = self.spill.take() {
+            spill.drain(&mut self.inner)?;
+        }
+        self.committed = end;
+        self.pending.zeroize();
+        Ok(())
+    }
+
+    fn into_inner(mut self) -> Result<W, String> {
+        self.commit().map_err(|e| e.to_string())?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SpoolWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // seek 保证 committed <= pos <= end
+        let offset = (self.pos - self.committed) as usize;
+        let head = self.pending.len();
+        let n = if offset < head {
+            // 改写内存中的部分，ZipWriter 只会回写文件头
+            let n = buf.len().min(head - offset);
+            self.pending[offset..offset + n].copy_from_slice(&buf[..n]);
+            n
+        } else if self.pos < self.end() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "不能改写已暂存到磁盘的数据"));
+        } else if head < SPOOL_MEMORY_LIMIT {
+            let n = buf.len().min(SPOOL_MEMORY_LIMIT - head);
+            self.pending.extend_from_slice(&buf[..n]);
+            n
+        } else {
+            let spill = match &mut self.spill {
+                Some(spill) => spill,
+                None => self.spill.insert(Spill::create()?),
+            };
+            let n = spill.writer.write(buf)?;
+            spill.len += n as u64;
+            n
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for SpoolWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let end = self.end();
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(delta) => end.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .filter(|t| (self.committed..=end).contains(t))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "不能定位到已写出的数据"))?;
+
+        // 从文件头回到末尾，说明当前文件已经写完，之前的数据不会再改动
+        if target == end && self.pos < end {
+            self.commit()?;
+        }
+        self.pos = target;
+        Ok(target)
+    }
+}
+
 // ---------------- 主导出命令 ----------------
 
 #[tauri::command]
 pub async fn export_project_handover(
     app: tauri::AppHandle,
-    project: Project,
+    mut project: Project,
     docvault_path: Option<String>,
     vault_entries: Option<Vec<VaultEntry>>,
     vault_masters: Option<String>,
     output_path: String,
     export_options: ExportOptions,
 ) -> Result<(), String> {
+    // 加密导出必须提供密码，在开始打包前检查
+    let zip_password = if export_options.zip_encryption {
+        match export_options.zip_password.as_deref() {
+            Some(p) if !p.is_empty() => Some(p.to_string()),
+            _ => return Err("已启用加密导出，请设置交接包密码".to_string()),
+        }
+    } else {
+        None
+    };
+
     // 发送初始进度
     let _ = app.emit("export-progress", ExportProgress {
         progress: 0,
         message: "正在统计文件数量...".to_string(),
         current: 0,
         total: 0,
+        failed: false,
     });
 
     // 1. 统计总文件数
     let ignore_dirs = export_options.ignore_plugin == "ignore-plugin-directory";
+    let settings = project.settings.as_ref().map(ProjectSettings::from_json_value);
     let project_root = PathBuf::from(&project.path);
     let mut total_files: usize = 1; // info.json
 
     if project_root.exists() && project_root.is_dir() {
-        total_files += count_files_in_directory(&project_root, ignore_dirs);
+        total_files += count_files_in_directory(&project_root, ignore_dirs, settings.as_ref());
     }
 
     if let Some(ref path) = docvault_path {
         let docvault_dir = PathBuf::from(path);
         if docvault_dir.exists() && docvault_dir.is_dir() {
-            total_files += count_files_in_directory(&docvault_dir, false);
+            total_files += count_files_in_directory(&docvault_dir, false, None);
         }
     }
 
@@ -325,82 +624,690 @@ pub async fn export_project_handover(
     let mut tracker = ProgressTracker::new(&app, total_files);
     tracker.emit(); // 发送初始进度（0/total）
 
-    // 3. 开始导出
-    let file = fs::File::create(&output_path).map_err(|e| e.to_string())?;
-    let mut zip = ZipWriter::new(file);
+    // 3. 项目基础信息，附带最近一次的项目分析结果（没有分析过则省略）
+    if project.analysis.is_none()
+        && let Ok(pool) = db::pool(&app).await
+    {
+        project.analysis = cached_analysis(&pool, &project.path).await.ok().flatten();
+    }
+    let project_json = serde_json::to_string_pretty(&project).map_err(|e| e.to_string())?;
 
-    let zip_options = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated);
+    // 4. 保险箱解密导出
+    let vault_files = match (vault_entries, vault_masters) {
+        (Some(entries), Some(master)) => {
+            let exported_at = Utc::now().to_rfc3339();
 
-    // 4. 项目基础信息
-    zip.start_file("info.json", zip_options).map_err(|e| e.to_string())?;
-    let project_json = serde_json::to_string_pretty(&project).map_err(|e| e.to_string())?;
-    zip.write_all(project_json.as_bytes()).map_err(|e| e.to_string())?;
-    tracker.increment();
+            let export_payload = VaultExport {
+                entries,
+                masters: master,
+            };
+            // 旧条目每个字段都要做一次 Argon2，放到阻塞线程池中解密
+            let handle = app.clone();
+            let decrypted = tauri::async_runtime::spawn_blocking(move || {
+                let sessions = handle.state::<crate::commands::vault::VaultSessions>();
+                decrypt_vault_export(&sessions, export_payload)
+            })
+            .await
+            .map_err(|e| e.to_string())??;
 
-    // 5. 项目源码导出
-    if project_root.exists() && project_root.is_dir() {
-        let folder_name = sanitize_filename(&project.name);
-        let zip_project_path = format!("project/{}", folder_name);
-        add_directory_to_zip(
-            &mut zip,
-            &project_root,
-            &zip_project_path,
-            zip_options,
-            ignore_dirs,
-            Some(&mut tracker),
-        )?;
-    }
+            let import_data: Vec<VaultImportEntry> = decrypted.iter().map(|e| {
+                VaultImportEntry {
+                    title: e.title.clone(),
+                    param_key: e.param_key.clone(),
+                    param_value: e.param_value.clone(),
+                    notes: e.notes.clone(),
+                    url: e.url.clone(),
+                    category: e.category.clone(),
+                }
+            }).collect();
 
-    // 6. 导出文档库
-    if let Some(path) = docvault_path {
-        let docvault_dir = PathBuf::from(&path);
-        if docvault_dir.exists() && docvault_dir.is_dir() {
-            add_directory_to_zip(&mut zip, &docvault_dir, "docs", zip_options, false, Some(&mut tracker))?;
+            let vault_json = serde_json::json!({
+                "entries": &import_data,
+                "exported_at": &exported_at,
+                "version": "1.0"
+            });
+
+            Some((
+                Zeroizing::new(serde_json::to_string_pretty(&vault_json).map_err(|e| e.to_string())?),
+                Zeroizing::new(vault_to_markdown(&decrypted, &exported_at)),
+            ))
         }
+        _ => None,
+    };
+
+    let contents = HandoverContents {
+        project_json: &project_json,
+        project_root: (project_root.exists() && project_root.is_dir()).then_some(project_root.as_path()),
+        folder_name: sanitize_filename(&project.name),
+        ignore_dirs,
+        settings: settings.as_ref(),
+        docvault_dir: docvault_path.as_deref().map(Path::new).filter(|d| d.is_dir()),
+        vault_files: vault_files.as_ref().map(|(json, md)| (json.as_str(), md.as_str())),
+    };
+
+    // 5. 打包；加密导出时 zip 直接写入加密流，明文不落盘
+    let file = fs::File::create(&output_path).map_err(|e| e.to_string())?;
+    let result = match zip_password {
+        Some(password) => crate::crypto::EncryptWriter::new(BufWriter::new(file), &password)
+            .and_then(|encryptor| {
+                write_handover_zip(SpoolWriter::new(encryptor), &contents, &mut tracker)
+            })
+            .and_then(SpoolWriter::into_inner)
+            .and_then(|encryptor| encryptor.finish())
+            .map(|_| ())
+            .map_err(|e| format!("加密交接包失败: {}", e)),
+        None => write_handover_zip(file, &contents, &mut tracker).map(|_| ()),
+    };
+    if let Err(e) = result {
+        let _ = fs::remove_file(&output_path);
+        return Err(e);
     }
 
-    // 7. 保险箱解密导出
-    if let (Some(entries), Some(master)) = (vault_entries, vault_masters) {
-        let exported_at = Utc::now().to_rfc3339();
+    // 发送完成事件
+    tracker.complete("导出完成！");
 
-        let export_payload = VaultExport {
-            entries,
-            masters: master,
-        };
-        let decrypted = decrypt_vault_export(export_payload).await?;
-
-        let import_data: Vec<VaultImportEntry> = decrypted.iter().map(|e| {
-            VaultImportEntry {
-                title: e.title.clone(),
-                param_key: e.param_key.clone(),
-                param_value: e.param_value.clone(),
-                notes: e.notes.clone(),
-                url: e.url.clone(),
-                category: e.category.clone(),
+    Ok(())
+}
+
+// ---------------- 导入 ----------------
+
+/// 交接包中 vault/info.json 的结构
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VaultExportFile {
+    pub entries: Vec<VaultImportEntry>,
+    pub exported_at: String,
+    pub version: String,
+}
+
+/// 交接包预览信息，用于导入前让用户确认
+#[derive(Debug, Serialize)]
+pub struct HandoverInfo {
+    pub project: Project,
+    pub project_folder: Option<String>,
+    pub has_docs: bool,
+    pub vault_entry_count: Option<usize>,
+}
+
+/// 重新加密后的保险箱条目，字段与 vault_entries 表一致
+#[derive(Debug, Serialize)]
+pub struct ImportedVaultEntry {
+    pub title: String,
+    pub param_key: Option<String>,
+    pub encrypted_value: String,
+    pub encrypted_notes: Option<String>,
+    pub url: Option<String>,
+    pub category: String,
+    pub salt: String,
+    pub nonce: String,
+}
+
+/// 重新加密后的保险箱，password_hash/salt/wrapped_key/kdf_params 对应 vault_master 表
+#[derive(Debug, Serialize)]
+pub struct ImportedVault {
+    pub password_hash: String,
+    pub salt: String,
+    pub wrapped_key: String,
+    pub kdf_params: KdfParams,
+    pub entries: Vec<ImportedVaultEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HandoverImportResult {
+    pub project: Project,
+    pub project_path: String,
+    pub docs_restored: usize,
+    pub vault: Option<ImportedVault>,
+}
+
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+type HandoverArchive = ZipArchive<Box<dyn ReadSeek>>;
+
+/// 打开交接包；加密的交接包在读取时按块解密，明文不落盘，也不会整体读入内存
+fn open_handover_archive(
+    zip_path: &str,
+    zip_password: Option<&str>,
+) -> Result<HandoverArchive, String> {
+    let path = Path::new(zip_path);
+    let reader: Box<dyn ReadSeek> = if crate::crypto::is_encrypted_file(path) {
+        let password = zip_password
+            .filter(|p| !p.is_empty())
+            .ok_or("交接包已加密，请输入交接包密码")?;
+        let file = fs::File::open(path).map_err(|e| format!("无法打开交接包: {}", e))?;
+        let decryptor = crate::crypto::DecryptReader::new(BufReader::new(file), password)
+            .map_err(|_| "交接包密码错误或文件已损坏".to_string())?;
+        Box::new(decryptor)
+    } else {
+        Box::new(fs::File::open(path).map_err(|e| format!("无法打开交接包: {}", e))?)
+    };
+
+    ZipArchive::new(reader).map_err(|e| format!("交接包格式错误: {}", e))
+}
+
+fn read_zip_json<T: DeserializeOwned>(
+    archive: &mut HandoverArchive,
+    name: &str,
+) -> Result<Option<T>, String> {
+    let mut file = match archive.by_name(name) {
+        Ok(f) => f,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("解析 {} 失败: {}", name, e))
+}
+
+// project/<folder>/... 中的 <folder>
+fn find_project_folder(archive: &HandoverArchive) -> Option<String> {
+    archive.file_names().find_map(|name| {
+        let mut parts = name.splitn(3, '/');
+        match (parts.next(), parts.next()) {
+            (Some("project"), Some(folder)) if !folder.is_empty() => Some(folder.to_string()),
+            _ => None,
+        }
+    })
+}
+
+// 只统计文件条目，目录条目不计入进度
+fn count_zip_files(archive: &HandoverArchive, prefix: &str) -> usize {
+    archive
+        .file_names()
+        .filter(|name| name.starts_with(prefix) && !name.ends_with('/'))
+        .count()
+}
+
+// 目录中是否有文件；空的子目录不算（例如 init_docvault 创建的 .attachments）
+fn has_files(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten().any(|entry| match entry.file_type() {
+                Ok(t) if t.is_dir() => has_files(&entry.path()),
+                _ => true,
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// 将 zip 中 prefix 下的所有文件解压到 dest，返回解压的文件数
+fn extract_zip_prefix<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    prefix: &str,
+    dest: &Path,
+    on_file: &mut dyn FnMut(),
+) -> Result<usize, String> {
+    let mut extracted = 0;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+
+        // enclosed_name 会拒绝绝对路径，但允许 a/../b 这类不越过根的 ..；
+        // 去掉前缀后只接受普通路径段，防止写出目标目录
+        let relative = match file
+            .enclosed_name()
+            .and_then(|p| p.strip_prefix(prefix).ok())
+            .map(Path::to_path_buf)
+        {
+            Some(p)
+                if !p.as_os_str().is_empty()
+                    && p.components().all(|c| matches!(c, std::path::Component::Normal(_))) =>
+            {
+                p
             }
-        }).collect();
+            _ => continue,
+        };
+
+        let out_path = dest.join(&relative);
+        if file.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
+            continue;
+        }
+
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut out = fs::File::create(&out_path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut file, &mut out).map_err(|e| e.to_string())?;
 
-        let vault_json = serde_json::json!({
-            "entries": &import_data,
-            "exported_at": &exported_at,
-            "version": "1.0"
+        extracted += 1;
+        on_file();
+    }
+
+    Ok(extracted)
+}
+
+/// 用新主密码创建保险箱：生成数据密钥，条目用数据密钥加密，
+/// 只有主密码哈希和数据密钥包装各做一次 Argon2，参数使用目标保险箱校准的 KDF 参数
+fn reencrypt_vault(
+    entries: Vec<VaultImportEntry>,
+    master_password: &str,
+    kdf_params: Option<KdfParams>,
+) -> Result<ImportedVault, String> {
+    let params = crate::commands::vault::resolve_kdf_params(kdf_params)?;
+    let (password_hash, salt) = crate::crypto::hash_password_with_params(master_password, &params)?;
+    let data_key = crate::crypto::generate_data_key();
+    let wrapped_key = crate::crypto::wrap_data_key(&data_key, master_password, &params)?;
+
+    let mut out = Vec::with_capacity(entries.len());
+    for e in entries {
+        let (encrypted_value, nonce, encrypted_notes, _, entry_salt) =
+            crate::commands::vault::encrypt_entry_fields_with_key(
+                &e.param_value,
+                e.notes.as_deref(),
+                &data_key,
+            )?;
+
+        out.push(ImportedVaultEntry {
+            title: e.title,
+            param_key: e.param_key,
+            encrypted_value,
+            encrypted_notes,
+            url: e.url,
+            category: e.category,
+            salt: entry_salt,
+            nonce,
         });
+    }
 
-        zip.start_file("vault/info.json", zip_options).map_err(|e| e.to_string())?;
-        zip.write_all(serde_json::to_string_pretty(&vault_json).unwrap().as_bytes()).map_err(|e| e.to_string())?;
-        tracker.increment();
+    Ok(ImportedVault {
+        password_hash,
+        salt,
+        wrapped_key,
+        kdf_params: params,
+        entries: out,
+    })
+}
 
-        let md_report = vault_to_markdown(&decrypted, &exported_at);
-        zip.start_file("vault/vault.md", zip_options).map_err(|e| e.to_string())?;
-        zip.write_all(md_report.as_bytes()).map_err(|e| e.to_string())?;
-        tracker.increment();
+/// 读取交接包的基础信息，不解压任何文件
+#[tauri::command]
+pub fn read_handover_info(
+    zip_path: String,
+    zip_password: Option<String>,
+) -> Result<HandoverInfo, String> {
+    let mut archive = open_handover_archive(&zip_path, zip_password.as_deref())?;
+
+    let project: Project = read_zip_json(&mut archive, "info.json")?
+        .ok_or("交接包缺少 info.json")?;
+    let project_folder = find_project_folder(&archive);
+    let has_docs = count_zip_files(&archive, "docs/") > 0;
+    let vault_entry_count = read_zip_json::<VaultExportFile>(&mut archive, "vault/info.json")?
+        .map(|v| v.entries.len());
+
+    Ok(HandoverInfo {
+        project,
+        project_folder,
+        has_docs,
+        vault_entry_count,
+    })
+}
+
+// 用解压好的暂存目录替换目标目录；调用前已确认目标目录中没有文件
+fn move_into_place(staging: &Path, dest: &Path) -> Result<(), String> {
+    if dest.exists() {
+        fs::remove_dir_all(dest).map_err(|e| e.to_string())?;
+    }
+    fs::rename(staging, dest).map_err(|e| format!("无法移动到 {}: {}", dest.display(), e))
+}
+
+/// 从交接包恢复项目：
+/// - project/<name>/ 解压到 target_dir/<name>
+/// - docs/ 解压到 project_id 对应的文档库，文档库中已有文件时拒绝导入
+/// - vault/info.json 使用新的主密码和 `kdf_params`（目标保险箱校准的参数）重新加密后返回，
+///   由前端写入数据库
+///
+/// 文件先解压到暂存目录，保险箱重新加密成功后才移动到目标位置，失败时不留下半成品
+#[tauri::command]
+pub async fn import_project_handover(
+    app: tauri::AppHandle,
+    zip_path: String,
+    target_dir: String,
+    project_id: i64,
+    zip_password: Option<String>,
+    vault_master: Option<String>,
+    kdf_params: Option<KdfParams>,
+) -> Result<HandoverImportResult, String> {
+    let mut archive = open_handover_archive(&zip_path, zip_password.as_deref())?;
+
+    let mut project: Project = read_zip_json(&mut archive, "info.json")?
+        .ok_or("交接包缺少 info.json")?;
+
+    let folder_name = find_project_folder(&archive)
+        .unwrap_or_else(|| sanitize_filename(&project.name));
+    let project_root = PathBuf::from(&target_dir).join(&folder_name);
+
+    if project_root.exists()
+        && fs::read_dir(&project_root)
+            .map_err(|e| e.to_string())?
+            .next()
+            .is_some()
+    {
+        return Err(format!("目标目录已存在且不为空: {}", project_root.display()));
     }
 
-    zip.finish().map_err(|e| e.to_string())?;
+    // 文档会解压到 project_id 的文档库，不能覆盖其中已有的文档
+    let docvault_dir = crate::commands::docvault::get_docvault_dir(&app, project_id)?;
+    let docs_total = count_zip_files(&archive, "docs/");
+    if docs_total > 0 && has_files(&docvault_dir) {
+        return Err(format!("项目 {} 的文档库不为空，请导入到新建的项目", project_id));
+    }
 
-    // 发送完成事件
-    tracker.complete();
+    // 保险箱需要新主密码，先检查再解压，避免留下半成品
+    let vault_file: Option<VaultExportFile> = read_zip_json(&mut archive, "vault/info.json")?;
+    if vault_file.is_some() && vault_master.is_none() {
+        return Err("交接包包含保险箱数据，请设置新的主密码".to_string());
+    }
+
+    let project_prefix = format!("project/{}/", folder_name);
+    let total_files = count_zip_files(&archive, &project_prefix) + docs_total;
+    let mut tracker =
+        ProgressTracker::with_event(&app, "import-progress", "正在解压文件", total_files);
+    tracker.emit();
+
+    // 暂存目录与目标目录放在同一目录下，保证最后可以直接 rename
+    let project_staging = PathBuf::from(&target_dir).join(format!(".{}.importing", folder_name));
+    let docs_staging = docvault_dir.with_extension("importing");
+    let cleanup = || {
+        let _ = fs::remove_dir_all(&project_staging);
+        let _ = fs::remove_dir_all(&docs_staging);
+    };
+    cleanup();
+
+    let staged = async {
+        // 1. 项目源码
+        fs::create_dir_all(&project_staging).map_err(|e| e.to_string())?;
+        extract_zip_prefix(&mut archive, &project_prefix, &project_staging, &mut || {
+            tracker.increment()
+        })?;
+
+        // 2. 文档库
+        let docs_restored = extract_zip_prefix(&mut archive, "docs/", &docs_staging, &mut || {
+            tracker.increment()
+        })?;
+
+        // 3. 保险箱
+        let vault = match (vault_file, vault_master) {
+            (Some(file), Some(master)) => Some(
+                // Argon2 会阻塞数秒，放到阻塞线程池中执行
+                tauri::async_runtime::spawn_blocking(move || {
+                    let master = Zeroizing::new(master);
+                    reencrypt_vault(file.entries, &master, kdf_params)
+                })
+                .await
+                .map_err(|e| e.to_string())??,
+            ),
+            _ => None,
+        };
+        Ok::<_, String>((docs_restored, vault))
+    }
+    .await;
+    let (docs_restored, vault) = match staged {
+        Ok(staged) => staged,
+        Err(e) => {
+            cleanup();
+            return Err(e);
+        }
+    };
+
+    // 4. 全部成功后移动到目标位置
+    if let Err(e) = move_into_place(&project_staging, &project_root) {
+        cleanup();
+        return Err(e);
+    }
+    if docs_restored > 0 {
+        if let Err(e) = move_into_place(&docs_staging, &docvault_dir) {
+            let _ = fs::remove_dir_all(&project_root);
+            cleanup();
+            return Err(e);
+        }
+        crate::commands::docvault::init_docvault(app.clone(), project_id)?;
+    }
+    cleanup();
+
+    project.path = project_root.to_string_lossy().to_string();
+    tracker.complete("导入完成！");
+
+    Ok(HandoverImportResult {
+        project_path: project.path.clone(),
+        project,
+        docs_restored,
+        vault,
+    })
+}
+
+/// 将加密的交接包解密为普通 zip，便于手动检查内容
+#[tauri::command]
+pub fn decrypt_handover_package(
+    input_path: String,
+    zip_password: String,
+    output_path: String,
+) -> Result<(), String> {
+    if !crate::crypto::is_encrypted_file(Path::new(&input_path)) {
+        return Err("该交接包未加密".to_string());
+    }
+
+    let input = fs::File::open(&input_path).map_err(|e| format!("无法打开交接包: {}", e))?;
+    let output = fs::File::create(&output_path).map_err(|e| e.to_string())?;
+
+    let result = crate::crypto::decrypt_stream(
+        BufReader::new(input),
+        BufWriter::new(output),
+        &zip_password,
+    );
+    if result.is_err() {
+        let _ = fs::remove_file(&output_path);
+        return Err("交接包密码错误或文件已损坏".to_string());
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::io::Cursor;
+
+
+    fn build_zip(files: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            if name.ends_with('/') {
+                zip.add_directory(*name, FileOptions::default()).unwrap();
+            } else {
+                zip.start_file(*name, FileOptions::default()).unwrap();
+                zip.write_all(content.as_bytes()).unwrap();
+            }
+        }
+        let cursor = zip.finish().unwrap();
+        ZipArchive::new(Cursor::new(cursor.into_inner())).unwrap()
+    }
+
+    #[test]
+    fn test_extract_zip_prefix() {
+        let root = temp_dir("handover", "extract");
+        let dest = root.join("dest");
+        let mut archive = build_zip(&[
+            ("info.json", "{}"),
+            ("project/app/", ""),
+            ("project/app/src/", ""),
+            ("project/app/src/main.rs", "fn main() {}"),
+            ("project/app/README.md", "readme"),
+            ("project/other/x.txt", "other"),
+            ("docs/a.md", "doc"),
+        ]);
+
+        let mut count = 0;
+        let extracted =
+            extract_zip_prefix(&mut archive, "project/app/", &dest, &mut || count += 1).unwrap();
+        assert_eq!(extracted, 2);
+        assert_eq!(count, 2);
+        assert_eq!(fs::read_to_string(dest.join("src/main.rs")).unwrap(), "fn main() {}");
+        assert_eq!(fs::read_to_string(dest.join("README.md")).unwrap(), "readme");
+        assert!(!dest.join("x.txt").exists());
+        assert!(!dest.join("a.md").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_extract_zip_prefix_rejects_path_traversal() {
+        let root = temp_dir("handover", "traversal");
+        let dest = root.join("a/b/dest");
+        let mut archive = build_zip(&[
+            ("project/app/ok.txt", "ok"),
+            ("project/app/../../evil.txt", "evil"),
+            ("project/app/sub/../../../escape.txt", "evil"),
+            ("../project/app/up.txt", "evil"),
+            ("/project/app/abs.txt", "evil"),
+        ]);
+
+        let extracted =
+            extract_zip_prefix(&mut archive, "project/app/", &dest, &mut || {}).unwrap();
+        assert_eq!(extracted, 1);
+        assert!(dest.join("ok.txt").exists());
+        for name in ["evil.txt", "escape.txt", "up.txt", "abs.txt"] {
+            assert!(!root.join(name).exists(), "{}", name);
+            assert!(!root.join("a").join(name).exists(), "{}", name);
+            assert!(!root.join("a/b").join(name).exists(), "{}", name);
+            assert!(!dest.join(name).exists(), "{}", name);
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_has_files_ignores_empty_directories() {
+        let root = temp_dir("handover", "has-files");
+        assert!(!has_files(&root.join("missing")));
+        fs::create_dir_all(root.join(".attachments/nested")).unwrap();
+        assert!(!has_files(&root));
+        fs::write(root.join(".attachments/nested/image.png"), "png").unwrap();
+        assert!(has_files(&root));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_encrypted_zip_roundtrip_through_spool() {
+        let large: String = (0..200_000).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        let files = [("info.json", "{}"), ("project/app/big.txt", large.as_str()), ("docs/a.md", "doc")];
+
+        let encryptor = crate::crypto::EncryptWriter::new(Vec::new(), "pw").unwrap();
+        let mut zip = ZipWriter::new(SpoolWriter::new(encryptor));
+        for (name, content) in files {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let spool = zip.finish().unwrap();
+        let (encrypted, _) = spool.into_inner().unwrap().finish().unwrap();
+        assert!(!encrypted.windows(9).any(|w| w == b"info.json"));
+
+        let reader = crate::crypto::DecryptReader::new(Cursor::new(encrypted), "pw").unwrap();
+        let mut archive = ZipArchive::new(reader).unwrap();
+        for (name, content) in files {
+            let mut read = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut read).unwrap();
+            assert_eq!(read, content, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_spool_writer_refuses_to_rewrite_committed_data() {
+        let mut spool = SpoolWriter::new(Vec::new());
+        spool.write_all(b"header").unwrap();
+        spool.seek(SeekFrom::Start(0)).unwrap();
+        spool.write_all(b"HEAD").unwrap();
+        // 回到末尾后前面的数据已交给下层
+        spool.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(spool.inner, b"HEADer");
+        spool.write_all(b"next").unwrap();
+        assert!(spool.seek(SeekFrom::Start(2)).is_err());
+        spool.seek(SeekFrom::Start(6)).unwrap();
+        spool.write_all(b"N").unwrap();
+        assert_eq!(spool.into_inner().unwrap(), b"HEADerNext");
+    }
+
+    #[test]
+    fn test_spool_writer_spills_large_members_to_disk() {
+        // 不可压缩的数据，保证压缩后仍超出内存上限
+        let mut state = 0x2545_f491_u32;
+        let large: Vec<u8> = (0..SPOOL_MEMORY_LIMIT * 3)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        let encryptor = crate::crypto::EncryptWriter::new(Vec::new(), "pw").unwrap();
+        let mut zip = ZipWriter::new(SpoolWriter::new(encryptor));
+        zip.start_file("big.bin", FileOptions::default()).unwrap();
+        zip.write_all(&large).unwrap();
+        zip.start_file("small.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"small").unwrap();
+        let spool = zip.finish().unwrap();
+        let (encrypted, _) = spool.into_inner().unwrap().finish().unwrap();
+
+        let reader = crate::crypto::DecryptReader::new(Cursor::new(encrypted), "pw").unwrap();
+        let mut archive = ZipArchive::new(reader).unwrap();
+        let mut read = Vec::new();
+        archive.by_name("big.bin").unwrap().read_to_end(&mut read).unwrap();
+        assert!(read == large);
+        let mut read = String::new();
+        archive.by_name("small.txt").unwrap().read_to_string(&mut read).unwrap();
+        assert_eq!(read, "small");
+    }
+
+    #[test]
+    fn test_spool_writer_spills_to_encrypted_temp_file() {
+        let total = SPOOL_MEMORY_LIMIT + 3 * crate::crypto::stream::CHUNK_SIZE;
+        let mut spool = SpoolWriter::new(Vec::new());
+        spool.write_all(&vec![1u8; total]).unwrap();
+        assert_eq!(spool.pending.len(), SPOOL_MEMORY_LIMIT);
+        let spill_path = spool.spill.as_ref().unwrap().path.0.clone();
+        // 临时文件中只有密文
+        let spilled = fs::read(&spill_path).unwrap();
+        assert!(spilled.len() > crate::crypto::stream::CHUNK_SIZE);
+        assert!(!spilled.windows(16).any(|w| w == [1u8; 16]));
+        spool.seek(SeekFrom::Start(4)).unwrap();
+        spool.write_all(b"head").unwrap();
+        spool.seek(SeekFrom::Start(SPOOL_MEMORY_LIMIT as u64 + 2)).unwrap();
+        assert!(spool.write_all(b"x").is_err());
+
+        let out = spool.into_inner().unwrap();
+        assert!(!spill_path.exists());
+        assert_eq!(out.len(), total);
+        assert_eq!(&out[..8], &[1, 1, 1, 1, b'h', b'e', b'a', b'd']);
+    }
+
+    #[test]
+    fn test_reencrypt_vault_seals_entries_with_wrapped_data_key() {
+        use crate::crypto::{
+            encryption::decrypt_versioned_with_key, unwrap_data_key, verify_password,
+            wrapped_key_params,
+        };
+
+        let entries = vec![VaultImportEntry {
+            title: "db".to_string(),
+            param_key: Some("DB_PASSWORD".to_string()),
+            param_value: "secret".to_string(),
+            notes: Some("note".to_string()),
+            url: None,
+            category: "password".to_string(),
+        }];
+        let vault = reencrypt_vault(entries, "new master", None).unwrap();
+
+        assert!(verify_password("new master", &vault.password_hash).unwrap());
+        assert_eq!(wrapped_key_params(&vault.wrapped_key), Some(vault.kdf_params));
+        // 条目只需数据密钥即可解密
+        let key = unwrap_data_key(&vault.wrapped_key, "new master").unwrap();
+        let entry = &vault.entries[0];
+        assert_eq!(decrypt_versioned_with_key(&entry.encrypted_value, &key).unwrap(), "secret");
+        let notes = entry.encrypted_notes.as_deref().unwrap();
+        assert_eq!(decrypt_versioned_with_key(notes, &key).unwrap(), "note");
+    }
+}
@@ -0,0 +1,10 @@
+// Legacy data migration report (the migration itself runs in `setup`, see `crate::migrator`)
+
+use crate::migrator::{self, MigrationLayout, MigrationReport};
+
+/// The most recent legacy data migration, or `None` if nothing was ever migrated
+#[tauri::command]
+pub fn get_migration_report(app: tauri::AppHandle) -> Result<Option<MigrationReport>, String> {
+    let layout = MigrationLayout::for_app(&app)?;
+    Ok(migrator::read_log(&layout.db_dir).pop())
+}
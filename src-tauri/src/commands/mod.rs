@@ -1,10 +1,23 @@
 // Commands module
-// pub mod projects;
 pub mod docvault;
 pub mod documents;
 pub mod editor;
 pub mod folder;
 pub mod git;
+pub mod git_branch;
+pub mod git_remote;
+pub mod git_stash;
 pub mod handover;
+pub mod migration;
+pub mod project_analysis;
+pub mod project_discovery;
+pub mod project_settings;
+pub mod projects;
 pub mod terminal;
 pub mod vault;
+pub mod vault_attachments;
+pub mod vault_audit;
+pub mod vault_export;
+pub mod vault_history;
+pub mod vault_import;
+pub mod vault_ssh;
@@ -0,0 +1,1088 @@
+// Project analysis: lines of code per language, toolchain and dependency summary.
+// Results are cached in SQLite; per-file line counts are reused while a file's size
+// and modification time are unchanged, so re-analysis only reads modified files.
+
+use crate::commands::handover::walk_files;
+use crate::commands::project_settings::glob_match;
+use crate::commands::projects::normalize_path;
+use crate::db;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Larger files are counted in size and file totals but not read for lines
+const MAX_LOC_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// File extension and the language it is counted as
+const LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("js", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("cjs", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("py", "Python"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("h", "C/C++ Header"),
+    ("hpp", "C/C++ Header"),
+    ("cpp", "C++"),
+    ("cc", "C++"),
+    ("cs", "C#"),
+    ("swift", "Swift"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("dart", "Dart"),
+    ("scala", "Scala"),
+    ("lua", "Lua"),
+    ("sh", "Shell"),
+    ("ps1", "PowerShell"),
+    ("sql", "SQL"),
+    ("html", "HTML"),
+    ("css", "CSS"),
+    ("scss", "SCSS"),
+    ("less", "LESS"),
+    ("md", "Markdown"),
+    ("json", "JSON"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("toml", "TOML"),
+    ("xml", "XML"),
+];
+
+/// Manifest files inspected for toolchain and dependency information
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "go.mod",
+    "pyproject.toml",
+    "requirements.txt",
+    "CMakeLists.txt",
+    "Makefile",
+];
+
+/// Dependency name (or name prefix up to `/` or `:`) and the framework it indicates
+const FRAMEWORKS: &[(&str, &str)] = &[
+    ("tauri", "Tauri"),
+    ("@tauri-apps/api", "Tauri"),
+    ("vue", "Vue"),
+    ("react", "React"),
+    ("next", "Next.js"),
+    ("nuxt", "Nuxt"),
+    ("svelte", "Svelte"),
+    ("@angular/core", "Angular"),
+    ("electron", "Electron"),
+    ("express", "Express"),
+    ("actix-web", "Actix Web"),
+    ("axum", "Axum"),
+    ("rocket", "Rocket"),
+    ("bevy", "Bevy"),
+    ("django", "Django"),
+    ("flask", "Flask"),
+    ("fastapi", "FastAPI"),
+    ("github.com/gin-gonic/gin", "Gin"),
+    ("github.com/labstack/echo", "Echo"),
+    ("github.com/gofiber/fiber", "Fiber"),
+    ("org.springframework.boot", "Spring Boot"),
+    ("io.quarkus", "Quarkus"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    /// All lines, including blank ones
+    pub lines: u64,
+    /// Non-blank lines
+    pub code: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestInfo {
+    /// Path relative to the project root, with `/` separators
+    pub path: String,
+    pub build_system: Option<String>,
+    pub package_manager: Option<String>,
+    pub dependencies: usize,
+    pub dev_dependencies: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectAnalysis {
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Sorted by non-blank lines, largest first
+    pub languages: Vec<LanguageStats>,
+    pub build_systems: Vec<String>,
+    pub package_managers: Vec<String>,
+    pub frameworks: Vec<String>,
+    pub manifests: Vec<ManifestInfo>,
+    /// Files whose lines were counted in this run; the rest came from the cache
+    pub rescanned_files: usize,
+    pub analyzed_at: String,
+}
+
+/// Line counts of one source file, as stored in `project_analysis_files`
+#[derive(Debug, Clone)]
+struct FileStats {
+    size: i64,
+    modified: i64,
+    language: String,
+    lines: i64,
+    code: i64,
+}
+
+// ---------------- .gitignore ----------------
+
+/// Simplified `.gitignore` line, used when the project is not inside a git repository
+struct IgnorePattern {
+    pattern: String,
+    dir_only: bool,
+    /// Patterns containing `/` match the path relative to the root, others the name
+    anchored: bool,
+}
+
+/// Lines of a root `.gitignore`, without comments and negations
+fn parse_ignore_patterns(content: &str) -> Vec<IgnorePattern> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('!'))
+        .map(|l| {
+            let dir_only = l.ends_with('/');
+            let l = l.trim_end_matches('/');
+            IgnorePattern {
+                anchored: l.contains('/'),
+                pattern: l.trim_start_matches('/').to_string(),
+                dir_only,
+            }
+        })
+        .filter(|p| !p.pattern.is_empty())
+        .collect()
+}
+
+enum GitIgnore {
+    /// Repository containing the project and the project root relative to its workdir
+    Repo(Repository, PathBuf),
+    /// Only the root `.gitignore`; negations are not supported
+    Patterns(Vec<IgnorePattern>),
+}
+
+impl GitIgnore {
+    fn load(root: &Path) -> Self {
+        if let Ok(repo) = Repository::discover(root) {
+            // Compare canonical paths so symlinked roots still resolve inside the workdir
+            let base = repo.workdir().and_then(|workdir| {
+                let workdir = fs::canonicalize(workdir).ok()?;
+                let root = fs::canonicalize(root).ok()?;
+                root.strip_prefix(workdir).ok().map(Path::to_path_buf)
+            });
+            if let Some(base) = base {
+                return GitIgnore::Repo(repo, base);
+            }
+        }
+
+        let content = fs::read_to_string(root.join(".gitignore")).unwrap_or_default();
+        GitIgnore::Patterns(parse_ignore_patterns(&content))
+    }
+
+    fn is_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        match self {
+            GitIgnore::Repo(repo, base) => {
+                let Ok(rel) = path.strip_prefix(root) else {
+                    return false;
+                };
+                repo.is_path_ignored(base.join(rel)).unwrap_or(false)
+            }
+            GitIgnore::Patterns(patterns) => {
+                let Ok(rel) = path.strip_prefix(root) else {
+                    return false;
+                };
+                let rel = rel_string(rel);
+                let name = rel.rsplit('/').next().unwrap_or(&rel);
+                patterns.iter().any(|p| {
+                    (!p.dir_only || is_dir)
+                        && glob_match(&p.pattern, if p.anchored { &rel } else { name })
+                })
+            }
+        }
+    }
+}
+
+fn rel_string(rel: &Path) -> String {
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+// ---------------- Lines of code ----------------
+
+fn language_of(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, language)| *language)
+}
+
+/// `(lines, non-blank lines)`; binary and oversized files count as empty
+fn count_lines(path: &Path, size: u64) -> (i64, i64) {
+    if size > MAX_LOC_FILE_SIZE {
+        return (0, 0);
+    }
+    let Ok(bytes) = fs::read(path) else {
+        return (0, 0);
+    };
+    if bytes.contains(&0) {
+        return (0, 0);
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines = 0;
+    let mut code = 0;
+    for line in text.lines() {
+        lines += 1;
+        if !line.trim().is_empty() {
+            code += 1;
+        }
+    }
+    (lines, code)
+}
+
+// ---------------- Manifests ----------------
+
+#[derive(Default)]
+struct ManifestDeps {
+    build_system: Option<&'static str>,
+    package_manager: Option<&'static str>,
+    deps: Vec<String>,
+    dev_deps: Vec<String>,
+}
+
+impl ManifestDeps {
+    fn push(&mut self, dev: bool, name: String) {
+        if dev {
+            self.dev_deps.push(name);
+        } else {
+            self.deps.push(name);
+        }
+    }
+}
+
+/// Key of a `key = value` TOML line, without quotes
+fn toml_key(line: &str) -> Option<String> {
+    let (key, _) = line.split_once('=')?;
+    let key = key.trim().trim_matches(['"', '\'']);
+    let key = key.split('.').next().unwrap_or(key);
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+/// Brackets still open after `line`, starting from `depth`; quoted text and comments are skipped
+fn open_brackets(line: &str, mut depth: usize) -> usize {
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Name of a Python requirement such as `Django>=4.2; python_version > "3.8"`
+fn python_requirement_name(req: &str) -> Option<String> {
+    let name = req
+        .trim()
+        .split(|c: char| "<>=!~;[ (@".contains(c))
+        .next()?
+        .trim()
+        .to_ascii_lowercase();
+    (!name.is_empty()).then_some(name)
+}
+
+fn parse_cargo(content: &str) -> ManifestDeps {
+    const TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut result = ManifestDeps {
+        build_system: Some("cargo"),
+        package_manager: Some("cargo"),
+        ..Default::default()
+    };
+
+    // None outside dependency tables, Some(true) inside dev-dependencies
+    let mut section: Option<bool> = None;
+    // 多行数组或内联表的续行，例如 `features = [` 之后的行，不是依赖名
+    let mut depth = 0;
+    for line in content.lines().map(str::trim) {
+        if depth > 0 {
+            depth = open_brackets(line, depth);
+            continue;
+        }
+        if line.starts_with('[') {
+            let header = line.trim_matches(['[', ']']).trim();
+            section = None;
+            for table in TABLES {
+                let dev = *table == "dev-dependencies";
+                // `[dependencies]`, `[workspace.dependencies]`, `[target.'cfg(unix)'.dependencies]`
+                if header == *table || header.ends_with(&format!(".{}", table)) {
+                    section = Some(dev);
+                    break;
+                }
+                // `[dependencies.serde]` declares a single dependency
+                let prefix = format!("{}.", table);
+                let name = header.strip_prefix(&prefix).or_else(|| {
+                    header
+                        .find(&format!(".{}", prefix))
+                        .map(|i| &header[i + prefix.len() + 1..])
+                });
+                if let Some(name) = name {
+                    result.push(dev, name.trim_matches(['"', '\'']).to_string());
+                    break;
+                }
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if let (Some(dev), Some(key)) = (section, toml_key(line)) {
+            result.push(dev, key);
+        }
+        depth = open_brackets(line, 0);
+    }
+    result
+}
+
+fn parse_package_json(content: &str, dir: &Path) -> ManifestDeps {
+    let json: serde_json::Value = serde_json::from_str(content).unwrap_or_default();
+    let names = |key: &str| -> Vec<String> {
+        json.get(key)
+            .and_then(|v| v.as_object())
+            .map(|deps| deps.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+
+    let package_manager = [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+        ("package-lock.json", "npm"),
+    ]
+    .iter()
+    .find(|(lock, _)| dir.join(lock).exists())
+    .map_or("npm", |(_, pm)| *pm);
+
+    let deps = names("dependencies");
+    let dev_deps = names("devDependencies");
+    let build_system = ["vite", "webpack", "rollup", "esbuild", "parcel"]
+        .into_iter()
+        .find(|tool| dev_deps.iter().chain(&deps).any(|d| d == tool));
+
+    ManifestDeps {
+        build_system,
+        package_manager: Some(package_manager),
+        deps,
+        dev_deps,
+    }
+}
+
+/// Text between `<tag>` and `</tag>`
+fn xml_value<'a>(block: &'a str, tag: &str) -> Option<&'a str> {
+    let start = block.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = block[start..].find(&format!("</{}>", tag))? + start;
+    Some(block[start..end].trim())
+}
+
+fn parse_pom(content: &str) -> ManifestDeps {
+    let mut result = ManifestDeps {
+        build_system: Some("maven"),
+        package_manager: Some("maven"),
+        ..Default::default()
+    };
+
+    for block in content.split("<dependency>").skip(1) {
+        let block = block.split("</dependency>").next().unwrap_or(block);
+        let name = format!(
+            "{}:{}",
+            xml_value(block, "groupId").unwrap_or_default(),
+            xml_value(block, "artifactId").unwrap_or_default()
+        );
+        result.push(xml_value(block, "scope") == Some("test"), name);
+    }
+    result
+}
+
+fn parse_gradle(content: &str) -> ManifestDeps {
+    const CONFIGURATIONS: &[&str] = &[
+        "implementation",
+        "api",
+        "compileOnly",
+        "runtimeOnly",
+        "kapt",
+        "annotationProcessor",
+        "testImplementation",
+        "testRuntimeOnly",
+        "androidTestImplementation",
+    ];
+    let mut result = ManifestDeps {
+        build_system: Some("gradle"),
+        package_manager: Some("gradle"),
+        ..Default::default()
+    };
+
+    for line in content.lines().map(str::trim) {
+        let Some(config) = CONFIGURATIONS.iter().find(|c| {
+            line.strip_prefix(**c)
+                .is_some_and(|rest| rest.starts_with(['(', ' ']))
+        }) else {
+            continue;
+        };
+        // `implementation("group:artifact:version")` or `implementation libs.foo`
+        let rest = line[config.len()..].trim_start_matches(['(', ' ']);
+        let coordinate = rest.trim_matches(['"', '\'', ')', ' ']);
+        let name = coordinate
+            .rsplit_once(':')
+            .filter(|(ga, _)| ga.contains(':'))
+            .map_or(coordinate, |(ga, _)| ga)
+            .to_string();
+        result.push(config.starts_with("test") || config.starts_with("androidTest"), name);
+    }
+    result
+}
+
+fn parse_go_mod(content: &str) -> ManifestDeps {
+    let mut result = ManifestDeps {
+        build_system: Some("go"),
+        package_manager: Some("go modules"),
+        ..Default::default()
+    };
+
+    let mut in_block = false;
+    for line in content.lines().map(str::trim) {
+        let spec = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if line == "require (" {
+            in_block = true;
+            continue;
+        } else if let Some(rest) = line.strip_prefix("require ") {
+            rest
+        } else {
+            continue;
+        };
+        if let Some(module) = spec.split_whitespace().next().filter(|m| !m.starts_with("//")) {
+            result.deps.push(module.to_string());
+        }
+    }
+    result
+}
+
+fn parse_pyproject(content: &str, dir: &Path) -> ManifestDeps {
+    let mut result = ManifestDeps::default();
+    let mut section = String::new();
+    // Inside a multi-line dependency array; `Some(true)` when it lists dev dependencies
+    let mut array: Option<bool> = None;
+    let mut build_requires = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') && array.is_none() {
+            section = line.trim_matches(['[', ']']).trim().to_string();
+            continue;
+        }
+
+        if let Some(dev) = array {
+            let items = line.trim_end_matches(',').trim_end_matches(']');
+            for item in items.split(',').map(|s| s.trim().trim_matches(['"', '\''])) {
+                if let Some(name) = python_requirement_name(item) {
+                    result.push(dev, name);
+                }
+            }
+            if line.ends_with(']') {
+                array = None;
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+        // PEP 735 groups such as `[dependency-groups] dev = [...]`
+        let dev = section == "dependency-groups";
+
+        if section == "build-system" && key == "requires" {
+            build_requires = value.to_string();
+        } else if (section == "project" && key == "dependencies") || dev {
+            // `dependencies = ["a", "b"]`, possibly spread over several lines
+            if let Some(items) = value.strip_prefix('[') {
+                let closed = items.ends_with(']');
+                for item in items.trim_end_matches(']').split(',') {
+                    if let Some(name) = python_requirement_name(item.trim().trim_matches(['"', '\''])) {
+                        result.push(dev, name);
+                    }
+                }
+                if !closed {
+                    array = Some(dev);
+                }
+            }
+        } else if section == "tool.poetry.dependencies" && key != "python" {
+            result.deps.push(key.to_ascii_lowercase());
+        } else if section == "tool.poetry.dev-dependencies"
+            || (section.starts_with("tool.poetry.group.") && section.ends_with(".dependencies"))
+        {
+            result.dev_deps.push(key.to_ascii_lowercase());
+        }
+    }
+
+    result.package_manager = Some(
+        if dir.join("poetry.lock").exists() || content.contains("[tool.poetry]") {
+            "poetry"
+        } else if dir.join("uv.lock").exists() {
+            "uv"
+        } else if dir.join("pdm.lock").exists() {
+            "pdm"
+        } else {
+            "pip"
+        },
+    );
+    result.build_system = ["poetry-core", "hatchling", "setuptools", "flit-core", "pdm-backend", "maturin"]
+        .into_iter()
+        .find(|backend| build_requires.contains(backend));
+    result
+}
+
+fn parse_requirements(content: &str) -> ManifestDeps {
+    ManifestDeps {
+        package_manager: Some("pip"),
+        deps: content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('-'))
+            .filter_map(python_requirement_name)
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn parse_manifest(name: &str, path: &Path) -> Option<ManifestDeps> {
+    let content = fs::read_to_string(path).ok()?;
+    let dir = path.parent()?;
+    Some(match name {
+        "Cargo.toml" => parse_cargo(&content),
+        "package.json" => parse_package_json(&content, dir),
+        "pom.xml" => parse_pom(&content),
+        "build.gradle" | "build.gradle.kts" => parse_gradle(&content),
+        "go.mod" => parse_go_mod(&content),
+        "pyproject.toml" => parse_pyproject(&content, dir),
+        "requirements.txt" => parse_requirements(&content),
+        "CMakeLists.txt" => ManifestDeps { build_system: Some("cmake"), ..Default::default() },
+        "Makefile" => ManifestDeps { build_system: Some("make"), ..Default::default() },
+        _ => return None,
+    })
+}
+
+fn framework_of(dependency: &str) -> Option<&'static str> {
+    FRAMEWORKS.iter().find_map(|(name, framework)| {
+        let matches = dependency == *name
+            || dependency
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with(['/', ':']));
+        matches.then_some(*framework)
+    })
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+// ---------------- Scan ----------------
+
+fn modified_millis(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+struct ScanResult {
+    analysis: ProjectAnalysis,
+    /// Current stats of every source file, keyed by relative path
+    files: HashMap<String, FileStats>,
+    /// Relative paths whose stats differ from the cache
+    changed: Vec<String>,
+}
+
+/// Walk the project like handover export does (skipping plugin directories and
+/// `.gitignore`d paths), reusing cached line counts for unchanged files
+fn scan_project(root: &Path, path: String, cache: HashMap<String, FileStats>) -> ScanResult {
+    let gitignore = GitIgnore::load(root);
+    let skip = |p: &Path, is_dir: bool| gitignore.is_ignored(root, p, is_dir);
+
+    let mut file_count = 0;
+    let mut total_size = 0;
+    let mut files: HashMap<String, FileStats> = HashMap::new();
+    let mut changed = Vec::new();
+    let mut manifests: Vec<(String, PathBuf)> = Vec::new();
+
+    walk_files(root, true, None, &skip, &mut |entry| {
+        let Ok(metadata) = entry.metadata() else {
+            return;
+        };
+        let path = entry.path();
+        file_count += 1;
+        total_size += metadata.len();
+
+        let rel = path.strip_prefix(root).map(rel_string).unwrap_or_default();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if MANIFESTS.contains(&name.as_str()) {
+            manifests.push((rel.clone(), path.clone()));
+        }
+
+        let Some(language) = language_of(&path) else {
+            return;
+        };
+        let size = metadata.len() as i64;
+        let modified = modified_millis(&metadata);
+        let stats = match cache.get(&rel) {
+            Some(cached) if cached.size == size && cached.modified == modified && cached.language == language => {
+                cached.clone()
+            }
+            _ => {
+                let (lines, code) = count_lines(&path, metadata.len());
+                changed.push(rel.clone());
+                FileStats { size, modified, language: language.to_string(), lines, code }
+            }
+        };
+        files.insert(rel, stats);
+    });
+
+    let mut languages: HashMap<&str, LanguageStats> = HashMap::new();
+    for stats in files.values() {
+        let entry = languages.entry(&stats.language).or_insert_with(|| LanguageStats {
+            language: stats.language.clone(),
+            files: 0,
+            lines: 0,
+            code: 0,
+        });
+        entry.files += 1;
+        entry.lines += stats.lines as u64;
+        entry.code += stats.code as u64;
+    }
+    let mut languages: Vec<LanguageStats> = languages.into_values().collect();
+    languages.sort_by(|a, b| b.code.cmp(&a.code).then_with(|| a.language.cmp(&b.language)));
+
+    manifests.sort();
+    let mut analysis = ProjectAnalysis {
+        path,
+        file_count,
+        total_size,
+        languages,
+        build_systems: Vec::new(),
+        package_managers: Vec::new(),
+        frameworks: Vec::new(),
+        manifests: Vec::new(),
+        rescanned_files: changed.len(),
+        analyzed_at: chrono::Utc::now().to_rfc3339(),
+    };
+    for (rel, path) in manifests {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let Some(deps) = parse_manifest(&name, &path) else {
+            continue;
+        };
+        if let Some(build_system) = deps.build_system {
+            push_unique(&mut analysis.build_systems, build_system);
+        }
+        if let Some(package_manager) = deps.package_manager {
+            push_unique(&mut analysis.package_managers, package_manager);
+        }
+        for framework in deps.deps.iter().chain(&deps.dev_deps).filter_map(|d| framework_of(d)) {
+            push_unique(&mut analysis.frameworks, framework);
+        }
+        analysis.manifests.push(ManifestInfo {
+            path: rel,
+            build_system: deps.build_system.map(str::to_string),
+            package_manager: deps.package_manager.map(str::to_string),
+            dependencies: deps.deps.len(),
+            dev_dependencies: deps.dev_deps.len(),
+        });
+    }
+
+    ScanResult { analysis, files, changed }
+}
+
+// ---------------- Cache ----------------
+
+async fn load_file_cache(pool: &SqlitePool, path: &str) -> Result<HashMap<String, FileStats>, String> {
+    let rows = sqlx::query(
+        "SELECT rel_path, size, modified, language, lines, code FROM project_analysis_files WHERE project_path = ?",
+    )
+    .bind(path)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load analysis cache: {}", e))?;
+
+    let get = |e: sqlx::Error| format!("Failed to read analysis cache: {}", e);
+    rows.iter()
+        .map(|row| {
+            Ok((
+                row.try_get("rel_path").map_err(get)?,
+                FileStats {
+                    size: row.try_get("size").map_err(get)?,
+                    modified: row.try_get("modified").map_err(get)?,
+                    language: row.try_get("language").map_err(get)?,
+                    lines: row.try_get("lines").map_err(get)?,
+                    code: row.try_get("code").map_err(get)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+async fn save_scan(
+    pool: &SqlitePool,
+    scan: &ScanResult,
+    removed: &[String],
+) -> Result<(), String> {
+    let db_err = |e: sqlx::Error| format!("Failed to save analysis cache: {}", e);
+    let path = &scan.analysis.path;
+    let summary = serde_json::to_string(&scan.analysis).map_err(|e| e.to_string())?;
+
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    for rel in removed {
+        sqlx::query("DELETE FROM project_analysis_files WHERE project_path = ? AND rel_path = ?")
+            .bind(path)
+            .bind(rel)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    }
+    for rel in &scan.changed {
+        let stats = &scan.files[rel];
+        sqlx::query(
+            "INSERT OR REPLACE INTO project_analysis_files \
+             (project_path, rel_path, size, modified, language, lines, code) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(path)
+        .bind(rel)
+        .bind(stats.size)
+        .bind(stats.modified)
+        .bind(&stats.language)
+        .bind(stats.lines)
+        .bind(stats.code)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
+    sqlx::query(
+        "INSERT OR REPLACE INTO project_analysis (path, summary, analyzed_at) VALUES (?, ?, CURRENT_TIMESTAMP)",
+    )
+    .bind(path)
+    .bind(&summary)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)
+}
+
+/// Last analysis stored for a project path, if any
+pub(crate) async fn cached_analysis(
+    pool: &SqlitePool,
+    path: &str,
+) -> Result<Option<ProjectAnalysis>, String> {
+    let summary: Option<String> = sqlx::query_scalar("SELECT summary FROM project_analysis WHERE path = ?")
+        .bind(normalize_path(path))
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database query failed: {}", e))?;
+
+    // 旧版本写入的摘要结构不兼容时当作没有缓存
+    Ok(summary.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+/// Analyze a project directory: lines of code per language (honoring `.gitignore`),
+/// build systems, package managers, frameworks and dependency counts from manifests,
+/// total size and file count. Only files changed since the last run are re-read.
+#[tauri::command]
+pub async fn analyze_project(app: tauri::AppHandle, path: String) -> Result<ProjectAnalysis, String> {
+    let path = normalize_path(&path);
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let pool = db::pool(&app).await?;
+    let cache = load_file_cache(&pool, &path).await?;
+    let cached_paths: HashSet<String> = cache.keys().cloned().collect();
+
+    // 遍历目录和读取文件都是阻塞 IO
+    let root = PathBuf::from(&path);
+    let scan = tauri::async_runtime::spawn_blocking(move || scan_project(&root, path, cache))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let removed: Vec<String> = cached_paths
+        .into_iter()
+        .filter(|rel| !scan.files.contains_key(rel))
+        .collect();
+    save_scan(&pool, &scan, &removed).await?;
+
+    Ok(scan.analysis)
+}
+
+/// The cached analysis of a project path without rescanning; `None` if it was never analyzed
+#[tauri::command]
+pub async fn get_project_analysis(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Option<ProjectAnalysis>, String> {
+    let pool = db::pool(&app).await?;
+    cached_analysis(&pool, &path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(deps: &[String]) -> Vec<&str> {
+        deps.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_parse_cargo() {
+        let deps = parse_cargo(
+            r#"
+[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+"tokio" = "1"
+sqlx = { version = "0.8", features = [
+    "sqlite",
+    "runtime-tokio",
+], default-features = false }
+# commented = "1"
+toml.workspace = true
+
+[dependencies.git2]
+version = "0.19"
+features = ["vendored-openssl"]
+
+[target.'cfg(windows)'.dependencies]
+windows = "0.58"
+
+[dev-dependencies]
+tempfile = "3"
+
+[build-dependencies]
+tauri-build = { version = "2" }
+
+[features]
+default = ["custom-protocol"]
+"#,
+        );
+        assert_eq!(deps.build_system, Some("cargo"));
+        assert_eq!(deps.package_manager, Some("cargo"));
+        assert_eq!(
+            names(&deps.deps),
+            vec!["serde", "tokio", "sqlx", "toml", "git2", "windows", "tauri-build"]
+        );
+        assert_eq!(names(&deps.dev_deps), vec!["tempfile"]);
+    }
+
+    #[test]
+    fn test_parse_pom() {
+        let deps = parse_pom(
+            r#"<project>
+  <groupId>com.example</groupId>
+  <dependencies>
+    <dependency>
+      <groupId>org.springframework.boot</groupId>
+      <artifactId>spring-boot-starter-web</artifactId>
+    </dependency>
+    <dependency>
+      <groupId>org.junit.jupiter</groupId>
+      <artifactId>junit-jupiter</artifactId>
+      <version>5.10.0</version>
+      <scope>test</scope>
+    </dependency>
+  </dependencies>
+</project>"#,
+        );
+        assert_eq!(deps.build_system, Some("maven"));
+        assert_eq!(
+            names(&deps.deps),
+            vec!["org.springframework.boot:spring-boot-starter-web"]
+        );
+        assert_eq!(names(&deps.dev_deps), vec!["org.junit.jupiter:junit-jupiter"]);
+    }
+
+    #[test]
+    fn test_parse_gradle() {
+        let deps = parse_gradle(
+            r#"
+plugins { id("org.springframework.boot") version "3.2.0" }
+dependencies {
+    implementation("org.springframework.boot:spring-boot-starter-web:3.2.0")
+    implementation 'com.google.guava:guava:32.1.3-jre'
+    api(libs.kotlinx.coroutines)
+    compileOnly "org.projectlombok:lombok"
+    implementationDetail("not:a:dependency")
+    testImplementation("org.junit.jupiter:junit-jupiter:5.10.0")
+    androidTestImplementation 'androidx.test:runner:1.5.2'
+}
+"#,
+        );
+        assert_eq!(deps.build_system, Some("gradle"));
+        assert_eq!(
+            names(&deps.deps),
+            vec![
+                "org.springframework.boot:spring-boot-starter-web",
+                "com.google.guava:guava",
+                "libs.kotlinx.coroutines",
+                "org.projectlombok:lombok",
+            ]
+        );
+        assert_eq!(
+            names(&deps.dev_deps),
+            vec!["org.junit.jupiter:junit-jupiter", "androidx.test:runner"]
+        );
+    }
+
+    #[test]
+    fn test_parse_go_mod() {
+        let deps = parse_go_mod(
+            r#"module example.com/app
+
+go 1.22
+
+require github.com/gin-gonic/gin v1.9.1
+
+require (
+	// comment
+	github.com/stretchr/testify v1.8.4
+	golang.org/x/net v0.20.0 // indirect
+)
+
+replace example.com/old => ../old
+"#,
+        );
+        assert_eq!(deps.package_manager, Some("go modules"));
+        assert_eq!(
+            names(&deps.deps),
+            vec!["github.com/gin-gonic/gin", "github.com/stretchr/testify", "golang.org/x/net"]
+        );
+        assert!(deps.dev_deps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_pyproject_pep621() {
+        let dir = Path::new("/nonexistent/pomo-analysis");
+        let deps = parse_pyproject(
+            r#"
+[build-system]
+requires = ["hatchling"]
+build-backend = "hatchling.build"
+
+[project]
+name = "demo"
+dependencies = ["FastAPI>=0.110", "uvicorn[standard]"]
+
+[dependency-groups]
+dev = [
+    "pytest>=8",
+    "ruff",
+]
+"#,
+            dir,
+        );
+        assert_eq!(deps.build_system, Some("hatchling"));
+        assert_eq!(deps.package_manager, Some("pip"));
+        assert_eq!(names(&deps.deps), vec!["fastapi", "uvicorn"]);
+        assert_eq!(names(&deps.dev_deps), vec!["pytest", "ruff"]);
+    }
+
+    #[test]
+    fn test_parse_pyproject_poetry() {
+        let dir = Path::new("/nonexistent/pomo-analysis");
+        let deps = parse_pyproject(
+            r#"
+[tool.poetry]
+name = "demo"
+
+[tool.poetry.dependencies]
+python = "^3.11"
+Django = "^5.0"
+
+[tool.poetry.group.test.dependencies]
+pytest = "^8.0"
+
+[build-system]
+requires = ["poetry-core"]
+"#,
+            dir,
+        );
+        assert_eq!(deps.build_system, Some("poetry-core"));
+        assert_eq!(deps.package_manager, Some("poetry"));
+        assert_eq!(names(&deps.deps), vec!["django"]);
+        assert_eq!(names(&deps.dev_deps), vec!["pytest"]);
+    }
+
+    #[test]
+    fn test_parse_requirements() {
+        let deps = parse_requirements(
+            "# web\nFlask==3.0.0\n-r base.txt\n\nrequests[socks] >= 2.31 ; python_version > \"3.8\"\n\
+             mypkg @ https://example.com/mypkg.whl\n",
+        );
+        assert_eq!(deps.package_manager, Some("pip"));
+        assert_eq!(names(&deps.deps), vec!["flask", "requests", "mypkg"]);
+    }
+
+    #[test]
+    fn test_gitignore_patterns() {
+        let ignore = GitIgnore::Patterns(parse_ignore_patterns(
+            "# build output\n/dist\nbuild/\n*.log\n!keep.log\ndocs/generated\n\n/\n",
+        ));
+        let root = Path::new("/project");
+        let ignored = |rel: &str, is_dir: bool| ignore.is_ignored(root, &root.join(rel), is_dir);
+
+        // `/dist` 只匹配根目录下的路径
+        assert!(ignored("dist", true));
+        assert!(!ignored("src/dist", true));
+        // `build/` 只匹配目录，任意层级
+        assert!(ignored("build", true));
+        assert!(ignored("app/build", true));
+        assert!(!ignored("build", false));
+        // 通配符匹配文件名；不支持取反
+        assert!(ignored("app/debug.log", false));
+        assert!(ignored("keep.log", false));
+        assert!(ignored("docs/generated", true));
+        assert!(!ignored("src/docs/generated", true));
+        assert!(!ignored("src/main.rs", false));
+        assert!(!ignore.is_ignored(root, Path::new("/elsewhere/a.log"), false));
+    }
+}
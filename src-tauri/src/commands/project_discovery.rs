@@ -0,0 +1,249 @@
+// Project discovery: scan workspace roots for directories that look like projects
+
+use crate::commands::handover::IGNORE_DIRS;
+use crate::commands::projects::{list_projects, normalize_path};
+use crate::db;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_MAX_DEPTH: usize = 3;
+const MAX_DEPTH_LIMIT: usize = 10;
+
+/// Marker file or directory and the stack it indicates
+const PROJECT_MARKERS: &[(&str, &str)] = &[
+    (".git", "git"),
+    ("Cargo.toml", "rust"),
+    ("package.json", "node"),
+    ("pom.xml", "maven"),
+    ("build.gradle", "gradle"),
+    ("build.gradle.kts", "gradle"),
+    ("go.mod", "go"),
+    ("pyproject.toml", "python"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscoveredProject {
+    pub name: String,
+    pub path: String,
+    /// Stacks detected from the markers, e.g. `["git", "rust"]`
+    pub stack: Vec<String>,
+    /// URL of `origin`, or of the first remote when there is no `origin`
+    pub git_remote: Option<String>,
+    /// Id of the registered project with this path, if any
+    pub registered_id: Option<i64>,
+}
+
+fn detect_stack(dir: &Path) -> Vec<String> {
+    let mut stack: Vec<String> = Vec::new();
+    for (marker, name) in PROJECT_MARKERS {
+        if dir.join(marker).exists() && !stack.iter().any(|s| s == name) {
+            stack.push(name.to_string());
+        }
+    }
+    stack
+}
+
+fn git_remote_url(dir: &Path) -> Option<String> {
+    let repo = Repository::open(dir).ok()?;
+    let remote = match repo.find_remote("origin") {
+        Ok(remote) => remote,
+        Err(_) => {
+            let names = repo.remotes().ok()?;
+            let first = names.iter().flatten().next()?;
+            repo.find_remote(first).ok()?
+        }
+    };
+    remote.url().map(str::to_string)
+}
+
+/// Depth-first walk; a directory with a marker is a project and is not descended into,
+/// so packages inside a repository are not reported separately
+fn walk(dir: &Path, depth: usize, max_depth: usize, found: &mut Vec<(PathBuf, Vec<String>)>) {
+    let stack = detect_stack(dir);
+    if !stack.is_empty() {
+        found.push((dir.to_path_buf(), stack));
+        return;
+    }
+    if depth >= max_depth {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir() && !t.is_symlink()))
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            !name.starts_with('.') && !IGNORE_DIRS.contains(&name.as_str())
+        })
+        .map(|e| e.path())
+        .collect();
+    children.sort();
+
+    for child in children {
+        walk(&child, depth + 1, max_depth, found);
+    }
+}
+
+/// Walk every root and describe each project directory once.
+/// `registered` maps normalized paths of registered projects to their ids
+fn discover(
+    roots: &[String],
+    max_depth: usize,
+    registered: &HashMap<String, i64>,
+) -> Vec<DiscoveredProject> {
+    let mut found = Vec::new();
+    for root in roots {
+        walk(Path::new(root), 0, max_depth, &mut found);
+    }
+
+    let mut seen = HashSet::new();
+    let mut projects = Vec::new();
+    for (dir, stack) in found {
+        let path = normalize_path(&dir.to_string_lossy());
+        // Overlapping roots find the same directory twice
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        let git_remote = if stack.iter().any(|s| s == "git") {
+            git_remote_url(&dir)
+        } else {
+            None
+        };
+        projects.push(DiscoveredProject {
+            name: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone()),
+            registered_id: registered.get(&path).copied(),
+            path,
+            stack,
+            git_remote,
+        });
+    }
+    projects
+}
+
+/// Scan `roots` up to `max_depth` levels (default 3) for project directories,
+/// detected by markers such as `.git`, `Cargo.toml` or `package.json`
+#[tauri::command]
+pub async fn discover_projects(
+    app: tauri::AppHandle,
+    roots: Vec<String>,
+    max_depth: Option<usize>,
+) -> Result<Vec<DiscoveredProject>, String> {
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH).min(MAX_DEPTH_LIMIT);
+    for root in &roots {
+        if !Path::new(root).is_dir() {
+            return Err(format!("Not a directory: {}", root));
+        }
+    }
+
+    let pool = db::pool(&app).await?;
+    let registered: HashMap<String, i64> = list_projects(&pool)
+        .await?
+        .into_iter()
+        .map(|p| (normalize_path(&p.path), p.id))
+        .collect();
+
+    // 目录遍历和读取 git 配置都是阻塞 IO
+    tauri::async_runtime::spawn_blocking(move || discover(&roots, max_depth, &registered))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    fn paths(projects: &[DiscoveredProject]) -> Vec<&str> {
+        projects.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_walk_detects_markers_and_skips_ignored_dirs() {
+        let root = temp_dir("discovery", "walk");
+        let repo = root.join("app");
+        Repository::init(&repo).unwrap();
+        touch(&repo.join("Cargo.toml"));
+        touch(&repo.join("package.json"));
+        // 项目内部的子包不单独上报
+        touch(&repo.join("crates/inner/Cargo.toml"));
+        touch(&root.join("group/service/go.mod"));
+        touch(&root.join("group/web/build.gradle.kts"));
+        touch(&root.join("node_modules/dep/package.json"));
+        touch(&root.join("target/debug/Cargo.toml"));
+        touch(&root.join(".cache/tool/pyproject.toml"));
+        touch(&root.join("empty/notes.txt"));
+
+        let mut found = Vec::new();
+        walk(&root, 0, DEFAULT_MAX_DEPTH, &mut found);
+        let found: Vec<(PathBuf, Vec<&str>)> = found
+            .iter()
+            .map(|(dir, stack)| (dir.clone(), stack.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (repo.clone(), vec!["git", "rust", "node"]),
+                (root.join("group/service"), vec!["go"]),
+                (root.join("group/web"), vec!["gradle"]),
+            ]
+        );
+
+        // 超过最大深度的目录不再遍历
+        let mut shallow = Vec::new();
+        walk(&root, 0, 1, &mut shallow);
+        assert_eq!(shallow.len(), 1);
+        assert_eq!(shallow[0].0, repo);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_discover_dedups_roots_and_matches_registered_projects() {
+        let root = temp_dir("discovery", "discover");
+        let repo = root.join("group/app");
+        let repo_path = repo.to_string_lossy().into_owned();
+        let remote = "https://example.com/app.git";
+        Repository::init(&repo).unwrap().remote("upstream", remote).unwrap();
+        touch(&root.join("group/lib/pom.xml"));
+
+        // 已登记的路径带有末尾分隔符，按 normalize_path 匹配
+        let registered = HashMap::from([(normalize_path(&format!("{}/", repo_path)), 7)]);
+        let roots = vec![
+            root.to_string_lossy().into_owned(),
+            format!("{}/", root.join("group").to_string_lossy()),
+        ];
+
+        let projects = discover(&roots, DEFAULT_MAX_DEPTH, &registered);
+        let lib_path = root.join("group/lib").to_string_lossy().into_owned();
+        assert_eq!(paths(&projects), vec![repo_path.as_str(), lib_path.as_str()]);
+
+        let app = &projects[0];
+        assert_eq!(app.name, "app");
+        assert_eq!(app.stack, vec!["git"]);
+        assert_eq!(app.git_remote.as_deref(), Some(remote));
+        assert_eq!(app.registered_id, Some(7));
+
+        let lib = &projects[1];
+        assert_eq!(lib.stack, vec!["maven"]);
+        assert_eq!(lib.git_remote, None);
+        assert_eq!(lib.registered_id, None);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
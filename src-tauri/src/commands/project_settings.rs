@@ -0,0 +1,419 @@
+// Typed per-project settings, stored as JSON in `projects.settings`
+
+use crate::db;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+/// Bump together with a new entry in `MIGRATIONS`
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
+
+const DEFAULT_GIT_REMOTE: &str = "origin";
+const MAX_IGNORE_PATTERNS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSettings {
+    pub schema_version: u32,
+    /// Editor executable or app name for "open in editor"; `None` uses the app-wide editor
+    pub editor_path: Option<String>,
+    /// Command used to open a terminal in the project; `None` uses the system terminal
+    pub terminal_command: Option<String>,
+    /// Names skipped by handover export in addition to the built-in plugin directories.
+    /// `*` and `?` wildcards are supported, e.g. `*.log`, `dist`
+    pub handover_ignore_patterns: Vec<String>,
+    /// Remote used for fetch/pull/push when the branch has no upstream
+    pub default_git_remote: String,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            editor_path: None,
+            terminal_command: None,
+            handover_ignore_patterns: Vec::new(),
+            default_git_remote: DEFAULT_GIT_REMOTE.to_string(),
+        }
+    }
+}
+
+type SettingsMigration = fn(Map<String, Value>) -> Map<String, Value>;
+
+/// Forward migrations; `MIGRATIONS[n]` upgrades schema version `n` to `n + 1`
+const MIGRATIONS: &[SettingsMigration] = &[migrate_v0_to_v1];
+
+/// Version 0 is the free-form JSON written by the frontend before settings were typed:
+/// keep the known keys (camelCase or snake_case) and drop everything else
+fn migrate_v0_to_v1(old: Map<String, Value>) -> Map<String, Value> {
+    let pick = |keys: &[&str]| keys.iter().find_map(|k| old.get(*k).cloned());
+    let mut new = Map::new();
+
+    if let Some(v) = pick(&["editor_path", "editorPath", "editor"]).filter(Value::is_string) {
+        new.insert("editor_path".into(), v);
+    }
+    if let Some(v) = pick(&["terminal_command", "terminalCommand", "terminal"]).filter(Value::is_string) {
+        new.insert("terminal_command".into(), v);
+    }
+    if let Some(v) = pick(&["default_git_remote", "defaultGitRemote", "remote"]).filter(Value::is_string) {
+        new.insert("default_git_remote".into(), v);
+    }
+
+    // 旧数据中的忽略规则可能是数组，也可能是逗号或换行分隔的字符串
+    let patterns = match pick(&["handover_ignore_patterns", "handoverIgnorePatterns", "ignore"]) {
+        Some(Value::Array(items)) => items.into_iter().filter(Value::is_string).collect(),
+        Some(Value::String(s)) => s
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| Value::String(p.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    new.insert("handover_ignore_patterns".into(), Value::Array(patterns));
+
+    new
+}
+
+fn schema_version_of(map: &Map<String, Value>) -> u64 {
+    map.get("schema_version").and_then(Value::as_u64).unwrap_or(0)
+}
+
+fn newer_schema_error(version: u64) -> String {
+    format!(
+        "Project settings use schema version {}, newer than this app supports ({})",
+        version, SETTINGS_SCHEMA_VERSION
+    )
+}
+
+/// Upgrade stored settings JSON to the current schema version
+pub fn migrate_settings(value: Value) -> Result<Value, String> {
+    let Value::Object(mut map) = value else {
+        return Err("Project settings must be a JSON object".to_string());
+    };
+
+    let mut version = schema_version_of(&map);
+    if version > u64::from(SETTINGS_SCHEMA_VERSION) {
+        return Err(newer_schema_error(version));
+    }
+
+    while version < u64::from(SETTINGS_SCHEMA_VERSION) {
+        map = MIGRATIONS[version as usize](map);
+        version += 1;
+        map.insert("schema_version".into(), Value::from(version));
+    }
+    Ok(Value::Object(map))
+}
+
+impl ProjectSettings {
+    /// Parse the `projects.settings` column, migrating older schemas.
+    /// Unreadable settings fall back to defaults so the project itself still loads.
+    pub fn from_stored(raw: Option<&str>) -> Self {
+        Self::parse_stored(raw).unwrap_or_default()
+    }
+
+    /// Like `from_stored`, but settings from a newer schema or that can't be read
+    /// are an error instead of being replaced by defaults
+    pub fn parse_stored(raw: Option<&str>) -> Result<Self, String> {
+        let Some(raw) = raw else {
+            return Ok(Self::default());
+        };
+        let value = serde_json::from_str::<Value>(raw)
+            .map_err(|e| format!("Stored project settings are not valid JSON: {}", e))?;
+        serde_json::from_value(migrate_settings(value)?)
+            .map_err(|e| format!("Stored project settings are invalid: {}", e))
+    }
+
+    /// Settings passed around as JSON, e.g. in a handover package: an object, or the
+    /// JSON string that older versions stored
+    pub fn from_json_value(value: &Value) -> Self {
+        match value {
+            Value::String(s) => Self::from_stored(Some(s)),
+            other => Self::from_stored(Some(&other.to_string())),
+        }
+    }
+
+    /// Trim values and reject anything that is not a usable setting
+    pub fn validate(mut self) -> Result<Self, String> {
+        if self.schema_version != SETTINGS_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported settings schema version {}, expected {}",
+                self.schema_version, SETTINGS_SCHEMA_VERSION
+            ));
+        }
+
+        self.editor_path = non_empty(self.editor_path);
+        if let Some(editor) = &self.editor_path {
+            check_single_line("Editor path", editor)?;
+            // A bare name is looked up on PATH (or as an app name on macOS)
+            if editor.contains(['/', '\\']) && !Path::new(editor).exists() {
+                return Err(format!("Editor not found: {}", editor));
+            }
+        }
+
+        self.terminal_command = non_empty(self.terminal_command);
+        if let Some(command) = &self.terminal_command {
+            check_single_line("Terminal command", command)?;
+        }
+
+        let patterns = std::mem::take(&mut self.handover_ignore_patterns);
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            check_single_line("Ignore pattern", pattern)?;
+            if pattern.contains(['/', '\\']) {
+                return Err(format!(
+                    "Ignore pattern must match a single file or directory name: {}",
+                    pattern
+                ));
+            }
+            if !self.handover_ignore_patterns.iter().any(|p| p == pattern) {
+                self.handover_ignore_patterns.push(pattern.to_string());
+            }
+        }
+        if self.handover_ignore_patterns.len() > MAX_IGNORE_PATTERNS {
+            return Err(format!("At most {} ignore patterns are allowed", MAX_IGNORE_PATTERNS));
+        }
+
+        self.default_git_remote = self.default_git_remote.trim().to_string();
+        if !git2::Remote::is_valid_name(&self.default_git_remote) {
+            return Err(format!("Invalid git remote name: {}", self.default_git_remote));
+        }
+
+        Ok(self)
+    }
+
+    /// Whether handover export skips a file or directory with this name
+    pub fn is_handover_ignored(&self, name: &str) -> bool {
+        self.handover_ignore_patterns
+            .iter()
+            .any(|p| glob_match(p, name))
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn check_single_line(label: &str, value: &str) -> Result<(), String> {
+    if value.chars().any(char::is_control) {
+        return Err(format!("{} must not contain control characters", label));
+    }
+    Ok(())
+}
+
+/// Match `name` against a pattern with `*` (any run) and `?` (one character)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position after the last `*` and the name index it is currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi + 1, ni));
+            pi += 1;
+        } else if let Some((star_pi, star_ni)) = star {
+            // Let the last `*` swallow one more character
+            pi = star_pi;
+            ni = star_ni + 1;
+            star = Some((star_pi, star_ni + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Raw `projects.settings` column of a project
+async fn stored_settings(pool: &SqlitePool, project_id: i64) -> Result<Option<String>, String> {
+    sqlx::query_scalar("SELECT settings FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database query failed: {}", e))?
+        .ok_or_else(|| format!("Project {} not found", project_id))
+}
+
+/// Refuse to overwrite settings saved by a newer version of the app
+pub(crate) async fn ensure_not_newer(pool: &SqlitePool, project_id: i64) -> Result<(), String> {
+    let stored_version = stored_settings(pool, project_id)
+        .await?
+        .and_then(|s| serde_json::from_str::<Map<String, Value>>(&s).ok())
+        .map_or(0, |m| schema_version_of(&m));
+    if stored_version > u64::from(SETTINGS_SCHEMA_VERSION) {
+        return Err(newer_schema_error(stored_version));
+    }
+    Ok(())
+}
+
+/// Settings of a project, migrated to the current schema. Unlike the project list,
+/// settings that can't be read are reported so the editor never shows defaults in
+/// their place.
+#[tauri::command]
+pub async fn get_project_settings(
+    app: tauri::AppHandle,
+    project_id: i64,
+) -> Result<ProjectSettings, String> {
+    let pool = db::pool(&app).await?;
+    ProjectSettings::parse_stored(stored_settings(&pool, project_id).await?.as_deref())
+}
+
+/// Replace the settings of a project. Unknown keys, wrong types and invalid values
+/// are rejected rather than stored.
+#[tauri::command]
+pub async fn update_project_settings(
+    app: tauri::AppHandle,
+    project_id: i64,
+    settings: Value,
+) -> Result<ProjectSettings, String> {
+    let pool = db::pool(&app).await?;
+
+    let settings: ProjectSettings = serde_json::from_value(settings)
+        .map_err(|e| format!("Invalid project settings: {}", e))?;
+    let settings = settings.validate()?;
+
+    ensure_not_newer(&pool, project_id).await?;
+
+    let json = serde_json::to_string(&settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    sqlx::query("UPDATE projects SET settings = ? WHERE id = ?")
+        .bind(&json)
+        .bind(project_id)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to update project settings: {}", e))?;
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_v0_camel_case_and_string_list() {
+        let stored = json!({
+            "editorPath": "code",
+            "terminalCommand": "wezterm",
+            "defaultGitRemote": "upstream",
+            "handoverIgnorePatterns": "*.log, dist\n\nnode_modules",
+            "theme": "dark"
+        });
+
+        let settings = ProjectSettings::from_stored(Some(&stored.to_string()));
+        assert_eq!(settings.schema_version, SETTINGS_SCHEMA_VERSION);
+        assert_eq!(settings.editor_path.as_deref(), Some("code"));
+        assert_eq!(settings.terminal_command.as_deref(), Some("wezterm"));
+        assert_eq!(settings.default_git_remote, "upstream");
+        assert_eq!(settings.handover_ignore_patterns, vec!["*.log", "dist", "node_modules"]);
+    }
+
+    #[test]
+    fn test_migrate_v0_array_and_wrong_types() {
+        let migrated = migrate_settings(json!({
+            "ignore": ["target", 3, "*.tmp"],
+            "editor": 42
+        }))
+        .unwrap();
+        assert_eq!(
+            migrated,
+            json!({
+                "schema_version": 1,
+                "handover_ignore_patterns": ["target", "*.tmp"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_schema() {
+        let newer = SETTINGS_SCHEMA_VERSION + 1;
+        let err = migrate_settings(json!({ "schema_version": newer })).unwrap_err();
+        assert!(err.contains("newer than this app supports"), "{}", err);
+        assert!(migrate_settings(json!(["not", "an", "object"])).is_err());
+
+        // 列表加载时回退到默认值，项目本身仍可加载；单独读取设置时报错
+        let stored = json!({ "schema_version": newer, "editor_path": "code" }).to_string();
+        assert_eq!(ProjectSettings::from_stored(Some(&stored)), ProjectSettings::default());
+        let err = ProjectSettings::parse_stored(Some(&stored)).unwrap_err();
+        assert!(err.contains("newer than this app supports"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_stored_reports_unreadable_settings() {
+        assert_eq!(ProjectSettings::parse_stored(None).unwrap(), ProjectSettings::default());
+        let v0 = json!({ "editorPath": "code" }).to_string();
+        let settings = ProjectSettings::parse_stored(Some(&v0)).unwrap();
+        assert_eq!(settings.editor_path.as_deref(), Some("code"));
+
+        for raw in ["{not json", "[1, 2]", r#"{"schema_version": 1, "editor_path": 3}"#] {
+            assert!(ProjectSettings::parse_stored(Some(raw)).is_err(), "{}", raw);
+            assert_eq!(ProjectSettings::from_stored(Some(raw)), ProjectSettings::default());
+        }
+    }
+
+    #[test]
+    fn test_glob_match_wildcards() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(glob_match("*.log", ".log"));
+        assert!(!glob_match("*.log", "debug.log.1"));
+        assert!(glob_match("dist", "dist"));
+        assert!(!glob_match("dist", "dist2"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file.txt"));
+        assert!(glob_match("a*b*c", "aXXbYYbZc"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**", "anything"));
+        assert!(glob_match("构建*", "构建产物"));
+    }
+
+    #[test]
+    fn test_validate_trims_and_dedups() {
+        let settings = ProjectSettings {
+            editor_path: Some("  ".to_string()),
+            terminal_command: Some(" wezterm start ".to_string()),
+            handover_ignore_patterns: vec![" *.log".into(), "".into(), "*.log".into()],
+            default_git_remote: " origin ".to_string(),
+            ..ProjectSettings::default()
+        }
+        .validate()
+        .unwrap();
+        assert_eq!(settings.editor_path, None);
+        assert_eq!(settings.terminal_command.as_deref(), Some("wezterm start"));
+        assert_eq!(settings.handover_ignore_patterns, vec!["*.log"]);
+        assert_eq!(settings.default_git_remote, "origin");
+    }
+
+    #[test]
+    fn test_validate_rejects_control_chars_and_separators() {
+        let with = |f: fn(&mut ProjectSettings)| {
+            let mut settings = ProjectSettings::default();
+            f(&mut settings);
+            settings.validate().unwrap_err()
+        };
+
+        let err = with(|s| s.terminal_command = Some("wezterm\tstart".into()));
+        assert!(err.contains("control characters"), "{}", err);
+        let err = with(|s| s.editor_path = Some("co\u{7}de".into()));
+        assert!(err.contains("control characters"), "{}", err);
+        let err = with(|s| s.handover_ignore_patterns = vec!["a\nb".into()]);
+        assert!(err.contains("control characters"), "{}", err);
+
+        let err = with(|s| s.handover_ignore_patterns = vec!["build/out".into()]);
+        assert!(err.contains("single file or directory name"), "{}", err);
+        let err = with(|s| s.handover_ignore_patterns = vec!["build\\out".into()]);
+        assert!(err.contains("single file or directory name"), "{}", err);
+
+        let err = with(|s| s.default_git_remote = "bad remote".into());
+        assert!(err.contains("Invalid git remote name"), "{}", err);
+        let err = with(|s| s.schema_version = 0);
+        assert!(err.contains("Unsupported settings schema version"), "{}", err);
+    }
+}
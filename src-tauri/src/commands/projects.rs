@@ -1,6 +1,23 @@
 // Project management commands
+// Project repository over the app SQLite database (see `crate::db`)
+
+use crate::commands::docvault::get_docvault_dir;
+use crate::commands::documents::get_document_dir;
+use crate::commands::project_settings::{ProjectSettings, ensure_not_newer};
+use crate::commands::vault::lock_project_sessions;
+use crate::commands::vault_attachments::get_project_attachments_dir;
+use crate::db;
 use serde::{Deserialize, Serialize};
-use tauri::Manager;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_COLOR: &str = "#FF6B9D";
+
+/// Timestamps are cast to TEXT so they decode as `String` whatever their declared type
+const PROJECT_COLUMNS: &str = "id, name, path, description, color, \
+    CAST(created_at AS TEXT) AS created_at, CAST(last_accessed AS TEXT) AS last_accessed, settings";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
@@ -11,7 +28,7 @@ pub struct Project {
     pub color: String,
     pub created_at: String,
     pub last_accessed: String,
-    pub settings: String,
+    pub settings: ProjectSettings,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,79 +37,478 @@ pub struct NewProject {
     pub path: String,
     pub description: Option<String>,
     pub color: Option<String>,
+    pub settings: Option<ProjectSettings>,
 }
 
-#[tauri::command]
-pub async fn get_all_projects(app: tauri::AppHandle) -> Result<Vec<Project>, String> {
-    use tauri_plugin_sql::{Builder, Migration, MigrationKind};
+/// Fields left as `None` are not changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProjectUpdate {
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub settings: Option<ProjectSettings>,
+}
+
+/// Files left behind by `delete_project`; the database rows are gone either way
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProjectCleanup {
+    pub removed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+fn project_from_row(row: &SqliteRow) -> Result<Project, String> {
+    let get = |e: sqlx::Error| format!("Failed to read project row: {}", e);
+    let settings: Option<String> = row.try_get("settings").map_err(get)?;
+
+    Ok(Project {
+        id: row.try_get("id").map_err(get)?,
+        name: row.try_get("name").map_err(get)?,
+        path: row.try_get("path").map_err(get)?,
+        description: row.try_get("description").map_err(get)?,
+        color: row
+            .try_get::<Option<String>, _>("color")
+            .map_err(get)?
+            .unwrap_or_else(|| DEFAULT_COLOR.to_string()),
+        created_at: row.try_get("created_at").map_err(get)?,
+        last_accessed: row.try_get("last_accessed").map_err(get)?,
+        // 设置损坏时退回默认值，不影响项目列表的加载
+        settings: ProjectSettings::from_stored(settings.as_deref()),
+    })
+}
+
+/// Trim whitespace and trailing separators so `/a/b/` and `/a/b` are the same project
+pub(crate) fn normalize_path(path: &str) -> String {
+    let trimmed = path.trim();
+    let stripped = trimmed.trim_end_matches(['/', '\\']);
+    if stripped.is_empty() || stripped.ends_with(':') {
+        // Keep filesystem roots such as `/` and `C:\`
+        return trimmed.chars().take(stripped.len() + 1).collect();
+    }
+    stripped.to_string()
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Project name must not be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// The path must be an existing directory not registered by another project
+async fn validate_path(
+    pool: &SqlitePool,
+    path: &str,
+    exclude_id: Option<i64>,
+) -> Result<String, String> {
+    let path = normalize_path(path);
+    if path.is_empty() {
+        return Err("Project path must not be empty".to_string());
+    }
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Project path does not exist or is not a directory: {}", path));
+    }
 
-    let db = app.state::<tauri_plugin_sql::DbPool>();
-    let connection = db.get("sqlite:pomo.db")
-        .ok_or("Failed to get database connection")?;
+    let existing = find_project_by_path(pool, &path).await?;
+    if let Some(existing) = existing.filter(|p| Some(p.id) != exclude_id) {
+        return Err(format!(
+            "Path is already registered by project \"{}\"",
+            existing.name
+        ));
+    }
+    Ok(path)
+}
+
+fn settings_json(settings: &ProjectSettings) -> Result<String, String> {
+    serde_json::to_string(settings).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
 
-    let result: Vec<Project> = tauri_plugin_sql::query("SELECT * FROM projects ORDER BY last_accessed DESC")
-        .fetch_all(connection)
+pub(crate) async fn list_projects(pool: &SqlitePool) -> Result<Vec<Project>, String> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM projects ORDER BY last_accessed DESC",
+        PROJECT_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database query failed: {}", e))?;
+
+    rows.iter().map(project_from_row).collect()
+}
+
+pub(crate) async fn find_project(pool: &SqlitePool, id: i64) -> Result<Project, String> {
+    let row = sqlx::query(&format!("SELECT {} FROM projects WHERE id = ?", PROJECT_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
         .await
-        .map_err(|e| format!("Database query failed: {}", e))?;
+        .map_err(|e| format!("Database query failed: {}", e))?
+        .ok_or_else(|| format!("Project {} not found", id))?;
+
+    project_from_row(&row)
+}
 
-    Ok(result)
+/// Look a project up by path, ignoring trailing separators
+pub(crate) async fn find_project_by_path(
+    pool: &SqlitePool,
+    path: &str,
+) -> Result<Option<Project>, String> {
+    let path = normalize_path(path);
+    let projects = list_projects(pool).await?;
+    Ok(projects
+        .into_iter()
+        .find(|p| normalize_path(&p.path) == path))
 }
 
+/// Get all projects, most recently accessed first
 #[tauri::command]
-pub async fn add_project(
-    app: tauri::AppHandle,
-    project: NewProject,
-) -> Result<i64, String> {
-    let db = app.state::<tauri_plugin_sql::DbPool>();
+pub async fn get_all_projects(app: tauri::AppHandle) -> Result<Vec<Project>, String> {
+    let pool = db::pool(&app).await?;
+    list_projects(&pool).await
+}
+
+#[tauri::command]
+pub async fn get_project(app: tauri::AppHandle, id: i64) -> Result<Project, String> {
+    let pool = db::pool(&app).await?;
+    find_project(&pool, id).await
+}
 
-    let color = project.color.unwrap_or_else(|| "#FF6B9D".to_string());
+/// Register a project for an existing directory
+#[tauri::command]
+pub async fn add_project(app: tauri::AppHandle, project: NewProject) -> Result<Project, String> {
+    let pool = db::pool(&app).await?;
+    insert_project(&pool, project).await
+}
+
+async fn insert_project(pool: &SqlitePool, project: NewProject) -> Result<Project, String> {
+    let name = validate_name(&project.name)?;
+    let path = validate_path(pool, &project.path, None).await?;
+    let color = project.color.unwrap_or_else(|| DEFAULT_COLOR.to_string());
     let description = project.description.unwrap_or_default();
+    let settings = settings_json(&project.settings.unwrap_or_default().validate()?)?;
 
-    let result = db
-        .get("sqlite:pomo.db")
-        .ok_or("Failed to get database connection")?
-        .execute(
-            "INSERT INTO projects (name, path, description, color) VALUES (?, ?, ?, ?)",
-            &[&project.name, &project.path, &description, &color],
-        )
-        .await
-        .map_err(|e| format!("Failed to insert project: {}", e))?;
+    let result = sqlx::query(
+        "INSERT INTO projects (name, path, description, color, settings) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&name)
+    .bind(&path)
+    .bind(&description)
+    .bind(&color)
+    .bind(&settings)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to insert project: {}", e))?;
 
-    Ok(result.last_insert_rowid())
+    find_project(pool, result.last_insert_rowid()).await
 }
 
 #[tauri::command]
-pub async fn delete_project(
+pub async fn update_project(
     app: tauri::AppHandle,
     id: i64,
-) -> Result<(), String> {
-    let db = app.state::<tauri_plugin_sql::DbPool>();
+    update: ProjectUpdate,
+) -> Result<Project, String> {
+    let pool = db::pool(&app).await?;
+    apply_project_update(&pool, id, update).await
+}
+
+async fn apply_project_update(
+    pool: &SqlitePool,
+    id: i64,
+    update: ProjectUpdate,
+) -> Result<Project, String> {
+    let current = find_project(pool, id).await?;
+
+    let name = match update.name {
+        Some(name) => validate_name(&name)?,
+        None => current.name,
+    };
+    let path = match update.path {
+        Some(path) => validate_path(pool, &path, Some(id)).await?,
+        None => current.path.clone(),
+    };
+    // 未携带设置时不写回该列，避免覆盖新版本或无法解析的设置
+    let settings = match update.settings {
+        Some(settings) => {
+            let settings = settings.validate()?;
+            ensure_not_newer(pool, id).await?;
+            Some(settings_json(&settings)?)
+        }
+        None => None,
+    };
+
+    let db_err = |e: sqlx::Error| format!("Failed to update project: {}", e);
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    sqlx::query(
+        "UPDATE projects SET name = ?, path = ?, description = ?, color = ?, \
+         settings = COALESCE(?, settings) WHERE id = ?",
+    )
+    .bind(&name)
+    .bind(&path)
+    .bind(update.description.or(current.description))
+    .bind(update.color.unwrap_or(current.color))
+    .bind(&settings)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    // 分析缓存按路径保存，路径变更后旧记录不再属于任何项目
+    let old_path = normalize_path(&current.path);
+    if normalize_path(&path) != old_path {
+        for sql in [
+            "DELETE FROM project_analysis_files WHERE project_path = ?",
+            "DELETE FROM project_analysis WHERE path = ?",
+        ] {
+            sqlx::query(sql)
+                .bind(&old_path)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    find_project(pool, id).await
+}
+
+/// Delete a project with its documents and vault, then remove its docvault,
+/// document and vault attachment directories
+#[tauri::command]
+pub async fn delete_project(app: tauri::AppHandle, id: i64) -> Result<ProjectCleanup, String> {
+    let pool = db::pool(&app).await?;
+    let document_ids = delete_project_rows(&pool, id).await?;
 
-    db.get("sqlite:pomo.db")
-        .ok_or("Failed to get database connection")?
-        .execute("DELETE FROM projects WHERE id = ?", &[&id])
+    lock_project_sessions(&app, id);
+
+    let mut dirs = vec![
+        get_docvault_dir(&app, id)?,
+        get_project_attachments_dir(&app, id)?,
+    ];
+    for doc_id in document_ids {
+        dirs.push(get_document_dir(&app, doc_id)?);
+    }
+
+    let mut cleanup = ProjectCleanup::default();
+    for dir in dirs.iter().filter(|d| d.exists()) {
+        let display = dir.to_string_lossy().to_string();
+        match fs::remove_dir_all(dir) {
+            Ok(()) => cleanup.removed.push(display),
+            Err(e) => cleanup.failed.push(format!("{}: {}", display, e)),
+        }
+    }
+
+    Ok(cleanup)
+}
+
+/// Delete the project row and every row that belongs to it in one transaction.
+/// Returns the ids of the deleted documents, whose directories are left to the caller.
+async fn delete_project_rows(pool: &SqlitePool, id: i64) -> Result<Vec<i64>, String> {
+    let db_err = |e: sqlx::Error| format!("Failed to delete project: {}", e);
+
+    let document_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM documents WHERE project_id = ?")
+        .bind(id)
+        .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to delete project: {}", e))?;
+        .map_err(db_err)?;
 
-    Ok(())
+    // 外键级联依赖 PRAGMA foreign_keys，这里显式删除关联数据
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    for sql in [
+        // 分析缓存按路径保存，需在删除项目行之前执行
+        "DELETE FROM project_analysis_files WHERE project_path = (SELECT path FROM projects WHERE id = ?)",
+        "DELETE FROM project_analysis WHERE path = (SELECT path FROM projects WHERE id = ?)",
+        "DELETE FROM vault_entry_history WHERE project_id = ?",
+        "DELETE FROM vault_entries WHERE project_id = ?",
+        "DELETE FROM vault_master WHERE project_id = ?",
+        "DELETE FROM documents WHERE project_id = ?",
+    ] {
+        sqlx::query(sql).bind(id).execute(&mut *tx).await.map_err(db_err)?;
+    }
+    let deleted = sqlx::query("DELETE FROM projects WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    if deleted.rows_affected() == 0 {
+        return Err(format!("Project {} not found", id));
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    Ok(document_ids)
 }
 
+/// Mark a project as just opened
 #[tauri::command]
-pub async fn update_project_access_time(
-    app: tauri::AppHandle,
-    id: i64,
-) -> Result<(), String> {
-    let db = app.state::<tauri_plugin_sql::DbPool>();
-
-    db.get("sqlite:pomo.db")
-        .ok_or("Failed to get database connection")?
-        .execute(
-            "UPDATE projects SET last_accessed = CURRENT_TIMESTAMP WHERE id = ?",
-            &[&id],
-        )
+pub async fn update_project_access_time(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+    let pool = db::pool(&app).await?;
+
+    let result = sqlx::query("UPDATE projects SET last_accessed = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
         .await
         .map_err(|e| format!("Failed to update access time: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err(format!("Project {} not found", id));
+    }
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tauri::async_runtime::block_on;
+
+
+    fn new_project(name: &str, path: &str) -> NewProject {
+        NewProject {
+            name: name.to_string(),
+            path: path.to_string(),
+            description: None,
+            color: None,
+            settings: None,
+        }
+    }
+
+    async fn count(pool: &SqlitePool, table: &str, project_id: i64) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE project_id = ?", table))
+            .bind(project_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(" /a/b/ "), "/a/b");
+        assert_eq!(normalize_path("C:\\work\\app\\\\"), "C:\\work\\app");
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("C:\\"), "C:\\");
+        assert_eq!(normalize_path("  "), "");
+    }
+
+    #[test]
+    fn test_add_project_normalizes_and_rejects_duplicate_paths() {
+        let dir = temp_dir("projects", "unique");
+        block_on(async {
+            let pool = db::memory_pool().await;
+            let path = dir.to_string_lossy().to_string();
+
+            let project = insert_project(&pool, new_project(" app ", &format!("{}/", path)))
+                .await
+                .unwrap();
+            assert_eq!(project.name, "app");
+            assert_eq!(project.path, path);
+            assert_eq!(project.color, DEFAULT_COLOR);
+
+            // 末尾分隔符不同也是同一个目录
+            let err = insert_project(&pool, new_project("again", &format!(" {}// ", path)))
+                .await
+                .unwrap_err();
+            assert!(err.contains("already registered"), "{}", err);
+            assert!(insert_project(&pool, new_project("  ", &path)).await.is_err());
+            let missing = dir.join("missing").to_string_lossy().to_string();
+            assert!(insert_project(&pool, new_project("missing", &missing)).await.is_err());
+            assert_eq!(list_projects(&pool).await.unwrap().len(), 1);
+        });
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_update_project_path_checks_uniqueness_and_drops_analysis() {
+        let dir = temp_dir("projects", "update");
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        block_on(async {
+            let pool = db::memory_pool().await;
+            let a = insert_project(&pool, new_project("a", &path("a"))).await.unwrap();
+            let b = insert_project(&pool, new_project("b", &path("b"))).await.unwrap();
+            sqlx::query("INSERT INTO project_analysis (path, summary) VALUES (?, '{}')")
+                .bind(path("a"))
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            let to = |p: String| ProjectUpdate {
+                path: Some(p),
+                ..Default::default()
+            };
+            let err = apply_project_update(&pool, b.id, to(format!("{}/", path("a"))))
+                .await
+                .unwrap_err();
+            assert!(err.contains("already registered"), "{}", err);
+            // 自己原来的路径不算重复
+            apply_project_update(&pool, a.id, to(path("a"))).await.unwrap();
+            let analyses: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM project_analysis")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(analyses, 1);
+
+            let moved = apply_project_update(&pool, a.id, to(format!("{}/", path("c"))))
+                .await
+                .unwrap();
+            assert_eq!(moved.path, path("c"));
+            assert_eq!(moved.name, "a");
+            let analyses: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM project_analysis")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(analyses, 0);
+        });
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_delete_project_removes_dependent_rows() {
+        let dir = temp_dir("projects", "delete");
+        for name in ["gone", "kept"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        block_on(async {
+            let pool = db::memory_pool().await;
+            let gone = insert_project(&pool, new_project("gone", &path("gone"))).await.unwrap();
+            let kept = insert_project(&pool, new_project("kept", &path("kept"))).await.unwrap();
+            for project in [&gone, &kept] {
+                for sql in [
+                    "INSERT INTO documents (project_id, title) VALUES (?, 'doc')",
+                    "INSERT INTO vault_master (project_id, password_hash, salt) \
+                     VALUES (?, 'h', 's')",
+                    "INSERT INTO vault_entries (project_id, title, encrypted_value, salt, nonce) \
+                     VALUES (?, 'entry', 'v', 's', 'n')",
+                    "INSERT INTO vault_entry_history \
+                     (entry_id, project_id, version, encrypted_value, salt, nonce) \
+                     SELECT id, project_id, 1, 'old', 's', 'n' FROM vault_entries \
+                     WHERE project_id = ?",
+                ] {
+                    sqlx::query(sql).bind(project.id).execute(&pool).await.unwrap();
+                }
+                sqlx::query("INSERT INTO project_analysis (path, summary) VALUES (?, '{}')")
+                    .bind(&project.path)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+
+            let document_ids = delete_project_rows(&pool, gone.id).await.unwrap();
+            assert_eq!(document_ids.len(), 1);
+            assert!(find_project(&pool, gone.id).await.is_err());
+            let tables = ["documents", "vault_master", "vault_entries", "vault_entry_history"];
+            for table in tables {
+                assert_eq!(count(&pool, table, gone.id).await, 0, "{}", table);
+                assert_eq!(count(&pool, table, kept.id).await, 1, "{}", table);
+            }
+            let analyses: Vec<String> = sqlx::query_scalar("SELECT path FROM project_analysis")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            assert_eq!(analyses, vec![kept.path.clone()]);
+
+            assert!(delete_project_rows(&pool, gone.id).await.is_err());
+        });
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,239 @@
-use crate::crypto::{decrypt_data, encrypt_data, hash_password, verify_password};
+use crate::crypto::envelope::{Envelope, KeySpec};
+use crate::crypto::generator::{
+    GeneratedPassword, GeneratorOptions, PolicyOptions, generate, generate_policy,
+};
+use crate::crypto::totp::{TotpCode, parse_totp, totp_now};
+use crate::crypto::{
+    DATA_KEY_LEN, KdfParams, calibrate_kdf_params, decrypt_data, decrypt_with_key,
+    encrypt_versioned, encrypt_versioned_with_key, generate_data_key, hash_password_with_params,
+    is_envelope, password_hash_params, unwrap_data_key, verify_password, wrap_data_key,
+    wrapped_key_params,
+};
+use crate::commands::vault_history::{
+    apply_archive, load_history, plan_archive, retention_from_column,
+};
+use crate::db;
 use rand::{Rng, distributions::Alphanumeric};
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Salt column marker for entries encrypted with the project data key
+/// instead of a key derived from the master password and a per-entry salt
+pub const DATA_KEY_SALT: &str = "$datakey";
+
+/// Default idle time before an unlocked vault locks itself again
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 5 * 60;
+
+/// How often the idle watcher checks for expired sessions
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Event emitted with the project id whenever a vault session is locked by Rust
+pub const VAULT_LOCKED_EVENT: &str = "vault-locked";
+
+/// Unlocked vault sessions keyed by an opaque session handle.
+/// Keys live in Rust memory only and are zeroized when a session is dropped.
+#[derive(Default)]
+pub struct VaultSessions(Mutex<HashMap<String, VaultSession>>);
+
+struct VaultSession {
+    project_id: i64,
+    data_key: Zeroizing<[u8; DATA_KEY_LEN]>,
+    // Still needed to read legacy per-entry-salt records
+    master_password: Zeroizing<String>,
+    idle_timeout: Duration,
+    last_used: Instant,
+}
+
+impl VaultSession {
+    fn is_expired(&self) -> bool {
+        self.last_used.elapsed() >= self.idle_timeout
+    }
+}
+
+impl VaultSessions {
+    /// Run `f` with a live session and refresh its idle timer
+    fn with_session<T>(
+        &self,
+        handle: &str,
+        f: impl FnOnce(&VaultSession) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut sessions = self.0.lock().map_err(|e| e.to_string())?;
+
+        if sessions.get(handle).is_some_and(VaultSession::is_expired) {
+            sessions.remove(handle);
+            return Err("Vault session expired".to_string());
+        }
+
+        let session = sessions.get_mut(handle).ok_or("Vault is locked")?;
+        session.last_used = Instant::now();
+        f(session)
+    }
+
+    /// Same as `with_session`, looked up by project instead of handle
+    fn with_project_session<T>(
+        &self,
+        project_id: i64,
+        f: impl FnOnce(&VaultSession) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut sessions = self.0.lock().map_err(|e| e.to_string())?;
+        let session = sessions
+            .values_mut()
+            .find(|s| s.project_id == project_id && !s.is_expired())
+            .ok_or("Vault is locked")?;
+        session.last_used = Instant::now();
+        f(session)
+    }
+
+    /// Store a new session, replacing any previous session of the same project
+    fn open(&self, session: VaultSession) -> Result<String, String> {
+        // Same 32-char random alphanumeric generator as entry salts
+        let handle = generate_salt();
+        let mut sessions = self.0.lock().map_err(|e| e.to_string())?;
+        sessions.retain(|_, s| s.project_id != session.project_id);
+        sessions.insert(handle.clone(), session);
+        Ok(handle)
+    }
+
+    /// Copy the project id and data key out of a live session, so long-running work
+    /// such as file encryption does not hold the session lock
+    pub(crate) fn data_key(
+        &self,
+        handle: &str,
+    ) -> Result<(i64, Zeroizing<[u8; DATA_KEY_LEN]>), String> {
+        self.with_session(handle, |s| Ok((s.project_id, s.data_key.clone())))
+    }
+
+    /// Project of a live session
+    pub(crate) fn project_id(&self, handle: &str) -> Result<i64, String> {
+        self.with_session(handle, |s| Ok(s.project_id))
+    }
+
+    /// Decrypt a stored entry of the session's project
+    pub(crate) fn decrypt_entry(
+        &self,
+        handle: &str,
+        entry: &VaultEntry,
+    ) -> Result<(String, Option<String>), String> {
+        self.with_session(handle, |s| {
+            if entry.project_id != s.project_id {
+                return Err("Entry does not belong to the unlocked vault".to_string());
+            }
+            decrypt_entry_fields_in_session(
+                s,
+                &entry.encrypted_value,
+                &entry.nonce,
+                entry.encrypted_notes.as_deref(),
+                &entry.salt,
+            )
+        })
+    }
+
+    /// Decrypt the TOTP secret of a stored entry of the session's project
+    pub(crate) fn decrypt_totp(
+        &self,
+        handle: &str,
+        entry: &VaultEntry,
+    ) -> Result<Option<String>, String> {
+        let Some(encrypted_totp) = entry.encrypted_totp.as_deref() else {
+            return Ok(None);
+        };
+        self.with_session(handle, |s| {
+            if entry.project_id != s.project_id {
+                return Err("Entry does not belong to the unlocked vault".to_string());
+            }
+            decrypt_field(
+                encrypted_totp,
+                "",
+                &entry.salt,
+                Some(&s.master_password),
+                Some(&s.data_key),
+            )
+            .map(Some)
+        })
+    }
+
+    /// Decrypt an entry of any project whose vault is currently unlocked
+    pub(crate) fn decrypt_unlocked_entry(
+        &self,
+        entry: &VaultEntry,
+    ) -> Result<(String, Option<String>), String> {
+        self.with_project_session(entry.project_id, |s| {
+            decrypt_entry_fields_in_session(
+                s,
+                &entry.encrypted_value,
+                &entry.nonce,
+                entry.encrypted_notes.as_deref(),
+                &entry.salt,
+            )
+        })
+    }
+
+    /// Whether the project has an unlocked, non-expired session
+    pub(crate) fn is_unlocked(&self, project_id: i64) -> bool {
+        self.with_project_session(project_id, |_| Ok(())).is_ok()
+    }
+
+    fn close(&self, handle: &str) -> Result<(), String> {
+        self.0.lock().map_err(|e| e.to_string())?.remove(handle);
+        Ok(())
+    }
+
+    /// Drop sessions matching `pred`, returning the affected project ids
+    fn close_where(&self, pred: impl Fn(&VaultSession) -> bool) -> Vec<i64> {
+        // A panic while holding the lock must not keep keys alive: recover the map
+        let mut sessions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let mut locked = Vec::new();
+        sessions.retain(|_, s| {
+            if pred(s) {
+                locked.push(s.project_id);
+                false
+            } else {
+                true
+            }
+        });
+        locked
+    }
+}
+
+/// Lock every open vault session and notify the frontend
+pub fn lock_all_sessions(app: &tauri::AppHandle) {
+    let locked = app.state::<VaultSessions>().close_where(|_| true);
+    notify_locked(app, &locked);
+}
+
+/// Lock the sessions of one project, e.g. when the project is deleted
+pub fn lock_project_sessions(app: &tauri::AppHandle, project_id: i64) {
+    let locked = app
+        .state::<VaultSessions>()
+        .close_where(|s| s.project_id == project_id);
+    notify_locked(app, &locked);
+}
+
+/// Periodically lock sessions that have been idle for longer than their timeout
+pub fn spawn_idle_watcher(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(IDLE_CHECK_INTERVAL);
+            let locked = app.state::<VaultSessions>().close_where(VaultSession::is_expired);
+            notify_locked(&app, &locked);
+        }
+    });
+}
+
+fn notify_locked(app: &tauri::AppHandle, project_ids: &[i64]) {
+    for project_id in project_ids {
+        let _ = app.emit(VAULT_LOCKED_EVENT, *project_id);
+    }
+}
 
 #[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VaultEntry {
     pub id: i64,
     pub project_id: i64,
@@ -17,9 +247,76 @@ pub struct VaultEntry {
     pub nonce: String,
     pub created_at: String,
     pub updated_at: String,
+    /// `otpauth://` URI or base32 TOTP secret, sealed in a versioned envelope
+    #[serde(default)]
+    pub encrypted_totp: Option<String>,
+    /// OpenSSH public key line of `ssh` entries generated by `vault_generate_ssh_key`
+    #[serde(default)]
+    pub ssh_public_key: Option<String>,
+    #[serde(default)]
+    pub ssh_fingerprint: Option<String>,
+}
+
+/// Column list matching `vault_entry_from_row`
+pub(crate) const VAULT_ENTRY_COLUMNS: &str = "id, project_id, title, param_key, encrypted_value, \
+    encrypted_notes, url, category, salt, nonce, CAST(created_at AS TEXT) AS created_at, \
+    CAST(updated_at AS TEXT) AS updated_at, encrypted_totp, ssh_public_key, ssh_fingerprint";
+
+pub(crate) fn vault_entry_from_row(row: &SqliteRow) -> Result<VaultEntry, String> {
+    let get_err = |e: sqlx::Error| format!("Failed to read vault entry: {}", e);
+    Ok(VaultEntry {
+        id: row.try_get("id").map_err(get_err)?,
+        project_id: row.try_get("project_id").map_err(get_err)?,
+        title: row.try_get("title").map_err(get_err)?,
+        param_key: row.try_get("param_key").map_err(get_err)?,
+        encrypted_value: row.try_get("encrypted_value").map_err(get_err)?,
+        encrypted_notes: row.try_get("encrypted_notes").map_err(get_err)?,
+        url: row.try_get("url").map_err(get_err)?,
+        category: row.try_get("category").map_err(get_err)?,
+        salt: row.try_get("salt").map_err(get_err)?,
+        nonce: row.try_get("nonce").map_err(get_err)?,
+        created_at: row.try_get("created_at").map_err(get_err)?,
+        updated_at: row.try_get("updated_at").map_err(get_err)?,
+        encrypted_totp: row.try_get("encrypted_totp").map_err(get_err)?,
+        ssh_public_key: row.try_get("ssh_public_key").map_err(get_err)?,
+        ssh_fingerprint: row.try_get("ssh_fingerprint").map_err(get_err)?,
+    })
+}
+
+/// All stored entries of a project vault
+async fn load_vault_entries(pool: &SqlitePool, project_id: i64) -> Result<Vec<VaultEntry>, String> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM vault_entries WHERE project_id = ?",
+        VAULT_ENTRY_COLUMNS
+    ))
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database query failed: {}", e))?;
+
+    rows.iter().map(vault_entry_from_row).collect()
+}
+
+/// One stored entry of a project vault
+pub(crate) async fn find_vault_entry(
+    pool: &SqlitePool,
+    project_id: i64,
+    entry_id: i64,
+) -> Result<VaultEntry, String> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM vault_entries WHERE id = ? AND project_id = ?",
+        VAULT_ENTRY_COLUMNS
+    ))
+    .bind(entry_id)
+    .bind(project_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database query failed: {}", e))?
+    .ok_or_else(|| format!("Entry {} not found", entry_id))?;
+
+    vault_entry_from_row(&row)
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateVaultEntryInput {
     pub project_id: i64,
@@ -46,6 +343,41 @@ pub struct DecryptedVaultEntry {
     pub updated_at: String,
 }
 
+/// `vault_master` columns and entry rows rewritten by a master password rotation
+struct RotateMasterResult {
+    password_hash: String,
+    salt: String,
+    wrapped_key: Option<String>,
+    /// Re-encrypted entries only; data-key entries are left untouched
+    entries: Vec<VaultEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultUnlockResult {
+    /// Newly created or re-wrapped data key, to be stored in `vault_master.wrapped_key`.
+    /// `None` when the stored one is still current.
+    pub new_wrapped_key: Option<String>,
+    /// Master hash upgraded to the vault's configured KDF params, with its salt,
+    /// to be stored in `vault_master`. `None` when the hash already uses them.
+    pub new_password_hash: Option<(String, String)>,
+    /// Handle to pass to the session commands
+    pub session: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KdfCalibration {
+    /// Suggested params, to be stored in `vault_master.kdf_params`
+    pub params: KdfParams,
+    /// Measured duration of one derivation with `params`
+    pub elapsed_ms: u64,
+}
+
+/// Default unlock time `vault_calibrate_kdf` aims for
+pub const DEFAULT_KDF_TARGET_MS: u64 = 500;
+
+/// Default memory cost used for calibration (64 MiB)
+const CALIBRATION_M_COST: u32 = 64 * 1024;
+
 /// Generate a random salt
 fn generate_salt() -> String {
     rand::thread_rng()
@@ -55,10 +387,54 @@ fn generate_salt() -> String {
         .collect()
 }
 
-/// Hash a master password using Argon2
+/// Hash a master password using Argon2, with the vault's KDF params when configured
+#[tauri::command]
+pub fn vault_hash_password(
+    password: String,
+    kdf_params: Option<KdfParams>,
+) -> Result<(String, String), String> {
+    let params = resolve_kdf_params(kdf_params)?;
+    hash_password_with_params(&password, &params)
+}
+
+/// Vault KDF params, falling back to the Argon2 defaults for vaults without any
+pub(crate) fn resolve_kdf_params(kdf_params: Option<KdfParams>) -> Result<KdfParams, String> {
+    match kdf_params {
+        Some(params) => {
+            params.validate()?;
+            Ok(params)
+        }
+        None => Ok(KdfParams::default()),
+    }
+}
+
+/// Benchmark Argon2id on this machine and suggest params that take about
+/// `target_ms` (default 500 ms) per unlock
 #[tauri::command]
-pub fn vault_hash_password(password: String) -> Result<(String, String), String> {
-    hash_password(&password)
+pub async fn vault_calibrate_kdf(
+    target_ms: Option<u64>,
+    m_cost: Option<u32>,
+    p_cost: Option<u32>,
+) -> Result<KdfCalibration, String> {
+    let target = Duration::from_millis(
+        target_ms
+            .filter(|ms| *ms > 0)
+            .unwrap_or(DEFAULT_KDF_TARGET_MS),
+    );
+    let m_cost = m_cost.unwrap_or(CALIBRATION_M_COST);
+    let p_cost = p_cost.unwrap_or(1);
+
+    // Hashing blocks for up to a few seconds; keep it off the async runtime
+    let (params, elapsed) = tauri::async_runtime::spawn_blocking(move || {
+        calibrate_kdf_params(target, m_cost, p_cost)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(KdfCalibration {
+        params,
+        elapsed_ms: elapsed.as_millis() as u64,
+    })
 }
 
 /// Verify master password for a project
@@ -70,41 +446,40 @@ pub async fn vault_verify_master(
     verify_password(&master_password, &password_hash)
 }
 
+/// (encrypted_value, nonce, encrypted_notes, notes_nonce, salt)
+pub(crate) type EncryptedFields = (String, String, Option<String>, Option<String>, String);
+
 /// Encrypt and prepare vault entry data
 #[tauri::command]
 pub async fn vault_encrypt_entry(
     param_value: String,
     notes: Option<String>,
     master_password: String,
-) -> Result<(String, String, Option<String>, Option<String>, String), String> {
-    // Generate a unique salt for this entry
-    let salt = generate_salt();
-
-    // Encrypt param_value
-    let (encrypted_value, value_nonce) = encrypt_data(&param_value, &master_password, &salt)?;
-
-    // Encrypt notes if provided
-    let (encrypted_notes, notes_nonce) = if let Some(notes_text) = notes {
-        let (enc, nonce) = encrypt_data(&notes_text, &master_password, &salt)?;
-        (Some(enc), Some(nonce))
-    } else {
-        (None, None)
-    };
+    kdf_params: Option<KdfParams>,
+) -> Result<EncryptedFields, String> {
+    let params = resolve_kdf_params(kdf_params)?;
+    encrypt_entry_fields(&param_value, notes.as_deref(), &master_password, &params)
+}
 
-    // For simplicity, we'll use the same nonce field for both
-    // In production, you might want separate nonce fields
-    let combined_nonce = if let Some(nn) = notes_nonce {
-        format!("{}|{}", value_nonce, nn)
-    } else {
-        value_nonce
-    };
+/// Encrypt a value and optional notes under a fresh salt and nonces
+fn encrypt_entry_fields(
+    param_value: &str,
+    notes: Option<&str>,
+    master_password: &str,
+    params: &KdfParams,
+) -> Result<EncryptedFields, String> {
+    // Each envelope records its own salt, KDF params and nonce
+    let encrypted_value = encrypt_versioned(param_value, master_password, params)?;
+    let encrypted_notes = notes
+        .map(|n| encrypt_versioned(n, master_password, params))
+        .transpose()?;
 
     Ok((
         encrypted_value,
-        combined_nonce,
+        String::new(),
         encrypted_notes,
-        None, // notes_nonce is combined
-        salt,
+        None,
+        String::new(),
     ))
 }
 
@@ -117,33 +492,520 @@ pub async fn vault_decrypt_entry(
     salt: String,
     master_password: String,
 ) -> Result<(String, Option<String>), String> {
-    // Split nonce if it contains both value and notes nonce
+    decrypt_entry_fields(
+        &encrypted_value,
+        &nonce,
+        encrypted_notes.as_deref(),
+        &salt,
+        &master_password,
+    )
+}
+
+/// Decrypt a value and optional notes with the master password
+fn decrypt_entry_fields(
+    encrypted_value: &str,
+    nonce: &str,
+    encrypted_notes: Option<&str>,
+    salt: &str,
+    master_password: &str,
+) -> Result<(String, Option<String>), String> {
+    decrypt_fields_with(encrypted_value, nonce, encrypted_notes, |enc, n| {
+        decrypt_field(enc, n, salt, Some(master_password), None)
+    })
+}
+
+/// Decrypt entry fields with the session: data-key entries use the cached data key,
+/// password-based entries fall back to the cached master password
+fn decrypt_entry_fields_in_session(
+    session: &VaultSession,
+    encrypted_value: &str,
+    nonce: &str,
+    encrypted_notes: Option<&str>,
+    salt: &str,
+) -> Result<(String, Option<String>), String> {
+    decrypt_fields_with(encrypted_value, nonce, encrypted_notes, |enc, n| {
+        decrypt_field(
+            enc,
+            n,
+            salt,
+            Some(&session.master_password),
+            Some(&session.data_key),
+        )
+    })
+}
+
+/// Decrypt one stored field, dispatching on its format:
+/// - versioned envelope: key source, KDF params, salt and nonce come from the envelope
+/// - `$datakey` salt: raw AES-GCM under the project data key with an external nonce
+/// - otherwise: legacy per-entry salt with default Argon2 costs
+fn decrypt_field(
+    encrypted: &str,
+    nonce: &str,
+    salt: &str,
+    master_password: Option<&str>,
+    data_key: Option<&[u8; DATA_KEY_LEN]>,
+) -> Result<String, String> {
+    let uses_data_key = if is_envelope(encrypted) {
+        envelope_uses_data_key(encrypted)
+    } else {
+        salt == DATA_KEY_SALT
+    };
+
+    if uses_data_key {
+        let key = data_key
+            .ok_or("Entry is encrypted with the project data key, unlock the vault first")?;
+        decrypt_with_key(encrypted, nonce, key)
+    } else {
+        let password = master_password.ok_or("Master password is required")?;
+        decrypt_data(encrypted, nonce, password, salt)
+    }
+}
+
+/// Decrypt a stored entry with the master password and, for data-key entries,
+/// the project data key unwrapped from it
+pub(crate) fn decrypt_entry_with_master(
+    entry: &VaultEntry,
+    master_password: &str,
+    data_key: Option<&[u8; DATA_KEY_LEN]>,
+) -> Result<(String, Option<String>), String> {
+    decrypt_fields_with(
+        &entry.encrypted_value,
+        &entry.nonce,
+        entry.encrypted_notes.as_deref(),
+        |enc, n| decrypt_field(enc, n, &entry.salt, Some(master_password), data_key),
+    )
+}
+
+fn envelope_uses_data_key(encrypted: &str) -> bool {
+    Envelope::parse(encrypted).is_ok_and(|e| e.key == KeySpec::DataKey)
+}
+
+/// Whether an entry needs the project data key rather than the master password
+fn entry_uses_data_key(encrypted_value: &str, salt: &str) -> bool {
+    salt == DATA_KEY_SALT || envelope_uses_data_key(encrypted_value)
+}
+
+fn decrypt_fields_with(
+    encrypted_value: &str,
+    nonce: &str,
+    encrypted_notes: Option<&str>,
+    decrypt: impl Fn(&str, &str) -> Result<String, String>,
+) -> Result<(String, Option<String>), String> {
+    // Legacy entries keep both nonces in one column as `value|notes`;
+    // versioned envelopes carry their own nonce and leave the column empty
     let nonce_parts: Vec<&str> = nonce.split('|').collect();
     let value_nonce = nonce_parts[0];
 
     // Decrypt param_value
-    let param_value = decrypt_data(&encrypted_value, value_nonce, &master_password, &salt)?;
+    let param_value = decrypt(encrypted_value, value_nonce)?;
 
     // Decrypt notes if provided
-    let notes = if let Some(enc_notes) = encrypted_notes {
-        if nonce_parts.len() > 1 {
-            let notes_nonce = nonce_parts[1];
-            Some(decrypt_data(
-                &enc_notes,
-                notes_nonce,
-                &master_password,
-                &salt,
-            )?)
-        } else {
-            None
+    let notes = match encrypted_notes {
+        Some(enc_notes) if is_envelope(enc_notes) => Some(decrypt(enc_notes, "")?),
+        Some(enc_notes) if nonce_parts.len() > 1 => Some(decrypt(enc_notes, nonce_parts[1])?),
+        Some(enc_notes) if !enc_notes.is_empty() => {
+            return Err("Notes nonce is missing from the nonce column".to_string());
         }
-    } else {
-        None
+        _ => None,
     };
 
     Ok((param_value, notes))
 }
 
+/// Encrypt a value and optional notes with the project data key
+pub(crate) fn encrypt_entry_fields_with_key(
+    param_value: &str,
+    notes: Option<&str>,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<EncryptedFields, String> {
+    let encrypted_value = encrypt_versioned_with_key(param_value, data_key)?;
+    let encrypted_notes = notes
+        .map(|n| encrypt_versioned_with_key(n, data_key))
+        .transpose()?;
+
+    // Nonces live inside the envelopes; the salt column only marks data-key entries
+    Ok((
+        encrypted_value,
+        String::new(),
+        encrypted_notes,
+        None,
+        DATA_KEY_SALT.to_string(),
+    ))
+}
+
+/// Decrypt a stored entry with whichever key it was written with: the unlocked
+/// project session for data-key entries, `master_password` for legacy entries
+pub(crate) fn decrypt_stored_entry(
+    sessions: &VaultSessions,
+    project_id: i64,
+    encrypted_value: &str,
+    nonce: &str,
+    encrypted_notes: Option<&str>,
+    salt: &str,
+    master_password: &str,
+) -> Result<(String, Option<String>), String> {
+    if entry_uses_data_key(encrypted_value, salt) {
+        sessions.with_project_session(project_id, |session| {
+            decrypt_entry_fields_in_session(session, encrypted_value, nonce, encrypted_notes, salt)
+        })
+    } else {
+        decrypt_entry_fields(encrypted_value, nonce, encrypted_notes, salt, master_password)
+    }
+}
+
+/// Unlock a project vault: verify the master password, unwrap (or create) the
+/// project data key and keep it in memory until the session is locked or idles out.
+/// When `kdf_params` differ from the costs the hash or wrapped key were made with,
+/// both are re-created with the new params and returned for storage.
+#[tauri::command]
+pub async fn vault_unlock(
+    sessions: tauri::State<'_, VaultSessions>,
+    project_id: i64,
+    master_password: String,
+    password_hash: String,
+    wrapped_key: Option<String>,
+    idle_timeout_secs: Option<u64>,
+    kdf_params: Option<KdfParams>,
+) -> Result<VaultUnlockResult, String> {
+    let master_password = Zeroizing::new(master_password);
+    let params = resolve_kdf_params(kdf_params)?;
+
+    // Each step below runs Argon2; keep it off the async runtime
+    let (master_password, data_key, new_wrapped_key, new_password_hash) =
+        tauri::async_runtime::spawn_blocking(move || {
+            if !verify_password(&master_password, &password_hash)? {
+                return Err("Invalid master password".to_string());
+            }
+
+            let (data_key, new_wrapped_key) = match wrapped_key.as_deref() {
+                Some(wrapped) if !wrapped.is_empty() => {
+                    let key = unwrap_data_key(wrapped, &master_password)?;
+                    let rewrapped = match wrapped_key_params(wrapped) {
+                        Some(current) if current == params => None,
+                        _ => Some(wrap_data_key(&key, &master_password, &params)?),
+                    };
+                    (key, rewrapped)
+                }
+                _ => {
+                    let key = generate_data_key();
+                    let wrapped = wrap_data_key(&key, &master_password, &params)?;
+                    (key, Some(wrapped))
+                }
+            };
+
+            // Upgrade the hash only after the password has been verified
+            let new_password_hash = if password_hash_params(&password_hash)? != params {
+                Some(hash_password_with_params(&master_password, &params)?)
+            } else {
+                None
+            };
+            Ok((master_password, data_key, new_wrapped_key, new_password_hash))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let idle_timeout = Duration::from_secs(
+        idle_timeout_secs
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+    );
+
+    let session = sessions.open(VaultSession {
+        project_id,
+        data_key,
+        master_password,
+        idle_timeout,
+        last_used: Instant::now(),
+    })?;
+
+    Ok(VaultUnlockResult {
+        new_wrapped_key,
+        new_password_hash,
+        session,
+    })
+}
+
+/// Lock a vault session and drop its cached keys
+#[tauri::command]
+pub fn vault_lock(sessions: tauri::State<'_, VaultSessions>, session: String) -> Result<(), String> {
+    sessions.close(&session)
+}
+
+/// Lock every unlocked vault, e.g. before the window is hidden to the tray
+#[tauri::command]
+pub fn vault_lock_all(app: tauri::AppHandle) -> Result<(), String> {
+    lock_all_sessions(&app);
+    Ok(())
+}
+
+/// Encrypt vault entry data with the unlocked project data key
+#[tauri::command]
+pub fn vault_session_encrypt_entry(
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    param_value: String,
+    notes: Option<String>,
+) -> Result<EncryptedFields, String> {
+    sessions.with_session(&session, |s| {
+        encrypt_entry_fields_with_key(&param_value, notes.as_deref(), &s.data_key)
+    })
+}
+
+/// Decrypt vault entry data using an unlocked vault session
+#[tauri::command]
+pub async fn vault_session_decrypt_entry(
+    app: tauri::AppHandle,
+    session: String,
+    encrypted_value: String,
+    nonce: String,
+    encrypted_notes: Option<String>,
+    salt: String,
+) -> Result<(String, Option<String>), String> {
+    // Legacy entries still derive a key from the master password per field
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<VaultSessions>().with_session(&session, |s| {
+            decrypt_entry_fields_in_session(
+                s,
+                &encrypted_value,
+                &nonce,
+                encrypted_notes.as_deref(),
+                &salt,
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Validate a TOTP secret (`otpauth://` URI or base32) and encrypt it with the
+/// unlocked project data key, for `vault_entries.encrypted_totp`
+#[tauri::command]
+pub fn vault_session_encrypt_totp(
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    totp: String,
+) -> Result<String, String> {
+    let totp = Zeroizing::new(totp);
+    parse_totp(&totp)?;
+    sessions.with_session(&session, |s| encrypt_versioned_with_key(&totp, &s.data_key))
+}
+
+/// Current TOTP code of an entry and the seconds until it changes.
+/// The secret is decrypted like any other field of the entry.
+#[tauri::command]
+pub async fn vault_totp_code(
+    app: tauri::AppHandle,
+    session: String,
+    encrypted_totp: String,
+    salt: String,
+) -> Result<TotpCode, String> {
+    // A secret sealed with the master password needs an Argon2 derivation
+    let secret = tauri::async_runtime::spawn_blocking(move || {
+        app.state::<VaultSessions>().with_session(&session, |s| {
+            decrypt_field(
+                &encrypted_totp,
+                "",
+                &salt,
+                Some(&s.master_password),
+                Some(&s.data_key),
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let secret = Zeroizing::new(secret?);
+
+    totp_now(&parse_totp(&secret)?)
+}
+
+/// Re-encrypt a vault for a new master password.
+/// Every entry is decrypted with the old master first, so a single failure
+/// leaves nothing rewritten. Only entries whose ciphertext changed are returned.
+fn rotate_master(
+    old_master: &str,
+    new_master: &str,
+    password_hash: &str,
+    wrapped_key: Option<&str>,
+    entries: &[VaultEntry],
+    kdf_params: Option<KdfParams>,
+) -> Result<RotateMasterResult, String> {
+    let params = resolve_kdf_params(kdf_params)?;
+    if new_master.is_empty() {
+        return Err("New master password must not be empty".to_string());
+    }
+    if !verify_password(old_master, password_hash)? {
+        return Err("Old master password is incorrect".to_string());
+    }
+
+    // With a data key only the key itself needs re-wrapping; data-key entries are
+    // left unchanged and legacy entries are moved onto the data key
+    let data_key = match wrapped_key {
+        Some(wrapped) if !wrapped.is_empty() => Some(unwrap_data_key(wrapped, old_master)?),
+        _ => None,
+    };
+    let pending: Vec<&VaultEntry> = entries
+        .iter()
+        .filter(|e| !entry_uses_data_key(&e.encrypted_value, &e.salt) || data_key.is_none())
+        .collect();
+
+    let mut decrypted = Vec::with_capacity(pending.len());
+    let mut rotated = Vec::with_capacity(pending.len());
+
+    let result = (|| {
+        for entry in &pending {
+            let fields = decrypt_entry_fields(
+                &entry.encrypted_value,
+                &entry.nonce,
+                entry.encrypted_notes.as_deref(),
+                &entry.salt,
+                old_master,
+            )
+            .map_err(|e| format!("Failed to decrypt entry {}: {}", entry.id, e))?;
+            decrypted.push(fields);
+        }
+
+        for (entry, (value, notes)) in pending.iter().zip(&decrypted) {
+            let encrypted = match &data_key {
+                Some(key) => encrypt_entry_fields_with_key(value, notes.as_deref(), key),
+                None => encrypt_entry_fields(value, notes.as_deref(), new_master, &params),
+            };
+            let (encrypted_value, nonce, encrypted_notes, _, salt) = encrypted
+                .map_err(|e| format!("Failed to encrypt entry {}: {}", entry.id, e))?;
+            rotated.push(VaultEntry {
+                encrypted_value,
+                encrypted_notes,
+                salt,
+                nonce,
+                ..(*entry).clone()
+            });
+        }
+        Ok::<(), String>(())
+    })();
+
+    // Zeroize decrypted plaintext whether or not the rotation succeeded
+    for (value, notes) in decrypted.iter_mut() {
+        value.zeroize();
+        if let Some(n) = notes {
+            n.zeroize();
+        }
+    }
+    result?;
+
+    let (password_hash, salt) = hash_password_with_params(new_master, &params)?;
+    let wrapped_key = match &data_key {
+        Some(key) => Some(wrap_data_key(key, new_master, &params)?),
+        None => None,
+    };
+
+    Ok(RotateMasterResult {
+        password_hash,
+        salt,
+        wrapped_key,
+        entries: rotated,
+    })
+}
+
+/// Rotate the master password of a project vault.
+/// The new hash, the re-wrapped data key and every re-encrypted entry are written
+/// in one transaction, together with a history version of each entry's previous
+/// ciphertext (still readable with the old master). Open sessions of the project
+/// are locked afterwards, since they cache the old master password.
+/// Returns the number of re-encrypted entries.
+#[tauri::command]
+pub async fn vault_rotate_master(
+    app: tauri::AppHandle,
+    project_id: i64,
+    old_master: String,
+    new_master: String,
+) -> Result<usize, String> {
+    let pool = db::pool(&app).await?;
+    let db_err = |e: sqlx::Error| format!("Failed to rotate master password: {}", e);
+
+    let master = sqlx::query(
+        "SELECT password_hash, wrapped_key, kdf_params, history_retention \
+         FROM vault_master WHERE project_id = ?",
+    )
+    .bind(project_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or("Vault is not initialized")?;
+    let password_hash: String = master.try_get("password_hash").map_err(db_err)?;
+    let wrapped_key: Option<String> = master.try_get("wrapped_key").map_err(db_err)?;
+    let kdf_params: Option<String> = master.try_get("kdf_params").map_err(db_err)?;
+    let retention: Option<i64> = master.try_get("history_retention").map_err(db_err)?;
+    let retention = retention_from_column(retention);
+    let kdf_params = kdf_params
+        .filter(|p| !p.is_empty())
+        .map(|p| serde_json::from_str::<KdfParams>(&p))
+        .transpose()
+        .map_err(|e| format!("Invalid KDF params: {}", e))?;
+
+    let entries = load_vault_entries(&pool, project_id).await?;
+    let history = load_history(&pool, project_id, None).await?;
+    let old_master = Zeroizing::new(old_master);
+    let new_master = Zeroizing::new(new_master);
+    let (rotated, entries) = tauri::async_runtime::spawn_blocking(move || {
+        let rotated = rotate_master(
+            &old_master,
+            &new_master,
+            &password_hash,
+            wrapped_key.as_deref(),
+            &entries,
+            kdf_params,
+        );
+        (rotated, entries)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let rotated = rotated?;
+    let previous: HashMap<i64, &VaultEntry> = entries.iter().map(|e| (e.id, e)).collect();
+
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    sqlx::query(
+        "UPDATE vault_master SET password_hash = ?, salt = ?, wrapped_key = ? WHERE project_id = ?",
+    )
+    .bind(&rotated.password_hash)
+    .bind(&rotated.salt)
+    .bind(&rotated.wrapped_key)
+    .bind(project_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+
+    for entry in &rotated.entries {
+        let stored = previous[&entry.id];
+        let plan = plan_archive(stored, true, &history, retention);
+        apply_archive(&mut tx, &plan).await.map_err(db_err)?;
+
+        // 只在密文未被并发修改时覆盖，否则整体回滚
+        let updated = sqlx::query(
+            "UPDATE vault_entries SET encrypted_value = ?, encrypted_notes = ?, salt = ?, nonce = ? \
+             WHERE id = ? AND project_id = ? AND encrypted_value = ?",
+        )
+        .bind(&entry.encrypted_value)
+        .bind(&entry.encrypted_notes)
+        .bind(&entry.salt)
+        .bind(&entry.nonce)
+        .bind(entry.id)
+        .bind(project_id)
+        .bind(&stored.encrypted_value)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        if updated.rows_affected() == 0 {
+            return Err(format!(
+                "Entry {} changed during the rotation, nothing was rewritten",
+                entry.id
+            ));
+        }
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    lock_project_sessions(&app, project_id);
+    Ok(rotated.entries.len())
+}
+
 /// Generate a random password
 #[tauri::command]
 pub fn vault_generate_password(
@@ -153,38 +1015,170 @@ pub fn vault_generate_password(
     include_numbers: bool,
     include_symbols: bool,
 ) -> Result<String, String> {
-    if length == 0 || length > 128 {
-        return Err("Password length must be between 1 and 128".to_string());
-    }
+    // Every selected character type is guaranteed to appear at least once
+    let options = PolicyOptions {
+        length,
+        uppercase: include_uppercase,
+        lowercase: include_lowercase,
+        numbers: include_numbers,
+        symbols: include_symbols,
+        ..PolicyOptions::default()
+    };
+
+    generate_policy(&options).map(|generated| generated.password)
+}
 
-    let mut charset = String::new();
+/// Generate a password or passphrase in any generator mode
+/// (policy, diceware, pronounceable or pattern), with an entropy estimate
+#[tauri::command]
+pub fn vault_generate(options: GeneratorOptions) -> Result<GeneratedPassword, String> {
+    generate(&options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::encryption::{decrypt_versioned_with_key, encrypt_data};
+
+    const OLD: &str = "old master password";
+    const NEW: &str = "new master password";
+    const LEGACY_SALT: &str = "legacy-entry-salt-0123456789abcdef";
 
-    if include_lowercase {
-        charset.push_str("abcdefghijklmnopqrstuvwxyz");
+    fn entry(
+        id: i64,
+        encrypted_value: String,
+        encrypted_notes: Option<String>,
+        salt: &str,
+        nonce: String,
+    ) -> VaultEntry {
+        VaultEntry {
+            id,
+            project_id: 1,
+            title: format!("entry {}", id),
+            param_key: None,
+            encrypted_value,
+            encrypted_notes,
+            url: None,
+            category: "general".to_string(),
+            salt: salt.to_string(),
+            nonce,
+            created_at: String::new(),
+            updated_at: String::new(),
+            encrypted_totp: None,
+            ssh_public_key: None,
+            ssh_fingerprint: None,
+        }
     }
-    if include_uppercase {
-        charset.push_str("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+
+    /// Entry in the pre-envelope layout: per-entry salt, nonces as `value|notes`
+    fn legacy_entry(id: i64, value: &str, notes: &str) -> VaultEntry {
+        let (enc_value, value_nonce) = encrypt_data(value, OLD, LEGACY_SALT).unwrap();
+        let (enc_notes, notes_nonce) = encrypt_data(notes, OLD, LEGACY_SALT).unwrap();
+        entry(
+            id,
+            enc_value,
+            Some(enc_notes),
+            LEGACY_SALT,
+            format!("{}|{}", value_nonce, notes_nonce),
+        )
     }
-    if include_numbers {
-        charset.push_str("0123456789");
+
+    #[test]
+    fn test_close_where_recovers_poisoned_lock() {
+        let sessions = VaultSessions::default();
+        sessions
+            .open(VaultSession {
+                project_id: 7,
+                data_key: generate_data_key(),
+                master_password: Zeroizing::new(OLD.to_string()),
+                idle_timeout: Duration::from_secs(60),
+                last_used: Instant::now(),
+            })
+            .unwrap();
+
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = sessions.0.lock().unwrap();
+            panic!("poison the session lock");
+        }));
+        assert!(sessions.0.is_poisoned());
+
+        assert_eq!(sessions.close_where(|_| true), vec![7]);
+        assert!(!sessions.is_unlocked(7));
     }
-    if include_symbols {
-        charset.push_str("!@#$%^&*()_+-=[]{}|;:,.<>?");
+
+    #[test]
+    fn test_rotate_master_rejects_wrong_old_master() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params(OLD, &params).unwrap();
+        let key = generate_data_key();
+        let wrapped = wrap_data_key(&key, OLD, &params).unwrap();
+        let entries = vec![legacy_entry(1, "value", "notes")];
+
+        let err = rotate_master("not the master", NEW, &hash, Some(&wrapped), &entries, None)
+            .err()
+            .unwrap();
+        assert_eq!(err, "Old master password is incorrect");
     }
 
-    if charset.is_empty() {
-        return Err("At least one character type must be selected".to_string());
+    #[test]
+    fn test_rotate_master_keeps_notes_and_totp() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params(OLD, &params).unwrap();
+        let key = generate_data_key();
+        let wrapped = wrap_data_key(&key, OLD, &params).unwrap();
+
+        let (enc_value, nonce, enc_notes, _, salt) =
+            encrypt_entry_fields_with_key("api-token", Some("data key notes"), &key).unwrap();
+        let mut keyed = entry(1, enc_value, enc_notes, &salt, nonce);
+        keyed.encrypted_totp = Some(encrypt_versioned_with_key("JBSWY3DPEHPK3PXP", &key).unwrap());
+        let entries = vec![keyed.clone(), legacy_entry(2, "db-password", "legacy notes")];
+
+        let rotated = rotate_master(OLD, NEW, &hash, Some(&wrapped), &entries, None).unwrap();
+        assert!(verify_password(NEW, &rotated.password_hash).unwrap());
+        assert!(!verify_password(OLD, &rotated.password_hash).unwrap());
+
+        // Only the legacy entry is rewritten, moved onto the same data key
+        let new_key = unwrap_data_key(rotated.wrapped_key.as_deref().unwrap(), NEW).unwrap();
+        assert_eq!(*new_key, *key);
+        assert!(unwrap_data_key(rotated.wrapped_key.as_deref().unwrap(), OLD).is_err());
+        assert_eq!(rotated.entries.len(), 1);
+        let moved = &rotated.entries[0];
+        assert_eq!(moved.id, 2);
+        assert_eq!(moved.salt, DATA_KEY_SALT);
+
+        let (value, notes) = decrypt_entry_with_master(moved, NEW, Some(&new_key)).unwrap();
+        assert_eq!(value, "db-password");
+        assert_eq!(notes.as_deref(), Some("legacy notes"));
+
+        let (value, notes) = decrypt_entry_with_master(&keyed, NEW, Some(&new_key)).unwrap();
+        assert_eq!(value, "api-token");
+        assert_eq!(notes.as_deref(), Some("data key notes"));
+        let totp = decrypt_versioned_with_key(keyed.encrypted_totp.as_deref().unwrap(), &new_key);
+        assert_eq!(totp.unwrap(), "JBSWY3DPEHPK3PXP");
     }
 
-    let charset_bytes: Vec<char> = charset.chars().collect();
-    let mut rng = rand::thread_rng();
+    #[test]
+    fn test_rotate_master_without_data_key_reencrypts_with_new_master() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params(OLD, &params).unwrap();
+        let entries = vec![legacy_entry(1, "value", "multi\nline notes")];
 
-    let password: String = (0..length)
-        .map(|_| {
-            let idx = rng.gen_range(0..charset_bytes.len());
-            charset_bytes[idx]
-        })
-        .collect();
+        let rotated = rotate_master(OLD, NEW, &hash, None, &entries, None).unwrap();
+        assert!(rotated.wrapped_key.is_none());
+        let (value, notes) = decrypt_entry_with_master(&rotated.entries[0], NEW, None).unwrap();
+        assert_eq!(value, "value");
+        assert_eq!(notes.as_deref(), Some("multi\nline notes"));
+        assert!(decrypt_entry_with_master(&rotated.entries[0], OLD, None).is_err());
+    }
+
+    #[test]
+    fn test_rotate_master_rejects_notes_without_nonce() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params(OLD, &params).unwrap();
+        let mut broken = legacy_entry(1, "value", "notes");
+        broken.nonce = broken.nonce.split('|').next().unwrap().to_string();
 
-    Ok(password)
+        let err = rotate_master(OLD, NEW, &hash, None, &[broken], None).err().unwrap();
+        assert!(err.contains("Notes nonce is missing"), "{}", err);
+    }
 }
@@ -0,0 +1,389 @@
+use crate::commands::vault::{VaultSessions, find_vault_entry};
+use crate::crypto::{
+    DATA_KEY_LEN, decrypt_stream_with_key, decrypt_with_key, encrypt_stream_with_key,
+    encrypt_versioned_with_key,
+};
+use crate::db;
+use chrono::Utc;
+use rand::{Rng, distributions::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// Extension of the encrypted file body (chunked AES-256-GCM stream, see `crypto::stream`)
+const BLOB_EXT: &str = "pmse";
+/// Extension of the per-attachment metadata file
+const META_EXT: &str = "json";
+
+/// Metadata stored next to every encrypted attachment.
+/// The original file name is sealed with the project data key.
+#[derive(Debug, Serialize, Deserialize)]
+struct AttachmentMeta {
+    id: String,
+    entry_id: i64,
+    encrypted_name: String,
+    size: u64,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultAttachment {
+    pub id: String,
+    pub entry_id: i64,
+    pub file_name: String,
+    pub size: u64,
+    pub created_at: String,
+}
+
+/// Get the attachments directory of a project vault:
+/// <app_data>/data/vault_attachments/<project_id>
+pub(crate) fn get_project_attachments_dir(
+    app: &tauri::AppHandle,
+    project_id: i64,
+) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    Ok(app_data_dir
+        .join("data")
+        .join("vault_attachments")
+        .join(project_id.to_string()))
+}
+
+/// Get the attachments directory of a vault entry:
+/// <app_data>/data/vault_attachments/<project_id>/<entry_id>
+fn get_entry_attachments_dir(
+    app: &tauri::AppHandle,
+    project_id: i64,
+    entry_id: i64,
+) -> Result<PathBuf, String> {
+    Ok(get_project_attachments_dir(app, project_id)?.join(entry_id.to_string()))
+}
+
+/// Attachments directory of `entry_id` once the entry is known to belong to the
+/// session's project, so a session can't touch blobs filed under another entry id
+async fn owned_entry_attachments_dir(
+    app: &tauri::AppHandle,
+    project_id: i64,
+    entry_id: i64,
+) -> Result<PathBuf, String> {
+    let pool = db::pool(app).await?;
+    find_vault_entry(&pool, project_id, entry_id).await?;
+    get_entry_attachments_dir(app, project_id, entry_id)
+}
+
+fn generate_attachment_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+/// Attachment ids become file names, so only accept what `generate_attachment_id` produces
+fn validate_attachment_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("Invalid attachment id".to_string());
+    }
+    Ok(())
+}
+
+fn read_meta(path: &Path) -> Result<AttachmentMeta, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read attachment metadata: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid attachment metadata: {}", e))
+}
+
+fn to_attachment(
+    meta: AttachmentMeta,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<VaultAttachment, String> {
+    Ok(VaultAttachment {
+        file_name: decrypt_with_key(&meta.encrypted_name, "", data_key)?,
+        id: meta.id,
+        entry_id: meta.entry_id,
+        size: meta.size,
+        created_at: meta.created_at,
+    })
+}
+
+/// Create a file readable only by the current user
+pub(crate) fn create_private_file(path: &Path) -> Result<fs::File, String> {
+    let file = private_open_options()
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    // `mode` only applies to new files; tighten an existing one as well
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to set file permissions: {}", e))?;
+    }
+
+    Ok(file)
+}
+
+/// Like `create_private_file`, but fails instead of replacing an existing file
+pub(crate) fn create_new_private_file(path: &Path) -> Result<fs::File, String> {
+    private_open_options()
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => format!("File already exists: {}", path.display()),
+            _ => format!("Failed to create file: {}", e),
+        })
+}
+
+fn private_open_options() -> fs::OpenOptions {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+/// Authenticated with every chunk of an attachment blob, so a blob copied to another
+/// entry or renamed to another attachment id no longer decrypts
+fn blob_context(entry_id: i64, attachment_id: &str) -> Vec<u8> {
+    format!("vault-attachment:{}:{}", entry_id, attachment_id).into_bytes()
+}
+
+/// Stream `source_path` through the encryptor into the entry's attachment directory
+#[tauri::command]
+pub async fn vault_attach_file(
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    entry_id: i64,
+    source_path: String,
+) -> Result<VaultAttachment, String> {
+    let (project_id, data_key) = sessions.data_key(&session)?;
+    let dir = owned_entry_attachments_dir(&app, project_id, entry_id).await?;
+
+    // Encrypting a large file takes a while; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        store_attachment(&dir, entry_id, Path::new(&source_path), &data_key)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn store_attachment(
+    dir: &Path,
+    entry_id: i64,
+    source: &Path,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<VaultAttachment, String> {
+    if !source.is_file() {
+        return Err(format!("File not found: {}", source.display()));
+    }
+    let file_name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Invalid file name")?
+        .to_string();
+
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+
+    let id = generate_attachment_id();
+    let blob_path = dir.join(format!("{}.{}", id, BLOB_EXT));
+    let tmp_path = dir.join(format!("{}.{}.tmp", id, BLOB_EXT));
+
+    // Encrypt into a temp file first so a failed write never leaves a half blob behind
+    let reader = BufReader::new(
+        fs::File::open(source).map_err(|e| format!("Failed to open file: {}", e))?,
+    );
+    let writer = BufWriter::new(create_private_file(&tmp_path)?);
+    let context = blob_context(entry_id, &id);
+    let size = match encrypt_stream_with_key(reader, writer, data_key, &context) {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    };
+    fs::rename(&tmp_path, &blob_path).map_err(|e| format!("Failed to store attachment: {}", e))?;
+
+    let meta = AttachmentMeta {
+        id: id.clone(),
+        entry_id,
+        encrypted_name: encrypt_versioned_with_key(&file_name, data_key)?,
+        size,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let json = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.{}", id, META_EXT)), json)
+        .map_err(|e| format!("Failed to write attachment metadata: {}", e))?;
+
+    to_attachment(meta, data_key)
+}
+
+/// List the attachments of a vault entry, oldest first
+#[tauri::command]
+pub async fn vault_list_attachments(
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    entry_id: i64,
+) -> Result<Vec<VaultAttachment>, String> {
+    let (project_id, data_key) = sessions.data_key(&session)?;
+
+    let dir = owned_entry_attachments_dir(&app, project_id, entry_id).await?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut attachments = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(META_EXT) {
+            continue;
+        }
+        attachments.push(to_attachment(read_meta(&path)?, &data_key)?);
+    }
+    attachments.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    Ok(attachments)
+}
+
+/// Decrypt an attachment to `target_path` (created with 0600 permissions on Unix).
+/// Returns the number of bytes written.
+#[tauri::command]
+pub async fn vault_extract_attachment(
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    entry_id: i64,
+    attachment_id: String,
+    target_path: String,
+) -> Result<u64, String> {
+    validate_attachment_id(&attachment_id)?;
+    let (project_id, data_key) = sessions.data_key(&session)?;
+    let dir = owned_entry_attachments_dir(&app, project_id, entry_id).await?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        extract_attachment(&dir, entry_id, &attachment_id, Path::new(&target_path), &data_key)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn extract_attachment(
+    dir: &Path,
+    entry_id: i64,
+    attachment_id: &str,
+    target: &Path,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<u64, String> {
+    let blob_path = dir.join(format!("{}.{}", attachment_id, BLOB_EXT));
+    if !blob_path.exists() {
+        return Err("Attachment not found".to_string());
+    }
+
+    // Decrypt next to the target and rename on success, so a tampered blob
+    // never leaves partially decrypted data at the destination
+    let tmp_name = format!(
+        ".{}.tmp",
+        target.file_name().and_then(|n| n.to_str()).ok_or("Invalid target path")?
+    );
+    let tmp_path = target.with_file_name(tmp_name);
+
+    let reader = BufReader::new(
+        fs::File::open(&blob_path).map_err(|e| format!("Failed to open attachment: {}", e))?,
+    );
+    let writer = BufWriter::new(create_private_file(&tmp_path)?);
+    let context = blob_context(entry_id, attachment_id);
+    let written = match decrypt_stream_with_key(reader, writer, data_key, &context) {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    };
+    fs::rename(&tmp_path, target).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(written)
+}
+
+/// Delete one attachment of a vault entry, or all of them when `attachment_id` is `None`
+/// (called before the entry itself is deleted)
+#[tauri::command]
+pub async fn vault_delete_attachment(
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    entry_id: i64,
+    attachment_id: Option<String>,
+) -> Result<(), String> {
+    let project_id = sessions.project_id(&session)?;
+    let dir = owned_entry_attachments_dir(&app, project_id, entry_id).await?;
+
+    let Some(attachment_id) = attachment_id else {
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .map_err(|e| format!("Failed to delete attachments: {}", e))?;
+        }
+        return Ok(());
+    };
+
+    validate_attachment_id(&attachment_id)?;
+    for ext in [BLOB_EXT, META_EXT] {
+        let path = dir.join(format!("{}.{}", attachment_id, ext));
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete attachment: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+
+    #[test]
+    fn test_attachment_roundtrip_and_binding() {
+        let root = temp_dir("attachments", "binding");
+        let key = [5u8; DATA_KEY_LEN];
+        let source = root.join("id_rsa.pem");
+        fs::write(&source, b"-----BEGIN KEY-----\nsecret\n").unwrap();
+
+        let entry_dir = root.join("1");
+        let attachment = store_attachment(&entry_dir, 1, &source, &key).unwrap();
+        assert_eq!(attachment.file_name, "id_rsa.pem");
+        assert_eq!(attachment.size, 27);
+
+        let target = root.join("out.pem");
+        extract_attachment(&entry_dir, 1, &attachment.id, &target, &key).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), fs::read(&source).unwrap());
+
+        // 复制到另一个条目下的附件无法解密，也不会留下临时文件
+        let other_dir = root.join("2");
+        fs::create_dir_all(&other_dir).unwrap();
+        let blob = format!("{}.{}", attachment.id, BLOB_EXT);
+        fs::copy(entry_dir.join(&blob), other_dir.join(&blob)).unwrap();
+        let moved = root.join("moved.pem");
+        assert!(extract_attachment(&other_dir, 2, &attachment.id, &moved, &key).is_err());
+        assert!(!moved.exists());
+        assert!(!root.join(".moved.pem.tmp").exists());
+
+        // 改名为另一个附件 id 同样无法解密
+        fs::copy(entry_dir.join(&blob), entry_dir.join(format!("Other.{}", BLOB_EXT))).unwrap();
+        assert!(extract_attachment(&entry_dir, 1, "Other", &moved, &key).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
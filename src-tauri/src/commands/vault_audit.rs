@@ -0,0 +1,401 @@
+use crate::commands::vault::{VaultEntry, VaultSessions, decrypt_entry_with_master};
+use crate::crypto::strength::estimate_strength;
+use crate::crypto::{unwrap_data_key, verify_password};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::Manager;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Default rotation age when the caller does not pass one
+pub const DEFAULT_ROTATION_DAYS: u32 = 90;
+
+/// Entries scoring below this are reported as weak
+const WEAK_SCORE: u8 = 3;
+
+/// Another project's entries, compared for reuse when that vault is unlocked
+#[derive(Debug, Deserialize)]
+pub struct AuditOtherProject {
+    pub project_id: i64,
+    pub project_name: String,
+    pub entries: Vec<VaultEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReuseRef {
+    pub project_id: i64,
+    pub project_name: Option<String>,
+    pub entry_id: i64,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntryAudit {
+    pub entry_id: i64,
+    pub title: String,
+    pub param_key: Option<String>,
+    pub category: String,
+    /// 0 (very weak) to 4 (very strong)
+    pub score: u8,
+    pub entropy_bits: f64,
+    pub warnings: Vec<String>,
+    /// Other entries, in this or other projects, holding the same value
+    pub reused_with: Vec<ReuseRef>,
+    /// Days since `updated_at`; `None` when the timestamp cannot be parsed
+    pub age_days: Option<i64>,
+    pub rotation_due: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuditSummary {
+    pub total: usize,
+    pub weak: usize,
+    pub reused: usize,
+    pub rotation_due: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub project_id: i64,
+    pub generated_at: String,
+    pub rotation_days: u32,
+    pub entries: Vec<EntryAudit>,
+    pub summary: AuditSummary,
+    /// Other projects that were not compared because their vault is locked
+    pub skipped_projects: Vec<i64>,
+}
+
+/// `updated_at` is written by SQLite as `YYYY-MM-DD HH:MM:SS` (UTC)
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc())
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc))
+        })
+}
+
+/// Decrypt every entry of a project with the master password and report weak values,
+/// values reused within the project or in other unlocked projects, and entries not
+/// rotated within `rotation_days` (default 90).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn vault_audit(
+    app: tauri::AppHandle,
+    project_id: i64,
+    master_password: String,
+    password_hash: String,
+    wrapped_key: Option<String>,
+    entries: Vec<VaultEntry>,
+    other_projects: Option<Vec<AuditOtherProject>>,
+    rotation_days: Option<u32>,
+) -> Result<AuditReport, String> {
+    let master_password = Zeroizing::new(master_password);
+
+    // Argon2 and decrypting every entry take a while; keep them off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        audit_project(
+            &app.state::<VaultSessions>(),
+            project_id,
+            &master_password,
+            &password_hash,
+            wrapped_key.as_deref(),
+            &entries,
+            other_projects.as_deref().unwrap_or_default(),
+            rotation_days,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn audit_project(
+    sessions: &VaultSessions,
+    project_id: i64,
+    master_password: &str,
+    password_hash: &str,
+    wrapped_key: Option<&str>,
+    entries: &[VaultEntry],
+    other_projects: &[AuditOtherProject],
+    rotation_days: Option<u32>,
+) -> Result<AuditReport, String> {
+    if !verify_password(master_password, password_hash)? {
+        return Err("Invalid master password".to_string());
+    }
+
+    let data_key = match wrapped_key {
+        Some(wrapped) if !wrapped.is_empty() => Some(unwrap_data_key(wrapped, master_password)?),
+        _ => None,
+    };
+
+    // (value, owner) pairs for every decrypted entry, this project first
+    let mut values: Vec<(String, ReuseRef)> = Vec::new();
+    let mut skipped_projects = Vec::new();
+
+    let result = (|| {
+        for entry in entries.iter().filter(|e| e.project_id == project_id) {
+            let (value, mut notes) =
+                decrypt_entry_with_master(entry, master_password, data_key.as_deref())
+                    .map_err(|e| format!("Failed to decrypt entry {}: {}", entry.id, e))?;
+            notes.zeroize();
+            values.push((
+                value,
+                ReuseRef {
+                    project_id,
+                    project_name: None,
+                    entry_id: entry.id,
+                    title: entry.title.clone(),
+                },
+            ));
+        }
+
+        for other in other_projects {
+            if other.project_id == project_id {
+                continue;
+            }
+            if !sessions.is_unlocked(other.project_id) {
+                skipped_projects.push(other.project_id);
+                continue;
+            }
+            for entry in other.entries.iter().filter(|e| e.project_id == other.project_id) {
+                // 其他项目的条目解密失败不影响本项目的审计
+                let Ok((value, mut notes)) = sessions.decrypt_unlocked_entry(entry) else {
+                    continue;
+                };
+                notes.zeroize();
+                values.push((
+                    value,
+                    ReuseRef {
+                        project_id: other.project_id,
+                        project_name: Some(other.project_name.clone()),
+                        entry_id: entry.id,
+                        title: entry.title.clone(),
+                    },
+                ));
+            }
+        }
+
+        Ok::<(), String>(())
+    })();
+
+    let report = result.map(|_| {
+        build_report(
+            project_id,
+            entries,
+            &values,
+            rotation_days.unwrap_or(DEFAULT_ROTATION_DAYS),
+            skipped_projects,
+        )
+    });
+
+    // Zeroize decrypted values whether or not the audit succeeded
+    for (value, _) in values.iter_mut() {
+        value.zeroize();
+    }
+
+    report
+}
+
+fn build_report(
+    project_id: i64,
+    entries: &[VaultEntry],
+    values: &[(String, ReuseRef)],
+    rotation_days: u32,
+    skipped_projects: Vec<i64>,
+) -> AuditReport {
+    let mut by_value: HashMap<&str, Vec<&ReuseRef>> = HashMap::new();
+    for (value, owner) in values {
+        by_value.entry(value.as_str()).or_default().push(owner);
+    }
+
+    let now = Utc::now();
+    let mut summary = AuditSummary::default();
+    let mut audits = Vec::new();
+
+    for (value, owner) in values.iter().filter(|(_, o)| o.project_id == project_id) {
+        let Some(entry) = entries.iter().find(|e| e.id == owner.entry_id) else {
+            continue;
+        };
+
+        let strength = estimate_strength(value);
+        let reused_with: Vec<ReuseRef> = by_value[value.as_str()]
+            .iter()
+            .filter(|o| !(o.project_id == owner.project_id && o.entry_id == owner.entry_id))
+            .map(|o| (*o).clone())
+            .collect();
+        let age_days = parse_timestamp(&entry.updated_at).map(|t| (now - t).num_days());
+        let rotation_due = age_days.is_some_and(|days| days >= i64::from(rotation_days));
+
+        summary.total += 1;
+        if strength.score < WEAK_SCORE {
+            summary.weak += 1;
+        }
+        if !reused_with.is_empty() {
+            summary.reused += 1;
+        }
+        if rotation_due {
+            summary.rotation_due += 1;
+        }
+
+        audits.push(EntryAudit {
+            entry_id: entry.id,
+            title: entry.title.clone(),
+            param_key: entry.param_key.clone(),
+            category: entry.category.clone(),
+            score: strength.score,
+            entropy_bits: strength.entropy_bits,
+            warnings: strength.warnings,
+            reused_with,
+            age_days,
+            rotation_due,
+        });
+    }
+
+    // 最需要处理的排在前面：复用 > 弱密码 > 过期
+    audits.sort_by_key(|a| (a.reused_with.is_empty(), a.score, !a.rotation_due));
+
+    AuditReport {
+        project_id,
+        generated_at: now.to_rfc3339(),
+        rotation_days,
+        entries: audits,
+        summary,
+        skipped_projects,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, project_id: i64, updated_at: &str) -> VaultEntry {
+        VaultEntry {
+            id,
+            project_id,
+            title: format!("entry {}", id),
+            param_key: None,
+            encrypted_value: String::new(),
+            encrypted_notes: None,
+            url: None,
+            category: "general".to_string(),
+            salt: String::new(),
+            nonce: String::new(),
+            created_at: String::new(),
+            updated_at: updated_at.to_string(),
+            encrypted_totp: None,
+            ssh_public_key: None,
+            ssh_fingerprint: None,
+        }
+    }
+
+    fn value(
+        value: &str,
+        project_id: i64,
+        project_name: Option<&str>,
+        entry_id: i64,
+    ) -> (String, ReuseRef) {
+        (
+            value.to_string(),
+            ReuseRef {
+                project_id,
+                project_name: project_name.map(str::to_string),
+                entry_id,
+                title: format!("entry {}", entry_id),
+            },
+        )
+    }
+
+    fn audit(report: &AuditReport, entry_id: i64) -> &EntryAudit {
+        report.entries.iter().find(|a| a.entry_id == entry_id).unwrap()
+    }
+
+    #[test]
+    fn test_build_report_detects_reuse_across_projects() {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let entries = vec![entry(1, 1, &now), entry(2, 1, &now), entry(3, 1, &now)];
+        let strong = "Vq8#mZ2!rT5&wLx9@Kp4";
+        let values = vec![
+            value("shared-in-project", 1, None, 1),
+            value("shared-in-project", 1, None, 2),
+            value(strong, 1, None, 3),
+            value(strong, 2, Some("Other"), 10),
+            // 其他项目内部的复用不属于本项目的报告
+            value("other-only", 2, Some("Other"), 11),
+            value("other-only", 2, Some("Other"), 12),
+        ];
+
+        let report = build_report(1, &entries, &values, 90, vec![3]);
+        assert_eq!(report.entries.len(), 3);
+        assert_eq!(report.skipped_projects, vec![3]);
+        assert_eq!(report.summary.total, 3);
+        assert_eq!(report.summary.reused, 3);
+
+        let first = audit(&report, 1);
+        assert_eq!(first.reused_with.len(), 1);
+        assert_eq!(first.reused_with[0].entry_id, 2);
+        assert_eq!(first.reused_with[0].project_name, None);
+
+        let cross = audit(&report, 3);
+        assert_eq!(cross.reused_with.len(), 1);
+        assert_eq!(cross.reused_with[0].project_id, 2);
+        assert_eq!(cross.reused_with[0].project_name.as_deref(), Some("Other"));
+        assert_eq!(cross.reused_with[0].entry_id, 10);
+    }
+
+    #[test]
+    fn test_build_report_weak_values_and_ordering() {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let entries = vec![entry(1, 1, &now), entry(2, 1, &now), entry(3, 1, &now)];
+        let values = vec![
+            value("Vq8#mZ2!rT5&wLx9@Kp4", 1, None, 1),
+            value("password", 1, None, 2),
+            value("dup-Vq8#mZ2!rT5&wLx9", 1, None, 3),
+            value("dup-Vq8#mZ2!rT5&wLx9", 2, Some("Other"), 10),
+        ];
+
+        let report = build_report(1, &entries, &values, 90, Vec::new());
+        assert_eq!(report.summary.weak, 1);
+        assert!(audit(&report, 2).score < WEAK_SCORE);
+
+        // 复用的排最前，其次是弱密码
+        let order: Vec<i64> = report.entries.iter().map(|a| a.entry_id).collect();
+        assert_eq!(order, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_build_report_rotation_age() {
+        let recent = (Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+        let entries = vec![
+            entry(1, 1, "2000-01-01 00:00:00"),
+            entry(2, 1, &recent),
+            entry(3, 1, "not a timestamp"),
+        ];
+        let values = vec![
+            value("Vq8#mZ2!rT5&wLx9@Kp4-a", 1, None, 1),
+            value("Vq8#mZ2!rT5&wLx9@Kp4-b", 1, None, 2),
+            value("Vq8#mZ2!rT5&wLx9@Kp4-c", 1, None, 3),
+        ];
+
+        let report = build_report(1, &entries, &values, 30, Vec::new());
+        assert_eq!(report.rotation_days, 30);
+        assert_eq!(report.summary.rotation_due, 1);
+
+        let old = audit(&report, 1);
+        assert!(old.rotation_due);
+        assert!(old.age_days.unwrap() > 365 * 20);
+
+        let fresh = audit(&report, 2);
+        assert_eq!(fresh.age_days, Some(10));
+        assert!(!fresh.rotation_due);
+        // 恰好到期也算需要轮换
+        let strict = build_report(1, &entries, &values, 10, Vec::new());
+        assert!(audit(&strict, 2).rotation_due);
+
+        let unknown = audit(&report, 3);
+        assert_eq!(unknown.age_days, None);
+        assert!(!unknown.rotation_due);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::commands::vault::{VaultEntry, VaultSessions};
+use crate::commands::vault_attachments::create_private_file;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use tauri::Manager;
+use zeroize::Zeroize;
+
+/// Output format of `vault_export_env`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvExportFormat {
+    /// `KEY=value` lines for `.env` files
+    Dotenv,
+    /// `{"KEY": "value"}`
+    Json,
+    /// `export KEY='value'` lines
+    Shell,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub id: i64,
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvExportResult {
+    /// Rendered output; `None` when it was written to `output_path`
+    pub content: Option<String>,
+    pub exported: usize,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Environment variable names: a letter or `_`, then letters, digits or `_`
+fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Values made only of these characters are written to `.env` without quotes
+fn is_plain_dotenv_value(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+,=".contains(c))
+}
+
+/// Double-quoted `.env` value; `$` is escaped so loaders with variable expansion
+/// keep it literal
+fn escape_dotenv(value: &str) -> String {
+    if is_plain_dotenv_value(value) {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '$' => out.push_str("\\$"),
+            '`' => out.push_str("\\`"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// POSIX single-quoted shell word: nothing is interpreted inside single quotes,
+/// and a literal `'` becomes `'\''`
+fn escape_shell(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn render(format: EnvExportFormat, vars: &[(String, String)]) -> Result<String, String> {
+    let content = match format {
+        EnvExportFormat::Dotenv => vars
+            .iter()
+            .map(|(k, v)| format!("{}={}\n", k, escape_dotenv(v)))
+            .collect(),
+        EnvExportFormat::Shell => vars
+            .iter()
+            .map(|(k, v)| format!("export {}={}\n", k, escape_shell(v)))
+            .collect(),
+        EnvExportFormat::Json => {
+            let map: serde_json::Map<String, serde_json::Value> = vars
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                .collect();
+            let mut json = serde_json::to_string_pretty(&map).map_err(|e| e.to_string())?;
+            json.push('\n');
+            json
+        }
+    };
+    Ok(content)
+}
+
+/// Decrypt a project's entries and render them as environment variables.
+/// `param_key` becomes the variable name; entries without a valid key, or whose
+/// key was already exported, are skipped and reported. When `categories` is given
+/// only entries in those categories are exported. With `output_path` the result is
+/// written to that file (0600 on Unix) instead of being returned.
+#[tauri::command]
+pub async fn vault_export_env(
+    app: tauri::AppHandle,
+    session: String,
+    entries: Vec<VaultEntry>,
+    format: EnvExportFormat,
+    categories: Option<Vec<String>>,
+    output_path: Option<String>,
+) -> Result<EnvExportResult, String> {
+    // Legacy entries need one Argon2 derivation per field; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        export_env(
+            &app.state::<VaultSessions>(),
+            &session,
+            &entries,
+            format,
+            categories.as_deref(),
+            output_path.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn export_env(
+    sessions: &VaultSessions,
+    session: &str,
+    entries: &[VaultEntry],
+    format: EnvExportFormat,
+    categories: Option<&[String]>,
+    output_path: Option<&str>,
+) -> Result<EnvExportResult, String> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skipped = Vec::new();
+
+    let result = (|| {
+        for entry in entries {
+            if categories
+                .is_some_and(|c| !c.contains(&entry.category))
+            {
+                continue;
+            }
+
+            let key = entry.param_key.as_deref().unwrap_or("").trim();
+            let reason = if key.is_empty() {
+                Some("Missing parameter key")
+            } else if !is_valid_env_key(key) {
+                Some("Parameter key is not a valid variable name")
+            } else if seen.contains(key) {
+                Some("Duplicate parameter key")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                skipped.push(SkippedEntry {
+                    id: entry.id,
+                    title: entry.title.clone(),
+                    reason: reason.to_string(),
+                });
+                continue;
+            }
+
+            let (value, mut notes) = sessions
+                .decrypt_entry(session, entry)
+                .map_err(|e| format!("Failed to decrypt entry {}: {}", entry.id, e))?;
+            notes.zeroize();
+
+            seen.insert(key.to_string());
+            vars.push((key.to_string(), value));
+        }
+
+        render(format, &vars)
+    })();
+
+    // Zeroize decrypted values whether or not rendering succeeded
+    for (_, value) in vars.iter_mut() {
+        value.zeroize();
+    }
+    let mut content = result?;
+    let exported = seen.len();
+
+    let Some(output_path) = output_path else {
+        return Ok(EnvExportResult {
+            content: Some(content),
+            exported,
+            skipped,
+        });
+    };
+
+    let written = create_private_file(std::path::Path::new(output_path)).and_then(|mut f| {
+        f.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write file: {}", e))
+    });
+    content.zeroize();
+    written?;
+
+    Ok(EnvExportResult {
+        content: None,
+        exported,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_dotenv() {
+        let cases = [
+            ("plain-value_1.2:3@host/path", "plain-value_1.2:3@host/path"),
+            ("", "\"\""),
+            ("with space", "\"with space\""),
+            ("say \"hi\"", "\"say \\\"hi\\\"\""),
+            ("it's", "\"it's\""),
+            ("$HOME and ${PATH}", "\"\\$HOME and \\${PATH}\""),
+            ("`whoami`", "\"\\`whoami\\`\""),
+            ("C:\\dir\\file", "\"C:\\\\dir\\\\file\""),
+            ("line1\nline2\r\n", "\"line1\\nline2\\r\\n\""),
+            ("a\tb", "\"a\\tb\""),
+            ("#not-a-comment", "\"#not-a-comment\""),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(escape_dotenv(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_escape_shell() {
+        let cases = [
+            ("plain", "'plain'"),
+            ("", "''"),
+            ("it's", "'it'\\''s'"),
+            ("''", "''\\'''\\'''"),
+            ("$HOME `whoami` \"q\"", "'$HOME `whoami` \"q\"'"),
+            ("back\\slash", "'back\\slash'"),
+            ("line1\nline2", "'line1\nline2'"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(escape_shell(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let vars = vec![
+            ("API_KEY".to_string(), "abc123".to_string()),
+            ("QUOTED".to_string(), "it's \"$x\"".to_string()),
+        ];
+
+        assert_eq!(
+            render(EnvExportFormat::Dotenv, &vars).unwrap(),
+            "API_KEY=abc123\nQUOTED=\"it's \\\"\\$x\\\"\"\n"
+        );
+        assert_eq!(
+            render(EnvExportFormat::Shell, &vars).unwrap(),
+            "export API_KEY='abc123'\nexport QUOTED='it'\\''s \"$x\"'\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(EnvExportFormat::Json, &vars).unwrap()).unwrap();
+        assert_eq!(json["QUOTED"], "it's \"$x\"");
+    }
+}
@@ -0,0 +1,589 @@
+use crate::commands::vault::{
+    VaultEntry, VaultSessions, decrypt_entry_with_master, encrypt_entry_fields_with_key,
+    find_vault_entry,
+};
+use crate::crypto::totp::parse_totp;
+use crate::crypto::{encrypt_versioned_with_key, unwrap_data_key, verify_password};
+use crate::db;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use tauri::Manager;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Versions kept per entry when the project has no `history_retention` set
+pub const DEFAULT_HISTORY_RETENTION: usize = 10;
+
+const HISTORY_COLUMNS: &str = "id, entry_id, project_id, version, encrypted_value, \
+    encrypted_notes, encrypted_totp, salt, nonce, CAST(archived_at AS TEXT) AS archived_at";
+
+/// A row of `vault_entry_history`: the ciphertext an entry held before an update
+#[derive(Debug, Clone)]
+pub(crate) struct VaultEntryVersion {
+    pub id: i64,
+    pub entry_id: i64,
+    pub project_id: i64,
+    pub version: i64,
+    pub encrypted_value: String,
+    pub encrypted_notes: Option<String>,
+    pub encrypted_totp: Option<String>,
+    pub salt: String,
+    pub nonce: String,
+    pub archived_at: String,
+}
+
+/// History row to insert, copied verbatim from the entry being overwritten
+#[derive(Debug)]
+pub(crate) struct NewEntryVersion {
+    pub entry_id: i64,
+    pub project_id: i64,
+    pub version: i64,
+    pub encrypted_value: String,
+    pub encrypted_notes: Option<String>,
+    pub encrypted_totp: Option<String>,
+    pub salt: String,
+    pub nonce: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct ArchivePlan {
+    /// `None` when the value and notes did not change
+    pub version: Option<NewEntryVersion>,
+    /// History row ids beyond the retention count, oldest first
+    pub prune: Vec<i64>,
+}
+
+/// Version metadata for listing; ciphertext stays out of the UI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultVersionInfo {
+    pub id: i64,
+    pub version: i64,
+    pub archived_at: String,
+    pub has_notes: bool,
+    pub has_totp: bool,
+}
+
+/// New contents of an entry for `vault_update_entry`
+#[derive(Debug, Deserialize)]
+pub struct VaultEntryUpdate {
+    pub title: String,
+    pub param_key: Option<String>,
+    pub param_value: String,
+    pub notes: Option<String>,
+    pub url: Option<String>,
+    pub category: Option<String>,
+    /// `None` keeps the stored TOTP secret, an empty string removes it
+    #[serde(default)]
+    pub totp: Option<String>,
+}
+
+impl VaultEntryUpdate {
+    /// Whether the update changes any encrypted field of the entry
+    fn changes(&self, value: &str, notes: Option<&str>, totp: Option<&str>) -> bool {
+        let totp_changed = match self.totp.as_deref() {
+            None => false,
+            Some("") => totp.is_some(),
+            Some(new) => totp != Some(new),
+        };
+        self.param_value != value || self.notes.as_deref() != notes || totp_changed
+    }
+}
+
+impl Drop for VaultEntryUpdate {
+    fn drop(&mut self) {
+        self.param_value.zeroize();
+        self.notes.zeroize();
+        self.totp.zeroize();
+    }
+}
+
+fn version_from_row(row: &SqliteRow) -> Result<VaultEntryVersion, String> {
+    let get_err = |e: sqlx::Error| format!("Failed to read entry version: {}", e);
+    Ok(VaultEntryVersion {
+        id: row.try_get("id").map_err(get_err)?,
+        entry_id: row.try_get("entry_id").map_err(get_err)?,
+        project_id: row.try_get("project_id").map_err(get_err)?,
+        version: row.try_get("version").map_err(get_err)?,
+        encrypted_value: row.try_get("encrypted_value").map_err(get_err)?,
+        encrypted_notes: row.try_get("encrypted_notes").map_err(get_err)?,
+        encrypted_totp: row.try_get("encrypted_totp").map_err(get_err)?,
+        salt: row.try_get("salt").map_err(get_err)?,
+        nonce: row.try_get("nonce").map_err(get_err)?,
+        archived_at: row.try_get("archived_at").map_err(get_err)?,
+    })
+}
+
+/// History rows of every entry of a project, or of one entry
+pub(crate) async fn load_history(
+    pool: &SqlitePool,
+    project_id: i64,
+    entry_id: Option<i64>,
+) -> Result<Vec<VaultEntryVersion>, String> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM vault_entry_history WHERE project_id = ? AND (? IS NULL OR entry_id = ?)",
+        HISTORY_COLUMNS
+    ))
+    .bind(project_id)
+    .bind(entry_id)
+    .bind(entry_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database query failed: {}", e))?;
+
+    rows.iter().map(version_from_row).collect()
+}
+
+/// `vault_master.history_retention` of a project; 0 disables history
+pub(crate) fn retention_from_column(value: Option<i64>) -> usize {
+    value.map_or(DEFAULT_HISTORY_RETENTION, |n| n.max(0) as usize)
+}
+
+/// Plan the history rows for an entry about to be rewritten.
+/// `previous` is the row as currently stored; it is archived only when `changed`,
+/// and versions beyond `retention` are returned for pruning.
+pub(crate) fn plan_archive(
+    previous: &VaultEntry,
+    changed: bool,
+    history: &[VaultEntryVersion],
+    retention: usize,
+) -> ArchivePlan {
+    let mut history: Vec<&VaultEntryVersion> = history
+        .iter()
+        .filter(|v| v.entry_id == previous.id)
+        .collect();
+    history.sort_by_key(|v| v.version);
+
+    let version = (changed && retention > 0).then(|| NewEntryVersion {
+        entry_id: previous.id,
+        project_id: previous.project_id,
+        version: history.last().map_or(1, |v| v.version + 1),
+        encrypted_value: previous.encrypted_value.clone(),
+        encrypted_notes: previous.encrypted_notes.clone(),
+        encrypted_totp: previous.encrypted_totp.clone(),
+        salt: previous.salt.clone(),
+        nonce: previous.nonce.clone(),
+    });
+
+    // The new version counts towards the retention limit
+    let kept = retention.saturating_sub(usize::from(version.is_some()));
+    let prune = history
+        .iter()
+        .take(history.len().saturating_sub(kept))
+        .map(|v| v.id)
+        .collect();
+
+    ArchivePlan { version, prune }
+}
+
+/// Write an `ArchivePlan`, inside the caller's transaction
+pub(crate) async fn apply_archive(
+    conn: &mut SqliteConnection,
+    plan: &ArchivePlan,
+) -> Result<(), sqlx::Error> {
+    if let Some(v) = &plan.version {
+        sqlx::query(
+            "INSERT INTO vault_entry_history \
+             (entry_id, project_id, version, encrypted_value, encrypted_notes, encrypted_totp, \
+              salt, nonce) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(v.entry_id)
+        .bind(v.project_id)
+        .bind(v.version)
+        .bind(&v.encrypted_value)
+        .bind(&v.encrypted_notes)
+        .bind(&v.encrypted_totp)
+        .bind(&v.salt)
+        .bind(&v.nonce)
+        .execute(&mut *conn)
+        .await?;
+    }
+    for id in &plan.prune {
+        sqlx::query("DELETE FROM vault_entry_history WHERE id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Re-encrypt an entry with the unlocked data key. The previous ciphertext is
+/// archived when the value, notes or TOTP secret change, and the history row, pruning and the
+/// entry update are written in one transaction.
+#[tauri::command]
+pub async fn vault_update_entry(
+    app: tauri::AppHandle,
+    session: String,
+    entry_id: i64,
+    update: VaultEntryUpdate,
+) -> Result<(), String> {
+    let pool = db::pool(&app).await?;
+    let db_err = |e: sqlx::Error| format!("Failed to update entry: {}", e);
+
+    let project_id = app.state::<VaultSessions>().project_id(&session)?;
+    let previous = find_vault_entry(&pool, project_id, entry_id).await?;
+    let history = load_history(&pool, project_id, Some(entry_id)).await?;
+    let retention: Option<i64> =
+        sqlx::query_scalar("SELECT history_retention FROM vault_master WHERE project_id = ?")
+            .bind(project_id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_err)?
+            .flatten();
+    let retention = retention_from_column(retention);
+    let expected_value = previous.encrypted_value.clone();
+
+    // Legacy entries are decrypted with Argon2; keep the crypto off the async runtime
+    let handle = app.clone();
+    let (plan, fields, encrypted_totp, update) = tauri::async_runtime::spawn_blocking(move || {
+        let sessions = handle.state::<VaultSessions>();
+        let (mut old_value, mut old_notes) = sessions.decrypt_entry(&session, &previous)?;
+        let mut old_totp = if update.totp.is_some() {
+            sessions.decrypt_totp(&session, &previous)?
+        } else {
+            None
+        };
+        let changed = update.changes(&old_value, old_notes.as_deref(), old_totp.as_deref());
+        old_value.zeroize();
+        old_notes.zeroize();
+        old_totp.zeroize();
+
+        let (_, data_key) = sessions.data_key(&session)?;
+        let fields =
+            encrypt_entry_fields_with_key(&update.param_value, update.notes.as_deref(), &data_key)?;
+        let encrypted_totp = match update.totp.as_deref() {
+            None => previous.encrypted_totp.clone(),
+            Some("") => None,
+            Some(totp) => {
+                parse_totp(totp)?;
+                Some(encrypt_versioned_with_key(totp, &data_key)?)
+            }
+        };
+
+        let plan = plan_archive(&previous, changed, &history, retention);
+        Ok::<_, String>((plan, fields, encrypted_totp, update))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let (encrypted_value, nonce, encrypted_notes, _, salt) = fields;
+
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    apply_archive(&mut tx, &plan).await.map_err(db_err)?;
+    // 只在密文未被并发修改时覆盖，否则整体回滚
+    let updated = sqlx::query(
+        "UPDATE vault_entries SET title = ?, param_key = ?, encrypted_value = ?, \
+         encrypted_notes = ?, url = ?, category = ?, salt = ?, nonce = ?, encrypted_totp = ?, \
+         updated_at = CURRENT_TIMESTAMP \
+         WHERE id = ? AND project_id = ? AND encrypted_value = ?",
+    )
+    .bind(&update.title)
+    .bind(update.param_key.as_deref().filter(|k| !k.is_empty()))
+    .bind(&encrypted_value)
+    .bind(&encrypted_notes)
+    .bind(update.url.as_deref().filter(|u| !u.is_empty()))
+    .bind(update.category.as_deref().filter(|c| !c.is_empty()).unwrap_or("general"))
+    .bind(&salt)
+    .bind(&nonce)
+    .bind(&encrypted_totp)
+    .bind(entry_id)
+    .bind(project_id)
+    .bind(&expected_value)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    if updated.rows_affected() == 0 {
+        return Err(format!("Entry {} changed while it was being updated", entry_id));
+    }
+    tx.commit().await.map_err(db_err)?;
+
+    Ok(())
+}
+
+/// Versions of an entry, newest first
+#[tauri::command]
+pub async fn vault_list_entry_versions(
+    app: tauri::AppHandle,
+    project_id: i64,
+    entry_id: i64,
+) -> Result<Vec<VaultVersionInfo>, String> {
+    let pool = db::pool(&app).await?;
+    let mut versions: Vec<VaultVersionInfo> = load_history(&pool, project_id, Some(entry_id))
+        .await?
+        .into_iter()
+        .map(|v| VaultVersionInfo {
+            id: v.id,
+            version: v.version,
+            archived_at: v.archived_at,
+            has_notes: v.encrypted_notes.is_some(),
+            has_totp: v.encrypted_totp.is_some(),
+        })
+        .collect();
+    versions.sort_by_key(|v| std::cmp::Reverse(v.version));
+    Ok(versions)
+}
+
+/// Decrypt a historical version with the master password.
+/// Versions archived by a master password rotation are still sealed with the
+/// master password that was current at the time and are opened with that one.
+#[tauri::command]
+pub async fn vault_decrypt_entry_version(
+    app: tauri::AppHandle,
+    project_id: i64,
+    version_id: i64,
+    master_password: String,
+) -> Result<(String, Option<String>), String> {
+    let pool = db::pool(&app).await?;
+    let db_err = |e: sqlx::Error| format!("Database query failed: {}", e);
+
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM vault_entry_history WHERE id = ? AND project_id = ?",
+        HISTORY_COLUMNS
+    ))
+    .bind(version_id)
+    .bind(project_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or("Version not found")?;
+    let version = version_from_row(&row)?;
+
+    let master =
+        sqlx::query("SELECT password_hash, wrapped_key FROM vault_master WHERE project_id = ?")
+            .bind(project_id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_err)?
+            .ok_or("Vault is not initialized")?;
+    let password_hash: String = master.try_get("password_hash").map_err(db_err)?;
+    let wrapped_key: Option<String> = master.try_get("wrapped_key").map_err(db_err)?;
+
+    let master_password = Zeroizing::new(master_password);
+    tauri::async_runtime::spawn_blocking(move || {
+        decrypt_version(&master_password, &password_hash, wrapped_key.as_deref(), version)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn decrypt_version(
+    master_password: &str,
+    password_hash: &str,
+    wrapped_key: Option<&str>,
+    version: VaultEntryVersion,
+) -> Result<(String, Option<String>), String> {
+    // The current master also unwraps the data key; an earlier one can only open
+    // versions it sealed itself
+    let is_current = verify_password(master_password, password_hash)?;
+    let data_key = match wrapped_key {
+        Some(wrapped) if is_current && !wrapped.is_empty() => {
+            Some(unwrap_data_key(wrapped, master_password)?)
+        }
+        _ => None,
+    };
+
+    let entry = VaultEntry {
+        id: version.entry_id,
+        project_id: version.project_id,
+        title: String::new(),
+        param_key: None,
+        encrypted_value: version.encrypted_value,
+        encrypted_notes: version.encrypted_notes,
+        url: None,
+        category: String::new(),
+        salt: version.salt,
+        nonce: version.nonce,
+        created_at: version.archived_at.clone(),
+        updated_at: version.archived_at,
+        encrypted_totp: None,
+        ssh_public_key: None,
+        ssh_fingerprint: None,
+    };
+
+    decrypt_entry_with_master(&entry, master_password, data_key.as_deref()).map_err(|e| {
+        if is_current {
+            format!(
+                "Failed to decrypt version {} of entry {}: {}",
+                version.version, version.entry_id, e
+            )
+        } else {
+            "Invalid master password".to_string()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::encryption::encrypt_data;
+    use crate::crypto::{KdfParams, generate_data_key, hash_password_with_params, wrap_data_key};
+
+    const SALT: &str = "history-entry-salt-0123456789abcdef";
+
+    fn entry(id: i64) -> VaultEntry {
+        VaultEntry {
+            id,
+            project_id: 1,
+            title: format!("entry {}", id),
+            param_key: None,
+            encrypted_value: format!("value-{}", id),
+            encrypted_notes: Some(format!("notes-{}", id)),
+            url: None,
+            category: "general".to_string(),
+            salt: SALT.to_string(),
+            nonce: "nonce".to_string(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            encrypted_totp: None,
+            ssh_public_key: None,
+            ssh_fingerprint: None,
+        }
+    }
+
+    fn version(id: i64, entry_id: i64, version: i64) -> VaultEntryVersion {
+        VaultEntryVersion {
+            id,
+            entry_id,
+            project_id: 1,
+            version,
+            encrypted_value: format!("old-{}", id),
+            encrypted_notes: None,
+            encrypted_totp: None,
+            salt: SALT.to_string(),
+            nonce: "nonce".to_string(),
+            archived_at: "2026-01-01 00:00:00".to_string(),
+        }
+    }
+
+    /// 旧版布局的历史版本：由主密码直接加密，nonce 为 `value|notes`
+    fn legacy_version(master: &str, value: &str, notes: &str) -> VaultEntryVersion {
+        let (enc_value, value_nonce) = encrypt_data(value, master, SALT).unwrap();
+        let (enc_notes, notes_nonce) = encrypt_data(notes, master, SALT).unwrap();
+        VaultEntryVersion {
+            encrypted_value: enc_value,
+            encrypted_notes: Some(enc_notes),
+            nonce: format!("{}|{}", value_nonce, notes_nonce),
+            ..version(1, 1, 1)
+        }
+    }
+
+    #[test]
+    fn test_retention_from_column() {
+        assert_eq!(retention_from_column(None), DEFAULT_HISTORY_RETENTION);
+        assert_eq!(retention_from_column(Some(3)), 3);
+        assert_eq!(retention_from_column(Some(0)), 0);
+        assert_eq!(retention_from_column(Some(-5)), 0);
+    }
+
+    #[test]
+    fn test_update_changes_cover_totp() {
+        let update = |notes: Option<&str>, totp: Option<&str>| VaultEntryUpdate {
+            title: "db".to_string(),
+            param_key: None,
+            param_value: "secret".to_string(),
+            notes: notes.map(str::to_string),
+            url: None,
+            category: None,
+            totp: totp.map(str::to_string),
+        };
+
+        let stored = Some("JBSWY3DPEHPK3PXP");
+        assert!(!update(None, None).changes("secret", None, stored));
+        assert!(update(Some("n"), None).changes("secret", None, None));
+        assert!(!update(None, stored).changes("secret", None, stored));
+        // 只改 TOTP 也要归档
+        assert!(update(None, Some("KRSXG5CTMVRXEZLU")).changes("secret", None, stored));
+        assert!(update(None, stored).changes("secret", None, None));
+        assert!(update(None, Some("")).changes("secret", None, stored));
+        assert!(!update(None, Some("")).changes("secret", None, None));
+    }
+
+    #[test]
+    fn test_plan_archive_copies_previous_ciphertext() {
+        let mut previous = entry(1);
+        previous.encrypted_totp = Some("totp".to_string());
+
+        let plan = plan_archive(&previous, true, &[], 10);
+        let v = plan.version.unwrap();
+        assert_eq!(v.version, 1);
+        assert_eq!(v.entry_id, 1);
+        assert_eq!(v.encrypted_value, "value-1");
+        assert_eq!(v.encrypted_notes.as_deref(), Some("notes-1"));
+        assert_eq!(v.encrypted_totp.as_deref(), Some("totp"));
+        assert!(plan.prune.is_empty());
+    }
+
+    #[test]
+    fn test_plan_archive_skips_unchanged_entries() {
+        let history = vec![version(10, 1, 1)];
+        let plan = plan_archive(&entry(1), false, &history, 10);
+        assert!(plan.version.is_none());
+        assert!(plan.prune.is_empty());
+    }
+
+    #[test]
+    fn test_plan_archive_numbers_after_the_entrys_latest_version() {
+        // 其他条目的历史不影响版本号
+        let history = vec![version(10, 1, 2), version(11, 1, 5), version(12, 2, 9)];
+        let plan = plan_archive(&entry(1), true, &history, 10);
+        assert_eq!(plan.version.unwrap().version, 6);
+        assert!(plan.prune.is_empty());
+    }
+
+    #[test]
+    fn test_plan_archive_prunes_oldest_beyond_retention() {
+        let history = vec![
+            version(13, 1, 4),
+            version(10, 1, 1),
+            version(12, 1, 3),
+            version(11, 1, 2),
+            version(20, 2, 1),
+        ];
+
+        // 新版本占用一个名额，保留最近 2 个旧版本
+        let plan = plan_archive(&entry(1), true, &history, 3);
+        assert_eq!(plan.version.unwrap().version, 5);
+        assert_eq!(plan.prune, vec![10, 11]);
+
+        // 未修改时只按保留数裁剪
+        let plan = plan_archive(&entry(1), false, &history, 3);
+        assert!(plan.version.is_none());
+        assert_eq!(plan.prune, vec![10]);
+    }
+
+    #[test]
+    fn test_plan_archive_with_history_disabled() {
+        let history = vec![version(10, 1, 1), version(11, 1, 2)];
+        let plan = plan_archive(&entry(1), true, &history, 0);
+        assert!(plan.version.is_none());
+        assert_eq!(plan.prune, vec![10, 11]);
+    }
+
+    #[test]
+    fn test_decrypt_version_with_current_master() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params("current", &params).unwrap();
+        let wrapped = wrap_data_key(&generate_data_key(), "current", &params).unwrap();
+
+        let v = legacy_version("current", "secret", "notes");
+        let (value, notes) = decrypt_version("current", &hash, Some(&wrapped), v).unwrap();
+        assert_eq!(value, "secret");
+        assert_eq!(notes.as_deref(), Some("notes"));
+    }
+
+    #[test]
+    fn test_decrypt_version_archived_before_rotation() {
+        let params = KdfParams::default();
+        let (hash, _) = hash_password_with_params("rotated", &params).unwrap();
+        let wrapped = wrap_data_key(&generate_data_key(), "rotated", &params).unwrap();
+
+        // 轮换前归档的版本仍由旧主密码加密
+        let v = legacy_version("previous", "old secret", "old notes");
+        let (value, notes) =
+            decrypt_version("previous", &hash, Some(&wrapped), v.clone()).unwrap();
+        assert_eq!(value, "old secret");
+        assert_eq!(notes.as_deref(), Some("old notes"));
+
+        let err = decrypt_version("wrong", &hash, Some(&wrapped), v).err().unwrap();
+        assert_eq!(err, "Invalid master password");
+    }
+}
@@ -0,0 +1,667 @@
+use crate::commands::handover::ImportedVaultEntry;
+use crate::commands::vault::{CreateVaultEntryInput, VaultSessions, encrypt_entry_fields_with_key};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// Source format of `vault_import_preview` / `vault_import_entries`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// `KEY=value` lines
+    Dotenv,
+    /// Password manager export with title/username/password/url/notes columns
+    /// (KeePass, Bitwarden, 1Password)
+    Csv,
+}
+
+/// A parsed row, before encryption
+struct ImportCandidate {
+    line: usize,
+    input: CreateVaultEntryInput,
+}
+
+impl Drop for ImportCandidate {
+    fn drop(&mut self) {
+        self.input.param_value.zeroize();
+        self.input.notes.zeroize();
+    }
+}
+
+/// A row that could not be imported
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportIssue {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Dry-run view of one row; secret values are never included
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreviewEntry {
+    pub line: usize,
+    pub title: String,
+    pub param_key: Option<String>,
+    pub url: Option<String>,
+    pub category: String,
+    pub has_notes: bool,
+    /// `param_key` already exists in the vault or earlier in the same file
+    pub duplicate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub entries: Vec<ImportPreviewEntry>,
+    /// Distinct duplicate `param_key`s
+    pub duplicates: Vec<String>,
+    pub issues: Vec<ImportIssue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportEntriesResult {
+    /// Encrypted rows for `vault_entries`, inserted by the frontend
+    pub entries: Vec<ImportedVaultEntry>,
+    pub skipped: Vec<ImportIssue>,
+}
+
+fn detect_format(path: &Path, format: Option<ImportFormat>) -> ImportFormat {
+    format.unwrap_or_else(|| {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()) {
+            Some(ext) if ext == "csv" => ImportFormat::Csv,
+            _ => ImportFormat::Dotenv,
+        }
+    })
+}
+
+/// Strip a UTF-8 BOM, which Windows exports usually start with
+fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Environment variable name derived from an entry title: `GitHub token` -> `GITHUB_TOKEN`
+fn env_key_from_title(title: &str) -> Option<String> {
+    let mut key = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            key.push(c.to_ascii_uppercase());
+        } else if !key.is_empty() && !key.ends_with('_') {
+            key.push('_');
+        }
+    }
+    let key = key.trim_end_matches('_');
+    match key.chars().next() {
+        None => None,
+        Some(c) if c.is_ascii_digit() => Some(format!("_{}", key)),
+        Some(_) => Some(key.to_string()),
+    }
+}
+
+// ============================================================================
+// .env
+// ============================================================================
+
+/// Parse a `.env` file: `KEY=value`, optional `export ` prefix, `#` comments,
+/// single quotes (literal), double quotes (with `\n`, `\"`, `\\`, `\$` escapes,
+/// may span lines) and unquoted values with trailing ` # comment`.
+fn parse_dotenv(
+    content: &str,
+    project_id: i64,
+    category: &str,
+) -> (Vec<ImportCandidate>, Vec<ImportIssue>) {
+    let mut candidates = Vec::new();
+    let mut issues = Vec::new();
+    let lines: Vec<&str> = strip_bom(content).lines().collect();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line_no = i + 1;
+        // 只去掉行首空白：跨行引号值的第一行需要原样保留（PEM、JSON 等）
+        let line = lines[i].trim_start();
+        i += 1;
+
+        if line.trim_end().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let Some((key, raw_value)) = line.split_once('=') else {
+            issues.push(ImportIssue {
+                line: line_no,
+                message: "Missing '='".to_string(),
+            });
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            issues.push(ImportIssue {
+                line: line_no,
+                message: "Missing variable name".to_string(),
+            });
+            continue;
+        }
+
+        let raw_value = raw_value.trim_start();
+        let value = if let Some(rest) = raw_value.strip_prefix('"') {
+            // 双引号值可以跨行，读到未转义的结束引号为止
+            let mut buf = rest.to_string();
+            loop {
+                if let Some(value) = parse_double_quoted(&buf) {
+                    break Some(value);
+                }
+                if i >= lines.len() {
+                    break None;
+                }
+                buf.push('\n');
+                buf.push_str(lines[i]);
+                i += 1;
+            }
+        } else if let Some(rest) = raw_value.strip_prefix('\'') {
+            rest.find('\'').map(|end| rest[..end].to_string())
+        } else {
+            let value = match raw_value.find(" #") {
+                Some(pos) => &raw_value[..pos],
+                None => raw_value,
+            };
+            Some(value.trim_end().to_string())
+        };
+
+        let Some(value) = value else {
+            issues.push(ImportIssue {
+                line: line_no,
+                message: "Unterminated quoted value".to_string(),
+            });
+            continue;
+        };
+
+        candidates.push(ImportCandidate {
+            line: line_no,
+            input: CreateVaultEntryInput {
+                project_id,
+                title: key.to_string(),
+                param_key: Some(key.to_string()),
+                param_value: value,
+                notes: None,
+                url: None,
+                category: Some(category.to_string()),
+            },
+        });
+    }
+
+    (candidates, issues)
+}
+
+/// Unescape the body of a double-quoted value (after the opening quote).
+/// Returns `None` when the closing quote has not been reached yet.
+fn parse_double_quoted(body: &str) -> Option<String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                other => out.push(other),
+            },
+            _ => out.push(c),
+        }
+    }
+    None
+}
+
+// ============================================================================
+// CSV
+// ============================================================================
+
+/// Parse RFC 4180 CSV: quoted fields may contain commas, newlines and `""`.
+/// Returns each record with the line number it starts on.
+fn parse_csv_records(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = strip_bom(content).chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quoted field starting at line {}", record_line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    // 跳过空行
+    records.retain(|(_, r)| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(records)
+}
+
+/// Column positions resolved from the header row
+struct CsvColumns {
+    title: Option<usize>,
+    username: Option<usize>,
+    password: usize,
+    url: Option<usize>,
+    notes: Option<usize>,
+    /// Bitwarden `type` column: only `login` rows carry a password
+    kind: Option<usize>,
+}
+
+impl CsvColumns {
+    fn from_header(header: &[String]) -> Result<Self, String> {
+        let find = |names: &[&str]| {
+            header.iter().position(|h| {
+                let h = h.trim().to_lowercase();
+                names.contains(&h.as_str())
+            })
+        };
+
+        Ok(CsvColumns {
+            // KeePass 1.x 用 Account / Login Name / Web Site / Comments
+            title: find(&["title", "name", "account"]),
+            username: find(&["username", "login_username", "login name", "user name", "user"]),
+            password: find(&["password", "login_password"]).ok_or("CSV has no password column")?,
+            url: find(&["url", "login_uri", "web site", "website"]),
+            notes: find(&["notes", "note", "comments"]),
+            kind: find(&["type"]),
+        })
+    }
+}
+
+fn parse_csv(
+    content: &str,
+    project_id: i64,
+    category: &str,
+) -> Result<(Vec<ImportCandidate>, Vec<ImportIssue>), String> {
+    let mut records = parse_csv_records(content)?.into_iter();
+    let (_, header) = records.next().ok_or("CSV file is empty")?;
+    let columns = CsvColumns::from_header(&header)?;
+
+    let mut candidates = Vec::new();
+    let mut issues = Vec::new();
+
+    for (line, mut record) in records {
+        let get = |idx: Option<usize>| idx.and_then(|i| record.get(i)).map(String::as_str);
+
+        if get(columns.kind).is_some_and(|k| !k.trim().is_empty() && k.trim() != "login") {
+            issues.push(ImportIssue {
+                line,
+                message: "Not a login item".to_string(),
+            });
+            continue;
+        }
+
+        let password = get(Some(columns.password)).unwrap_or("");
+        if password.is_empty() {
+            issues.push(ImportIssue {
+                line,
+                message: "Empty password".to_string(),
+            });
+            continue;
+        }
+
+        let username = get(columns.username).and_then(non_empty);
+        let title = get(columns.title)
+            .and_then(non_empty)
+            .or_else(|| username.clone())
+            .unwrap_or_else(|| format!("Imported entry {}", line));
+
+        // param_key 是导出时的环境变量名，由标题生成；用户名没有单独的列，放在备注开头
+        let notes = get(columns.notes).filter(|n| !n.is_empty());
+        let notes = match (&username, notes) {
+            (Some(user), Some(notes)) => Some(format!("Username: {}\n\n{}", user, notes)),
+            (Some(user), None) => Some(format!("Username: {}", user)),
+            (None, notes) => notes.map(str::to_string),
+        };
+
+        candidates.push(ImportCandidate {
+            line,
+            input: CreateVaultEntryInput {
+                project_id,
+                param_key: env_key_from_title(&title),
+                title,
+                param_value: password.to_string(),
+                notes,
+                url: get(columns.url).and_then(non_empty),
+                category: Some(category.to_string()),
+            },
+        });
+
+        record.iter_mut().for_each(Zeroize::zeroize);
+    }
+
+    Ok((candidates, issues))
+}
+
+fn parse_source(
+    source_path: &str,
+    format: Option<ImportFormat>,
+    project_id: i64,
+    category: Option<&str>,
+) -> Result<(Vec<ImportCandidate>, Vec<ImportIssue>), String> {
+    let path = Path::new(source_path);
+    let mut content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let category = category.filter(|c| !c.is_empty()).unwrap_or("general");
+
+    let parsed = match detect_format(path, format) {
+        ImportFormat::Dotenv => Ok(parse_dotenv(&content, project_id, category)),
+        ImportFormat::Csv => parse_csv(&content, project_id, category),
+    };
+    content.zeroize();
+    parsed
+}
+
+/// Mark candidates whose `param_key` is already in the vault or appeared earlier in the file
+fn find_duplicates(candidates: &[ImportCandidate], existing_keys: &[String]) -> Vec<bool> {
+    let mut seen: HashSet<&str> = existing_keys.iter().map(String::as_str).collect();
+    candidates
+        .iter()
+        .map(|c| match c.input.param_key.as_deref() {
+            Some(key) => !seen.insert(key),
+            None => false,
+        })
+        .collect()
+}
+
+/// Parse a `.env` or CSV file without encrypting anything and report what would
+/// be imported, including `param_key`s that clash with `existing_keys`.
+/// `format` is detected from the file extension when omitted.
+#[tauri::command]
+pub fn vault_import_preview(
+    source_path: String,
+    format: Option<ImportFormat>,
+    project_id: i64,
+    category: Option<String>,
+    existing_keys: Vec<String>,
+) -> Result<ImportPreview, String> {
+    let (candidates, issues) =
+        parse_source(&source_path, format, project_id, category.as_deref())?;
+    let duplicate_flags = find_duplicates(&candidates, &existing_keys);
+
+    let mut duplicates = Vec::new();
+    let entries = candidates
+        .iter()
+        .zip(duplicate_flags)
+        .map(|(c, duplicate)| {
+            if let Some(key) = c
+                .input
+                .param_key
+                .as_ref()
+                .filter(|k| duplicate && !duplicates.contains(*k))
+            {
+                duplicates.push(key.clone());
+            }
+            ImportPreviewEntry {
+                line: c.line,
+                title: c.input.title.clone(),
+                param_key: c.input.param_key.clone(),
+                url: c.input.url.clone(),
+                category: c.input.category.clone().unwrap_or_default(),
+                has_notes: c.input.notes.is_some(),
+                duplicate,
+            }
+        })
+        .collect();
+
+    Ok(ImportPreview {
+        entries,
+        duplicates,
+        issues,
+    })
+}
+
+/// Parse a `.env` or CSV file and encrypt every row with the unlocked project data key,
+/// returning rows in the same shape as `vault_session_encrypt_entry` for the frontend
+/// to insert. With `skip_duplicates` rows whose `param_key` clashes are left out.
+#[tauri::command]
+pub fn vault_import_entries(
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    source_path: String,
+    format: Option<ImportFormat>,
+    category: Option<String>,
+    existing_keys: Vec<String>,
+    skip_duplicates: bool,
+) -> Result<ImportEntriesResult, String> {
+    let (project_id, data_key) = sessions.data_key(&session)?;
+    let (candidates, mut skipped) =
+        parse_source(&source_path, format, project_id, category.as_deref())?;
+    let duplicate_flags = find_duplicates(&candidates, &existing_keys);
+
+    let mut entries = Vec::with_capacity(candidates.len());
+    for (candidate, duplicate) in candidates.iter().zip(duplicate_flags) {
+        if duplicate && skip_duplicates {
+            skipped.push(ImportIssue {
+                line: candidate.line,
+                message: format!(
+                    "Key {} already exists",
+                    candidate.input.param_key.as_deref().unwrap_or_default()
+                ),
+            });
+            continue;
+        }
+
+        let input = &candidate.input;
+        let (encrypted_value, nonce, encrypted_notes, _, salt) =
+            encrypt_entry_fields_with_key(&input.param_value, input.notes.as_deref(), &data_key)
+                .map_err(|e| format!("Failed to encrypt line {}: {}", candidate.line, e))?;
+
+        entries.push(ImportedVaultEntry {
+            title: input.title.clone(),
+            param_key: input.param_key.clone(),
+            encrypted_value,
+            encrypted_notes,
+            url: input.url.clone(),
+            category: input.category.clone().unwrap_or_else(|| "general".to_string()),
+            salt,
+            nonce,
+        });
+    }
+
+    Ok(ImportEntriesResult { entries, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dotenv(content: &str) -> (Vec<(String, String)>, Vec<usize>) {
+        let (candidates, issues) = parse_dotenv(content, 1, "general");
+        let vars = candidates
+            .iter()
+            .map(|c| (c.input.param_key.clone().unwrap(), c.input.param_value.clone()))
+            .collect();
+        (vars, issues.iter().map(|i| i.line).collect())
+    }
+
+    #[test]
+    fn test_parse_dotenv_values() {
+        let cases: &[(&str, &str, &str)] = &[
+            ("KEY=value", "KEY", "value"),
+            ("  KEY = value  ", "KEY", "value"),
+            ("export KEY=value", "KEY", "value"),
+            ("export   KEY=value", "KEY", "value"),
+            ("KEY=", "KEY", ""),
+            ("KEY=value # comment", "KEY", "value"),
+            ("KEY=value#not-a-comment", "KEY", "value#not-a-comment"),
+            ("KEY=a=b=c", "KEY", "a=b=c"),
+            ("KEY='single $x \\n # kept'", "KEY", "single $x \\n # kept"),
+            ("KEY=\"double # kept\"", "KEY", "double # kept"),
+            ("KEY=\"say \\\"hi\\\"\"", "KEY", "say \"hi\""),
+            ("KEY=\"a\\nb\\tc\\\\d\\$e\"", "KEY", "a\nb\tc\\d$e"),
+            ("KEY=\"a,b,'c'\"", "KEY", "a,b,'c'"),
+            ("\u{feff}KEY=bom", "KEY", "bom"),
+        ];
+        for (content, key, value) in cases {
+            let (vars, issues) = dotenv(content);
+            assert!(issues.is_empty(), "{:?}", content);
+            assert_eq!(vars, vec![(key.to_string(), value.to_string())], "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_parse_dotenv_comments_and_crlf() {
+        let content = "# header\r\n\r\nA=1\r\n  # indented comment\r\nexport B=\"two\"\r\nC='3'\r\n";
+        let (vars, issues) = dotenv(content);
+        assert!(issues.is_empty());
+        assert_eq!(
+            vars,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "two".to_string()),
+                ("C".to_string(), "3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_multiline_values_are_verbatim() {
+        let content = "KEY=\"-----BEGIN KEY-----  \n  line two\n-----END KEY-----\"\nJSON=\"{\n  \\\"a\\\": 1\n}\"\nNEXT=x\n";
+        let (vars, issues) = dotenv(content);
+        assert!(issues.is_empty());
+        assert_eq!(vars[0].1, "-----BEGIN KEY-----  \n  line two\n-----END KEY-----");
+        assert_eq!(vars[1].1, "{\n  \"a\": 1\n}");
+        assert_eq!(vars[2], ("NEXT".to_string(), "x".to_string()));
+
+        // CRLF 文件中的跨行值以 \n 连接
+        let (vars, _) = dotenv("KEY=\"a\r\nb\"\r\n");
+        assert_eq!(vars[0].1, "a\nb");
+    }
+
+    #[test]
+    fn test_parse_dotenv_issues() {
+        let (vars, issues) = dotenv("NO_EQUALS\n=value\nOK=1\nOPEN=\"never closed\nMORE=2\n");
+        assert_eq!(vars, vec![("OK".to_string(), "1".to_string())]);
+        assert_eq!(issues, vec![1, 2, 4]);
+
+        // 行号指向值开始的那一行
+        let (candidates, _) = parse_dotenv("# c\n\nA=\"x\ny\"\nB=2\n", 1, "general");
+        let lines: Vec<usize> = candidates.iter().map(|c| c.line).collect();
+        assert_eq!(lines, vec![3, 5]);
+    }
+
+    #[test]
+    fn test_parse_double_quoted() {
+        assert_eq!(parse_double_quoted("abc\" trailing").as_deref(), Some("abc"));
+        assert_eq!(parse_double_quoted("a\\\"b\"").as_deref(), Some("a\"b"));
+        assert_eq!(parse_double_quoted("a\\\\\"").as_deref(), Some("a\\"));
+        assert_eq!(parse_double_quoted("unterminated"), None);
+        assert_eq!(parse_double_quoted("escaped quote\\\""), None);
+        assert_eq!(parse_double_quoted("dangling\\"), None);
+    }
+
+    #[test]
+    fn test_parse_csv_records() {
+        let content = "title,password,notes\r\nplain,p1,\r\n\"with, comma\",\"p\"\"2\",\"multi\nline\"\n\nlast,p3,n";
+        let records = parse_csv_records(content).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["title".into(), "password".into(), "notes".into()]),
+                (2, vec!["plain".into(), "p1".into(), "".into()]),
+                (3, vec!["with, comma".into(), "p\"2".into(), "multi\nline".into()]),
+                (6, vec!["last".into(), "p3".into(), "n".into()]),
+            ]
+        );
+
+        assert!(parse_csv_records("a,\"open\nb").is_err());
+    }
+
+    fn csv(content: &str) -> Vec<CreateVaultEntryInput> {
+        let (candidates, _) = parse_csv(content, 1, "general").unwrap();
+        candidates
+            .iter()
+            .map(|c| CreateVaultEntryInput {
+                project_id: c.input.project_id,
+                title: c.input.title.clone(),
+                param_key: c.input.param_key.clone(),
+                param_value: c.input.param_value.clone(),
+                notes: c.input.notes.clone(),
+                url: c.input.url.clone(),
+                category: c.input.category.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_csv_header_variants() {
+        // KeePass 1.x、KeePass 2.x、Bitwarden、1Password
+        let exports = [
+            "\"Account\",\"Login Name\",\"Password\",\"Web Site\",\"Comments\"\n\"GitHub token\",\"octo\",\"s3cret\",\"https://github.com\",\"ci\"\n",
+            "Title,Username,Password,URL,Notes\nGitHub token,octo,s3cret,https://github.com,ci\n",
+            "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n,,login,GitHub token,ci,,0,https://github.com,octo,s3cret,\n,,note,Secure note,text,,0,,,,\n",
+            "\u{feff}Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes\nGitHub token,https://github.com,octo,s3cret,,false,false,,ci\n",
+        ];
+        for content in exports {
+            let entries = csv(content);
+            assert_eq!(entries.len(), 1, "{}", content);
+            let entry = &entries[0];
+            assert_eq!(entry.title, "GitHub token");
+            assert_eq!(entry.param_key.as_deref(), Some("GITHUB_TOKEN"));
+            assert_eq!(entry.param_value, "s3cret");
+            assert_eq!(entry.url.as_deref(), Some("https://github.com"));
+            assert_eq!(entry.notes.as_deref(), Some("Username: octo\n\nci"));
+        }
+    }
+
+    #[test]
+    fn test_parse_csv_rows() {
+        let content = "name,username,password\n,octo,p1\nEmpty,,\n,,p3\n";
+        let (candidates, issues) = parse_csv(content, 1, "general").unwrap();
+        let titles: Vec<_> = candidates.iter().map(|c| c.input.title.as_str()).collect();
+        assert_eq!(titles, vec!["octo", "Imported entry 4"]);
+        assert_eq!(candidates[0].input.notes.as_deref(), Some("Username: octo"));
+        assert_eq!(candidates[1].input.notes, None);
+        assert_eq!(issues.iter().map(|i| i.line).collect::<Vec<_>>(), vec![3]);
+
+        assert!(parse_csv("name,username\nx,y\n", 1, "general").is_err());
+    }
+
+    #[test]
+    fn test_env_key_from_title() {
+        assert_eq!(env_key_from_title("GitHub token").as_deref(), Some("GITHUB_TOKEN"));
+        assert_eq!(env_key_from_title("  db--password (prod) ").as_deref(), Some("DB_PASSWORD_PROD"));
+        assert_eq!(env_key_from_title("1password").as_deref(), Some("_1PASSWORD"));
+        assert_eq!(env_key_from_title("数据库"), None);
+    }
+}
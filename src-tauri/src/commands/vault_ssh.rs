@@ -0,0 +1,152 @@
+use crate::commands::vault::{VaultEntry, VaultSessions, encrypt_entry_fields_with_key};
+use crate::commands::vault_attachments::{create_new_private_file, create_private_file};
+use crate::crypto::ssh::{SshKeyAlgorithm, generate_ssh_key, parse_private_key};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A generated key pair ready to be stored as an `ssh` vault entry.
+/// The private key is sealed like `encrypted_value` of any other entry; the public
+/// key and fingerprint go into plain columns so they can be shown while locked.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedSshKey {
+    pub algorithm: SshKeyAlgorithm,
+    pub encrypted_value: String,
+    pub encrypted_notes: Option<String>,
+    pub nonce: String,
+    pub salt: String,
+    pub public_key: String,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SshKeyFiles {
+    pub private_key_path: String,
+    pub public_key_path: String,
+}
+
+/// Generate an Ed25519 or RSA key pair in OpenSSH format and encrypt the private
+/// key with the unlocked project data key
+#[tauri::command]
+pub async fn vault_generate_ssh_key(
+    sessions: tauri::State<'_, VaultSessions>,
+    session: String,
+    algorithm: SshKeyAlgorithm,
+    comment: Option<String>,
+    rsa_bits: Option<usize>,
+    notes: Option<String>,
+) -> Result<GeneratedSshKey, String> {
+    let (_, data_key) = sessions.data_key(&session)?;
+
+    // RSA 4096 takes a few seconds; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let pair = generate_ssh_key(algorithm, comment.as_deref().unwrap_or(""), rsa_bits)?;
+        let (encrypted_value, nonce, encrypted_notes, _, salt) =
+            encrypt_entry_fields_with_key(&pair.private_key, notes.as_deref(), &data_key)?;
+
+        Ok(GeneratedSshKey {
+            algorithm,
+            encrypted_value,
+            encrypted_notes,
+            nonce,
+            salt,
+            public_key: pair.public_key,
+            fingerprint: pair.fingerprint,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Decrypt the private key of an `ssh` entry and write it to `path` with 0600
+/// permissions, plus the public key to `<path>.pub`
+#[tauri::command]
+pub async fn vault_write_ssh_key(
+    app: tauri::AppHandle,
+    session: String,
+    entry: VaultEntry,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<SshKeyFiles, String> {
+    // Legacy entries derive a key per field, and RSA keys take a moment to parse
+    tauri::async_runtime::spawn_blocking(move || {
+        write_ssh_key(
+            &app.state::<VaultSessions>(),
+            &session,
+            &entry,
+            PathBuf::from(path),
+            overwrite.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn write_ssh_key(
+    sessions: &VaultSessions,
+    session: &str,
+    entry: &VaultEntry,
+    private_path: PathBuf,
+    overwrite: bool,
+) -> Result<SshKeyFiles, String> {
+    let mut public_path = private_path.clone().into_os_string();
+    public_path.push(".pub");
+    let public_path = PathBuf::from(public_path);
+
+    let (value, mut notes) = sessions.decrypt_entry(session, entry)?;
+    notes.zeroize();
+    let value = Zeroizing::new(value);
+    // Re-encode so only a well-formed OpenSSH key ever reaches the disk
+    let pair = parse_private_key(&value)?;
+
+    if let Some(parent) = private_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    // Without `overwrite` both files are created exclusively, so a file appearing
+    // in the meantime is never replaced
+    let mut private_file = if overwrite {
+        create_private_file(&private_path)?
+    } else {
+        create_new_private_file(&private_path)?
+    };
+    let mut public_file = match create_public_file(&public_path, overwrite) {
+        Ok(file) => file,
+        Err(e) => {
+            if !overwrite {
+                drop(private_file);
+                let _ = fs::remove_file(&private_path);
+            }
+            return Err(e);
+        }
+    };
+
+    private_file
+        .write_all(pair.private_key.as_bytes())
+        .map_err(|e| format!("Failed to write private key: {}", e))?;
+    public_file
+        .write_all(format!("{}\n", pair.public_key).as_bytes())
+        .map_err(|e| format!("Failed to write public key: {}", e))?;
+
+    Ok(SshKeyFiles {
+        private_key_path: private_path.to_string_lossy().to_string(),
+        public_key_path: public_path.to_string_lossy().to_string(),
+    })
+}
+
+fn create_public_file(path: &Path, overwrite: bool) -> Result<fs::File, String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options.open(path).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => format!("File already exists: {}", path.display()),
+        _ => format!("Failed to write public key: {}", e),
+    })
+}
@@ -3,25 +3,39 @@ use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
 };
 use argon2::{
-    Argon2,
+    Argon2, Params,
     password_hash::{
         PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::RngCore,
     },
 };
 use base64::{Engine as _, engine::general_purpose};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
-/// Hash a password using Argon2id
+use super::envelope::{Envelope, KdfParams, KeySpec, derive_key_with_params, is_envelope};
+
+/// Length of a project data key in bytes (AES-256)
+pub const DATA_KEY_LEN: usize = 32;
+
+/// Hash a password using Argon2id with default costs
+#[cfg(test)]
 pub fn hash_password(password: &str) -> Result<(String, String), String> {
+    hash_password_with_params(password, &KdfParams::default())
+}
+
+/// Hash a password using Argon2id with the given cost parameters
+pub fn hash_password_with_params(
+    password: &str,
+    params: &KdfParams,
+) -> Result<(String, String), String> {
     let mut password_bytes = password.as_bytes().to_vec();
 
     // Generate a random salt
     let salt = SaltString::generate(&mut OsRng);
 
-    // Argon2 with default params (Argon2id)
-    let argon2 = Argon2::default();
+    // Argon2id with the requested costs
+    let argon2 = params.argon2()?;
 
-    // Hash password to PHC string ($argon2id$v=19$...)
+    // Hash password to PHC string ($argon2id$v=19$m=..,t=..,p=..$...)
     let password_hash = argon2
         .hash_password(&password_bytes, &salt)
         .map_err(|e| format!("Failed to hash password: {}", e))?
@@ -33,6 +47,20 @@ pub fn hash_password(password: &str) -> Result<(String, String), String> {
     Ok((password_hash, salt.to_string()))
 }
 
+/// Read the Argon2 cost parameters encoded in a PHC password hash
+pub fn password_hash_params(password_hash: &str) -> Result<KdfParams, String> {
+    let parsed_hash = PasswordHash::new(password_hash)
+        .map_err(|e| format!("Failed to parse password hash: {}", e))?;
+    let params = Params::try_from(&parsed_hash)
+        .map_err(|e| format!("Failed to read password hash params: {}", e))?;
+
+    Ok(KdfParams {
+        m_cost: params.m_cost(),
+        t_cost: params.t_cost(),
+        p_cost: params.p_cost(),
+    })
+}
+
 /// Verify a password against a hash
 pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, String> {
     let mut password_bytes = password.as_bytes().to_vec();
@@ -40,6 +68,7 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, Stri
     let parsed_hash = PasswordHash::new(password_hash)
         .map_err(|e| format!("Failed to parse password hash: {}", e))?;
 
+    // Costs are taken from the hash itself, so any params verify
     let argon2 = Argon2::default();
     let result = argon2
         .verify_password(&password_bytes, &parsed_hash)
@@ -51,39 +80,181 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, Stri
     Ok(result)
 }
 
-/// Derive an encryption key from a password and salt using Argon2
-fn derive_key(password: &str, salt: &str) -> Result<[u8; 32], String> {
-    let mut password_bytes = password.as_bytes().to_vec();
-    let salt_bytes = salt.as_bytes();
-
-    let argon2 = Argon2::default();
-
-    let mut key = [0u8; 32];
-    argon2
-        .hash_password_into(&password_bytes, salt_bytes, &mut key)
-        .map_err(|e| format!("Failed to derive key: {}", e))?;
-
-    // Zeroize password from memory
-    password_bytes.zeroize();
-
-    Ok(key)
+/// Derive an encryption key from a password and salt using Argon2 default costs,
+/// as used by all legacy ciphertexts
+pub(crate) fn derive_key(password: &str, salt: &str) -> Result<[u8; 32], String> {
+    derive_key_with_params(password, salt, &KdfParams::default())
 }
 
 /// Encrypt data using AES-256-GCM
 /// Returns (encrypted_data_base64, nonce_base64)
+///
+/// Legacy layout: new data is sealed in envelopes, this only builds test fixtures
+#[cfg(test)]
 pub fn encrypt_data(
     plaintext: &str,
     password: &str,
     salt: &str,
 ) -> Result<(String, String), String> {
-    let mut plaintext_bytes = plaintext.as_bytes().to_vec();
+    // Derive key from password
+    let key = Zeroizing::new(derive_key(password, salt)?);
+
+    encrypt_with_key(plaintext, &key)
+}
+
+/// Decrypt data using AES-256-GCM.
+/// Versioned envelopes carry their own salt and nonce; anything else is the
+/// legacy layout with an external nonce and salt and default Argon2 costs.
+pub fn decrypt_data(
+    encrypted_b64: &str,
+    nonce_b64: &str,
+    password: &str,
+    salt: &str,
+) -> Result<String, String> {
+    if is_envelope(encrypted_b64) {
+        return decrypt_versioned(encrypted_b64, password);
+    }
 
     // Derive key from password
-    let key = derive_key(password, salt)?;
+    let key = Zeroizing::new(derive_key(password, salt)?);
+
+    decrypt_with_key(encrypted_b64, nonce_b64, &key)
+}
+
+/// Encrypt data using AES-256-GCM with an already derived key
+/// Returns (encrypted_data_base64, nonce_base64)
+#[cfg(test)]
+pub fn encrypt_with_key(
+    plaintext: &str,
+    key: &[u8; DATA_KEY_LEN],
+) -> Result<(String, String), String> {
+    let (ciphertext, nonce_bytes) = seal(plaintext.as_bytes(), key)?;
+
+    // Encode to base64
+    let encrypted_b64 = general_purpose::STANDARD.encode(&ciphertext);
+    let nonce_b64 = general_purpose::STANDARD.encode(nonce_bytes);
+
+    Ok((encrypted_b64, nonce_b64))
+}
+
+/// Decrypt data using AES-256-GCM with an already derived key
+pub fn decrypt_with_key(
+    encrypted_b64: &str,
+    nonce_b64: &str,
+    key: &[u8; DATA_KEY_LEN],
+) -> Result<String, String> {
+    if is_envelope(encrypted_b64) {
+        return decrypt_versioned_with_key(encrypted_b64, key);
+    }
+
+    into_utf8(open(encrypted_b64, nonce_b64, key)?)
+}
+
+/// Encrypt data into a versioned envelope keyed by the password, a fresh salt and `params`
+pub fn encrypt_versioned(
+    plaintext: &str,
+    password: &str,
+    params: &KdfParams,
+) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng).as_str().to_string();
+    let key = Zeroizing::new(derive_key_with_params(password, &salt, params)?);
+
+    let spec = KeySpec::Argon2id {
+        params: *params,
+        salt,
+    };
+    Ok(Envelope::seal(plaintext.as_bytes(), &key, spec)?.encode())
+}
+
+/// Encrypt data into a versioned envelope keyed by the project data key
+pub fn encrypt_versioned_with_key(
+    plaintext: &str,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<String, String> {
+    Ok(Envelope::seal(plaintext.as_bytes(), data_key, KeySpec::DataKey)?.encode())
+}
+
+/// Decrypt a password-based versioned envelope
+pub fn decrypt_versioned(envelope: &str, password: &str) -> Result<String, String> {
+    let envelope = Envelope::parse(envelope)?;
+    let key = Zeroizing::new(envelope.derive_key(password)?);
+
+    into_utf8(envelope.open(&key)?)
+}
+
+/// Decrypt a versioned envelope keyed by the project data key
+pub fn decrypt_versioned_with_key(
+    envelope: &str,
+    data_key: &[u8; DATA_KEY_LEN],
+) -> Result<String, String> {
+    let envelope = Envelope::parse(envelope)?;
+    if envelope.key != KeySpec::DataKey {
+        return Err("Envelope is not encrypted with the project data key".to_string());
+    }
+
+    into_utf8(envelope.open(data_key)?)
+}
+
+fn into_utf8(plaintext_bytes: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(plaintext_bytes).map_err(|e| format!("Failed to convert to UTF-8: {}", e))
+}
+
+/// Generate a random data key for a project vault
+pub fn generate_data_key() -> Zeroizing<[u8; DATA_KEY_LEN]> {
+    let mut key = Zeroizing::new([0u8; DATA_KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
 
+/// Wrap a data key with a key derived from the master password and `params`.
+/// Returns a versioned envelope (see `Envelope`)
+pub fn wrap_data_key(
+    data_key: &[u8; DATA_KEY_LEN],
+    password: &str,
+    params: &KdfParams,
+) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng).as_str().to_string();
+    let kek = Zeroizing::new(derive_key_with_params(password, &salt, params)?);
+
+    let spec = KeySpec::Argon2id {
+        params: *params,
+        salt,
+    };
+    Ok(Envelope::seal(data_key, &kek, spec)?.encode())
+}
+
+/// Argon2 costs a wrapped data key was sealed with; `None` for anything else
+pub fn wrapped_key_params(wrapped: &str) -> Option<KdfParams> {
+    match Envelope::parse(wrapped).ok()?.key {
+        KeySpec::Argon2id { params, .. } => Some(params),
+        KeySpec::DataKey => None,
+    }
+}
+
+/// Unwrap a data key produced by `wrap_data_key`
+pub fn unwrap_data_key(
+    wrapped: &str,
+    password: &str,
+) -> Result<Zeroizing<[u8; DATA_KEY_LEN]>, String> {
+    let envelope = Envelope::parse(wrapped)?;
+    let kek = Zeroizing::new(envelope.derive_key(password)?);
+    let plaintext = Zeroizing::new(envelope.open(&kek)?);
+
+    if plaintext.len() != DATA_KEY_LEN {
+        return Err("Invalid data key length".to_string());
+    }
+
+    let mut key = Zeroizing::new([0u8; DATA_KEY_LEN]);
+    key.copy_from_slice(&plaintext);
+    Ok(key)
+}
+
+/// AES-256-GCM encrypt raw bytes under a fresh random nonce
+#[cfg(test)]
+fn seal(plaintext: &[u8], key: &[u8; DATA_KEY_LEN]) -> Result<(Vec<u8>, [u8; 12]), String> {
     // Create cipher
     let cipher =
-        Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to create cipher: {}", e))?;
+        Aes256Gcm::new_from_slice(key).map_err(|e| format!("Failed to create cipher: {}", e))?;
 
     // Generate a random nonce
     let mut nonce_bytes = [0u8; 12];
@@ -92,26 +263,14 @@ pub fn encrypt_data(
 
     // Encrypt
     let ciphertext = cipher
-        .encrypt(nonce, plaintext_bytes.as_ref())
+        .encrypt(nonce, plaintext)
         .map_err(|e| format!("Encryption failed: {}", e))?;
 
-    // Zeroize sensitive data
-    plaintext_bytes.zeroize();
-
-    // Encode to base64
-    let encrypted_b64 = general_purpose::STANDARD.encode(&ciphertext);
-    let nonce_b64 = general_purpose::STANDARD.encode(&nonce_bytes);
-
-    Ok((encrypted_b64, nonce_b64))
+    Ok((ciphertext, nonce_bytes))
 }
 
-/// Decrypt data using AES-256-GCM
-pub fn decrypt_data(
-    encrypted_b64: &str,
-    nonce_b64: &str,
-    password: &str,
-    salt: &str,
-) -> Result<String, String> {
+/// AES-256-GCM decrypt base64 ciphertext and nonce into raw bytes
+fn open(encrypted_b64: &str, nonce_b64: &str, key: &[u8; DATA_KEY_LEN]) -> Result<Vec<u8>, String> {
     // Decode from base64
     let ciphertext = general_purpose::STANDARD
         .decode(encrypted_b64)
@@ -121,25 +280,19 @@ pub fn decrypt_data(
         .decode(nonce_b64)
         .map_err(|e| format!("Failed to decode nonce: {}", e))?;
 
+    if nonce_bytes.len() != 12 {
+        return Err("Invalid nonce length".to_string());
+    }
     let nonce = Nonce::from_slice(&nonce_bytes);
 
-    // Derive key from password
-    let key = derive_key(password, salt)?;
-
     // Create cipher
     let cipher =
-        Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to create cipher: {}", e))?;
+        Aes256Gcm::new_from_slice(key).map_err(|e| format!("Failed to create cipher: {}", e))?;
 
     // Decrypt
-    let plaintext_bytes = cipher
+    cipher
         .decrypt(nonce, ciphertext.as_ref())
-        .map_err(|e| format!("Decryption failed: {}", e))?;
-
-    // Convert to string
-    let plaintext = String::from_utf8(plaintext_bytes)
-        .map_err(|e| format!("Failed to convert to UTF-8: {}", e))?;
-
-    Ok(plaintext)
+        .map_err(|e| format!("Decryption failed: {}", e))
 }
 
 #[cfg(test)]
@@ -154,6 +307,22 @@ mod tests {
         assert!(verify_password(password, &hash).unwrap());
         assert!(!verify_password("wrong_password", &hash).unwrap());
     }
+
+    #[test]
+    fn test_password_hashing_with_custom_params() {
+        let params = KdfParams {
+            m_cost: 32 * 1024,
+            t_cost: 3,
+            p_cost: 2,
+        };
+        let (hash, _salt) = hash_password_with_params("test_password_123", &params).unwrap();
+
+        assert_eq!(password_hash_params(&hash).unwrap(), params);
+        assert!(verify_password("test_password_123", &hash).unwrap());
+
+        let (default_hash, _) = hash_password("test_password_123").unwrap();
+        assert_eq!(password_hash_params(&default_hash).unwrap(), KdfParams::default());
+    }
     #[test]
     fn test_decrypt_vault_entry_from_json() {
         let encrypted_password = "EWCVvQ41DUmIN6FfOxCHF1je2LTmofJA7BROgBwyYdI=";
@@ -196,4 +365,38 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_data_key_wrap_unwrap() {
+        let data_key = generate_data_key();
+        let wrapped = wrap_data_key(&data_key, "master_password", &KdfParams::default()).unwrap();
+
+        let unwrapped = unwrap_data_key(&wrapped, "master_password").unwrap();
+        assert_eq!(*data_key, *unwrapped);
+
+        assert!(unwrap_data_key(&wrapped, "wrong_password").is_err());
+    }
+
+    #[test]
+    fn test_encryption_with_data_key() {
+        let data_key = generate_data_key();
+        let other_key = generate_data_key();
+
+        let (encrypted, nonce) = encrypt_with_key("Secret data!", &data_key).unwrap();
+        assert_eq!(decrypt_with_key(&encrypted, &nonce, &data_key).unwrap(), "Secret data!");
+        assert!(decrypt_with_key(&encrypted, &nonce, &other_key).is_err());
+    }
+
+    #[test]
+    fn test_versioned_encryption() {
+        let params = KdfParams::default();
+        let envelope = encrypt_versioned("Secret data!", "my_password", &params).unwrap();
+
+        assert!(envelope.starts_with("$pmenc$v=1$"));
+        assert_eq!(decrypt_versioned(&envelope, "my_password").unwrap(), "Secret data!");
+        assert!(decrypt_versioned(&envelope, "wrong_password").is_err());
+
+        // decrypt_data dispatches on the envelope and ignores the legacy nonce/salt arguments
+        assert_eq!(decrypt_data(&envelope, "", "my_password", "").unwrap(), "Secret data!");
+    }
 }
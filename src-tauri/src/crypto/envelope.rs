@@ -0,0 +1,369 @@
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit},
+};
+use argon2::{Algorithm, Argon2, Params, Version, password_hash::rand_core::RngCore};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use super::encryption::DATA_KEY_LEN;
+
+/// Prefix shared by every versioned ciphertext
+pub const ENVELOPE_PREFIX: &str = "$pmenc$";
+
+/// Current envelope version
+pub const ENVELOPE_VERSION: u32 = 1;
+
+const ALG_AES_256_GCM: &str = "aes-256-gcm";
+const KDF_ARGON2ID: &str = "argon2id";
+const KEY_DATA_KEY: &str = "datakey";
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    /// Number of iterations
+    pub t_cost: u32,
+    /// Degree of parallelism
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// Same values as `Argon2::default()`, which all legacy ciphertexts used
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Lowest memory cost accepted for a vault; never weaker than the defaults
+    pub const MIN_M_COST: u32 = Params::DEFAULT_M_COST;
+    /// 4 GiB
+    pub const MAX_M_COST: u32 = 4 * 1024 * 1024;
+    pub const MAX_T_COST: u32 = 64;
+    pub const MAX_P_COST: u32 = 16;
+
+    /// Check that the costs are within the range accepted for a vault
+    pub fn validate(&self) -> Result<(), String> {
+        if !(Self::MIN_M_COST..=Self::MAX_M_COST).contains(&self.m_cost) {
+            return Err(format!(
+                "Memory cost must be between {} and {} KiB",
+                Self::MIN_M_COST,
+                Self::MAX_M_COST
+            ));
+        }
+        if !(1..=Self::MAX_T_COST).contains(&self.t_cost) {
+            return Err(format!("Iterations must be between 1 and {}", Self::MAX_T_COST));
+        }
+        if !(1..=Self::MAX_P_COST).contains(&self.p_cost) {
+            return Err(format!("Parallelism must be between 1 and {}", Self::MAX_P_COST));
+        }
+        Ok(())
+    }
+
+    pub fn argon2(&self) -> Result<Argon2<'static>, String> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, None)
+            .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    fn encode(&self) -> String {
+        format!("m={},t={},p={}", self.m_cost, self.t_cost, self.p_cost)
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut params = KdfParams {
+            m_cost: 0,
+            t_cost: 0,
+            p_cost: 0,
+        };
+        for pair in s.split(',') {
+            let (name, value) = pair.split_once('=').ok_or("Invalid KDF parameters")?;
+            let value: u32 = value.parse().map_err(|_| "Invalid KDF parameter value")?;
+            match name {
+                "m" => params.m_cost = value,
+                "t" => params.t_cost = value,
+                "p" => params.p_cost = value,
+                _ => return Err(format!("Unknown KDF parameter: {}", name)),
+            }
+        }
+        // Costs come from stored data; never run Argon2 with out-of-range values
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+/// Where the AES key of an envelope comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySpec {
+    /// Derived from the master password with Argon2id
+    Argon2id { params: KdfParams, salt: String },
+    /// The project data key (see `wrap_data_key`)
+    DataKey,
+}
+
+/// A self-describing ciphertext:
+///
+/// `$pmenc$v=1$aes-256-gcm$argon2id$m=19456,t=2,p=1$<salt>$<nonce>$<ciphertext>`
+/// `$pmenc$v=1$aes-256-gcm$datakey$<nonce>$<ciphertext>`
+///
+/// Nonce and ciphertext are standard base64, which never contains `$`.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub key: KeySpec,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+/// Check whether a stored value uses the versioned envelope format
+pub fn is_envelope(s: &str) -> bool {
+    s.starts_with(ENVELOPE_PREFIX)
+}
+
+impl Envelope {
+    /// Encrypt `plaintext` with an already derived `key` described by `spec`
+    pub fn seal(plaintext: &[u8], key: &[u8; DATA_KEY_LEN], spec: KeySpec) -> Result<Self, String> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        Ok(Self {
+            key: spec,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt with the key matching `self.key`
+    pub fn open(&self, key: &[u8; DATA_KEY_LEN]) -> Result<Vec<u8>, String> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+        cipher
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_ref())
+            .map_err(|e| format!("Decryption failed: {}", e))
+    }
+
+    /// Derive the key for a password-based envelope
+    pub fn derive_key(&self, password: &str) -> Result<[u8; DATA_KEY_LEN], String> {
+        match &self.key {
+            KeySpec::Argon2id { params, salt } => derive_key_with_params(password, salt, params),
+            KeySpec::DataKey => Err("Envelope is encrypted with the project data key".to_string()),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let nonce = general_purpose::STANDARD.encode(self.nonce);
+        let ciphertext = general_purpose::STANDARD.encode(&self.ciphertext);
+
+        match &self.key {
+            KeySpec::Argon2id { params, salt } => format!(
+                "{}v={}${}${}${}${}${}${}",
+                ENVELOPE_PREFIX,
+                ENVELOPE_VERSION,
+                ALG_AES_256_GCM,
+                KDF_ARGON2ID,
+                params.encode(),
+                salt,
+                nonce,
+                ciphertext
+            ),
+            KeySpec::DataKey => format!(
+                "{}v={}${}${}${}${}",
+                ENVELOPE_PREFIX, ENVELOPE_VERSION, ALG_AES_256_GCM, KEY_DATA_KEY, nonce, ciphertext
+            ),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let body = s
+            .strip_prefix(ENVELOPE_PREFIX)
+            .ok_or("Not a versioned ciphertext")?;
+        let parts: Vec<&str> = body.split('$').collect();
+
+        let version = parts
+            .first()
+            .and_then(|v| v.strip_prefix("v="))
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or("Missing ciphertext version")?;
+
+        match version {
+            1 => Self::parse_v1(&parts[1..]),
+            _ => Err(format!("Unsupported ciphertext version: {}", version)),
+        }
+    }
+
+    fn parse_v1(parts: &[&str]) -> Result<Self, String> {
+        let (key, nonce_b64, ciphertext_b64) = match parts {
+            [ALG_AES_256_GCM, KDF_ARGON2ID, params, salt, nonce, ciphertext] => (
+                KeySpec::Argon2id {
+                    params: KdfParams::parse(params)?,
+                    salt: salt.to_string(),
+                },
+                *nonce,
+                *ciphertext,
+            ),
+            [ALG_AES_256_GCM, KEY_DATA_KEY, nonce, ciphertext] => {
+                (KeySpec::DataKey, *nonce, *ciphertext)
+            }
+            [alg, ..] if *alg != ALG_AES_256_GCM => {
+                return Err(format!("Unsupported algorithm: {}", alg));
+            }
+            _ => return Err("Malformed versioned ciphertext".to_string()),
+        };
+
+        let nonce_bytes = general_purpose::STANDARD
+            .decode(nonce_b64)
+            .map_err(|e| format!("Failed to decode nonce: {}", e))?;
+        let nonce: [u8; 12] = nonce_bytes
+            .try_into()
+            .map_err(|_| "Invalid nonce length".to_string())?;
+
+        let ciphertext = general_purpose::STANDARD
+            .decode(ciphertext_b64)
+            .map_err(|e| format!("Failed to decode ciphertext: {}", e))?;
+
+        Ok(Self {
+            key,
+            nonce,
+            ciphertext,
+        })
+    }
+}
+
+/// Derive an encryption key from a password and salt with explicit Argon2id costs
+pub fn derive_key_with_params(
+    password: &str,
+    salt: &str,
+    params: &KdfParams,
+) -> Result<[u8; DATA_KEY_LEN], String> {
+    let mut password_bytes = password.as_bytes().to_vec();
+
+    let mut key = [0u8; DATA_KEY_LEN];
+    let result = params
+        .argon2()?
+        .hash_password_into(&password_bytes, salt.as_bytes(), &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e));
+
+    // Zeroize password from memory
+    password_bytes.zeroize();
+
+    result.map(|_| key)
+}
+
+/// Find Argon2id costs for which one key derivation takes about `target` on this machine.
+/// Memory and parallelism are kept as given (memory is halved only when a single
+/// iteration is already too slow); iterations are scaled to reach the target.
+/// Returns the suggested params and the measured duration.
+pub fn calibrate_kdf_params(
+    target: Duration,
+    m_cost: u32,
+    p_cost: u32,
+) -> Result<(KdfParams, Duration), String> {
+    let mut params = KdfParams {
+        m_cost,
+        t_cost: 1,
+        p_cost,
+    };
+    params.validate()?;
+
+    let mut elapsed = measure_kdf(&params)?;
+    while elapsed > target && params.m_cost / 2 >= KdfParams::MIN_M_COST {
+        params.m_cost /= 2;
+        elapsed = measure_kdf(&params)?;
+    }
+    if elapsed >= target {
+        return Ok((params, elapsed));
+    }
+
+    // Cost grows linearly with iterations
+    let per_iteration = elapsed.as_secs_f64().max(f64::EPSILON);
+    let t_cost = (target.as_secs_f64() / per_iteration).round() as u32;
+    params.t_cost = t_cost.clamp(1, KdfParams::MAX_T_COST);
+    let elapsed = measure_kdf(&params)?;
+
+    Ok((params, elapsed))
+}
+
+fn measure_kdf(params: &KdfParams) -> Result<Duration, String> {
+    let start = Instant::now();
+    derive_key_with_params("pomo-kdf-calibration", "calibration-salt", params)?.zeroize();
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_encode_parse() {
+        let key = [7u8; DATA_KEY_LEN];
+        let spec = KeySpec::Argon2id {
+            params: KdfParams::default(),
+            salt: "abcdefghijklmnop".to_string(),
+        };
+        let envelope = Envelope::seal(b"Secret data!", &key, spec.clone()).unwrap();
+
+        let encoded = envelope.encode();
+        assert!(encoded.starts_with("$pmenc$v=1$aes-256-gcm$argon2id$m=19456,t=2,p=1$abcdefghijklmnop$"));
+
+        let parsed = Envelope::parse(&encoded).unwrap();
+        assert_eq!(parsed.key, spec);
+        assert_eq!(parsed.open(&key).unwrap(), b"Secret data!");
+    }
+
+    #[test]
+    fn test_envelope_rejects_unknown_version() {
+        let key = [1u8; DATA_KEY_LEN];
+        let encoded = Envelope::seal(b"x", &key, KeySpec::DataKey).unwrap().encode();
+
+        let future = encoded.replacen("v=1", "v=9", 1);
+        assert!(Envelope::parse(&future).is_err());
+        assert!(Envelope::parse("not an envelope").is_err());
+    }
+
+    #[test]
+    fn test_kdf_params_validate() {
+        assert!(KdfParams::default().validate().is_ok());
+
+        let too_little_memory = KdfParams {
+            m_cost: 1024,
+            ..KdfParams::default()
+        };
+        assert!(too_little_memory.validate().is_err());
+
+        let no_iterations = KdfParams {
+            t_cost: 0,
+            ..KdfParams::default()
+        };
+        assert!(no_iterations.validate().is_err());
+    }
+
+    #[test]
+    fn test_envelope_rejects_out_of_range_kdf_params() {
+        let key = [3u8; DATA_KEY_LEN];
+        let spec = KeySpec::Argon2id {
+            params: KdfParams::default(),
+            salt: "abcdefghijklmnop".to_string(),
+        };
+        let encoded = Envelope::seal(b"x", &key, spec).unwrap().encode();
+
+        for params in ["m=4294967295,t=2,p=1", "m=19456,t=0,p=1", "m=19456,t=2"] {
+            let tampered = encoded.replacen("m=19456,t=2,p=1", params, 1);
+            assert!(Envelope::parse(&tampered).is_err(), "{}", params);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+/// Characters that are easily confused with each other in many fonts
+const AMBIGUOUS: &str = "0Oo1lI|";
+const CONSONANTS: &str = "bcdfghjklmnprstvz";
+const VOWELS: &str = "aeiou";
+
+/// Embedded diceware wordlist, one lowercase word per line
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+pub const MAX_LENGTH: usize = 128;
+pub const MAX_WORDS: usize = 20;
+
+/// Generator settings; `mode` selects the variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum GeneratorOptions {
+    Policy(PolicyOptions),
+    Diceware(DicewareOptions),
+    Pronounceable(PronounceableOptions),
+    Pattern(PatternOptions),
+}
+
+/// Random characters from the selected classes, with at least `min_per_class`
+/// characters of every selected class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyOptions {
+    pub length: usize,
+    pub uppercase: bool,
+    pub lowercase: bool,
+    pub numbers: bool,
+    pub symbols: bool,
+    pub min_per_class: usize,
+    pub exclude_ambiguous: bool,
+    /// Extra class of user supplied characters
+    pub custom_charset: Option<String>,
+}
+
+impl Default for PolicyOptions {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            uppercase: true,
+            lowercase: true,
+            numbers: true,
+            symbols: true,
+            min_per_class: 1,
+            exclude_ambiguous: false,
+            custom_charset: None,
+        }
+    }
+}
+
+/// Words from the embedded wordlist joined by `separator`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DicewareOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    /// Append a random digit to one random word
+    pub include_number: bool,
+}
+
+impl Default for DicewareOptions {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            include_number: false,
+        }
+    }
+}
+
+/// Alternating consonants and vowels, e.g. `tobakemi`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PronounceableOptions {
+    pub length: usize,
+    pub capitalize: bool,
+    /// Number of random digits appended at the end
+    pub digits: usize,
+}
+
+impl Default for PronounceableOptions {
+    fn default() -> Self {
+        Self {
+            length: 12,
+            capitalize: false,
+            digits: 0,
+        }
+    }
+}
+
+/// Template where every placeholder is replaced by a random pick:
+/// `l` lowercase, `u` uppercase, `d` digit, `s` symbol, `a` letter or digit,
+/// `c` consonant, `v` vowel, `w` word; `\x` is a literal `x`, anything else is kept.
+/// Example: `uccvd-wsd`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternOptions {
+    pub pattern: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    /// Estimated entropy in bits, assuming the attacker knows the generator settings
+    pub entropy_bits: f64,
+}
+
+/// Generate a password or passphrase with the given settings
+pub fn generate(options: &GeneratorOptions) -> Result<GeneratedPassword, String> {
+    match options {
+        GeneratorOptions::Policy(o) => generate_policy(o),
+        GeneratorOptions::Diceware(o) => generate_diceware(o),
+        GeneratorOptions::Pronounceable(o) => generate_pronounceable(o),
+        GeneratorOptions::Pattern(o) => generate_pattern(o),
+    }
+}
+
+fn charset(chars: &str, exclude_ambiguous: bool) -> Vec<char> {
+    chars
+        .chars()
+        .filter(|c| !(exclude_ambiguous && AMBIGUOUS.contains(*c)))
+        .collect()
+}
+
+fn pick<R: Rng>(rng: &mut R, set: &[char]) -> char {
+    set[rng.gen_range(0..set.len())]
+}
+
+pub fn generate_policy(options: &PolicyOptions) -> Result<GeneratedPassword, String> {
+    if options.length == 0 || options.length > MAX_LENGTH {
+        return Err(format!("Password length must be between 1 and {}", MAX_LENGTH));
+    }
+
+    let mut classes: Vec<Vec<char>> = Vec::new();
+    for (enabled, chars) in [
+        (options.lowercase, LOWERCASE),
+        (options.uppercase, UPPERCASE),
+        (options.numbers, DIGITS),
+        (options.symbols, SYMBOLS),
+    ] {
+        if enabled {
+            classes.push(charset(chars, options.exclude_ambiguous));
+        }
+    }
+    if let Some(custom) = options.custom_charset.as_deref().filter(|c| !c.is_empty()) {
+        let mut custom = charset(custom, options.exclude_ambiguous);
+        custom.sort_unstable();
+        custom.dedup();
+        classes.push(custom);
+    }
+    classes.retain(|c| !c.is_empty());
+
+    if classes.is_empty() {
+        return Err("At least one character type must be selected".to_string());
+    }
+    if classes.len() * options.min_per_class > options.length {
+        return Err(format!(
+            "Length {} is too short for {} of each of {} character types",
+            options.length,
+            options.min_per_class,
+            classes.len()
+        ));
+    }
+
+    let mut all: Vec<char> = classes.concat();
+    all.sort_unstable();
+    all.dedup();
+
+    let mut rng = rand::thread_rng();
+    let mut password: Vec<char> = Vec::with_capacity(options.length);
+    for class in &classes {
+        for _ in 0..options.min_per_class {
+            password.push(pick(&mut rng, class));
+        }
+    }
+    while password.len() < options.length {
+        password.push(pick(&mut rng, &all));
+    }
+    // 保证的字符总在前面生成，打乱后位置才是随机的
+    password.shuffle(&mut rng);
+
+    Ok(GeneratedPassword {
+        password: password.into_iter().collect(),
+        entropy_bits: options.length as f64 * (all.len() as f64).log2(),
+    })
+}
+
+pub(crate) fn wordlist() -> Vec<&'static str> {
+    WORDLIST
+        .lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn generate_diceware(options: &DicewareOptions) -> Result<GeneratedPassword, String> {
+    if options.words == 0 || options.words > MAX_WORDS {
+        return Err(format!("Word count must be between 1 and {}", MAX_WORDS));
+    }
+
+    let list = wordlist();
+    let mut rng = rand::thread_rng();
+    let mut words: Vec<String> = (0..options.words)
+        .map(|_| {
+            let word = list[rng.gen_range(0..list.len())];
+            if options.capitalize {
+                capitalize(word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect();
+
+    let mut entropy_bits = options.words as f64 * (list.len() as f64).log2();
+    if options.include_number {
+        let idx = rng.gen_range(0..words.len());
+        words[idx].push(char::from(b'0' + rng.gen_range(0..10u8)));
+        entropy_bits += (10.0 * options.words as f64).log2();
+    }
+
+    Ok(GeneratedPassword {
+        password: words.join(&options.separator),
+        entropy_bits,
+    })
+}
+
+pub fn generate_pronounceable(options: &PronounceableOptions) -> Result<GeneratedPassword, String> {
+    if options.length == 0 || options.length + options.digits > MAX_LENGTH {
+        return Err(format!("Password length must be between 1 and {}", MAX_LENGTH));
+    }
+
+    let consonants: Vec<char> = CONSONANTS.chars().collect();
+    let vowels: Vec<char> = VOWELS.chars().collect();
+    let digits: Vec<char> = DIGITS.chars().collect();
+
+    let mut rng = rand::thread_rng();
+    let mut password = String::with_capacity(options.length + options.digits);
+    let mut entropy_bits = 0.0;
+    for i in 0..options.length {
+        let set = if i % 2 == 0 { &consonants } else { &vowels };
+        password.push(pick(&mut rng, set));
+        entropy_bits += (set.len() as f64).log2();
+    }
+    if options.capitalize {
+        password = capitalize(&password);
+    }
+    for _ in 0..options.digits {
+        password.push(pick(&mut rng, &digits));
+        entropy_bits += (digits.len() as f64).log2();
+    }
+
+    Ok(GeneratedPassword {
+        password,
+        entropy_bits,
+    })
+}
+
+pub fn generate_pattern(options: &PatternOptions) -> Result<GeneratedPassword, String> {
+    let pattern_len = options.pattern.chars().count();
+    if pattern_len == 0 || pattern_len > MAX_LENGTH {
+        return Err(format!("Pattern length must be between 1 and {}", MAX_LENGTH));
+    }
+
+    let lower = charset(LOWERCASE, false);
+    let upper = charset(UPPERCASE, false);
+    let digits = charset(DIGITS, false);
+    let symbols = charset(SYMBOLS, false);
+    let alnum = [LOWERCASE, UPPERCASE, DIGITS].concat().chars().collect::<Vec<_>>();
+    let consonants = charset(CONSONANTS, false);
+    let vowels = charset(VOWELS, false);
+    let list = wordlist();
+
+    let mut rng = rand::thread_rng();
+    let mut password = String::new();
+    let mut entropy_bits = 0.0;
+    let mut chars = options.pattern.chars();
+    while let Some(c) = chars.next() {
+        let set = match c {
+            'l' => &lower,
+            'u' => &upper,
+            'd' => &digits,
+            's' => &symbols,
+            'a' => &alnum,
+            'c' => &consonants,
+            'v' => &vowels,
+            'w' => {
+                password.push_str(list[rng.gen_range(0..list.len())]);
+                entropy_bits += (list.len() as f64).log2();
+                continue;
+            }
+            '\\' => {
+                password.push(chars.next().ok_or("Pattern ends with an escape character")?);
+                continue;
+            }
+            literal => {
+                password.push(literal);
+                continue;
+            }
+        };
+        password.push(pick(&mut rng, set));
+        entropy_bits += (set.len() as f64).log2();
+    }
+
+    Ok(GeneratedPassword {
+        password,
+        entropy_bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_guarantees_each_class() {
+        let options = PolicyOptions {
+            length: 8,
+            min_per_class: 2,
+            ..PolicyOptions::default()
+        };
+        for _ in 0..200 {
+            let password = generate_policy(&options).unwrap().password;
+            assert_eq!(password.chars().count(), 8);
+            for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+                assert!(password.chars().filter(|c| class.contains(*c)).count() >= 2);
+            }
+        }
+
+        let too_short = PolicyOptions {
+            length: 3,
+            ..PolicyOptions::default()
+        };
+        assert!(generate_policy(&too_short).is_err());
+    }
+
+    #[test]
+    fn test_policy_excludes_ambiguous_and_uses_custom_charset() {
+        let options = PolicyOptions {
+            length: 64,
+            symbols: false,
+            exclude_ambiguous: true,
+            custom_charset: Some("~€".to_string()),
+            ..PolicyOptions::default()
+        };
+        let generated = generate_policy(&options).unwrap();
+        assert!(!generated.password.chars().any(|c| AMBIGUOUS.contains(c)));
+        assert!(generated.password.chars().any(|c| c == '~' || c == '€'));
+        assert!(generated.entropy_bits > 64.0 * 5.0);
+    }
+
+    #[test]
+    fn test_diceware() {
+        let options = DicewareOptions {
+            words: 5,
+            separator: ".".to_string(),
+            ..DicewareOptions::default()
+        };
+        let generated = generate_diceware(&options).unwrap();
+        let list = wordlist();
+        let words: Vec<&str> = generated.password.split('.').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| list.contains(w)));
+        assert!((generated.entropy_bits - 5.0 * (list.len() as f64).log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wordlist_is_clean() {
+        let mut list = wordlist();
+        assert!(list.len() >= 1296);
+        assert!(list.iter().all(|w| w.chars().all(|c| c.is_ascii_lowercase())));
+        let len = list.len();
+        list.sort_unstable();
+        list.dedup();
+        assert_eq!(list.len(), len);
+    }
+
+    #[test]
+    fn test_pronounceable() {
+        let options = PronounceableOptions {
+            length: 6,
+            capitalize: true,
+            digits: 2,
+        };
+        let password = generate_pronounceable(&options).unwrap().password;
+        let chars: Vec<char> = password.chars().collect();
+        assert_eq!(chars.len(), 8);
+        assert!(chars[0].is_ascii_uppercase());
+        assert!(VOWELS.contains(chars[1]));
+        assert!(chars[6].is_ascii_digit() && chars[7].is_ascii_digit());
+    }
+
+    #[test]
+    fn test_pattern() {
+        let options = PatternOptions {
+            pattern: r"uldd-\d".to_string(),
+        };
+        let generated = generate_pattern(&options).unwrap();
+        let chars: Vec<char> = generated.password.chars().collect();
+        assert_eq!(chars.len(), 6);
+        assert!(chars[0].is_ascii_uppercase());
+        assert!(chars[1].is_ascii_lowercase());
+        assert!(chars[2].is_ascii_digit() && chars[3].is_ascii_digit());
+        assert_eq!(&generated.password[4..], "-d");
+        let expected = 2.0 * 26f64.log2() + 2.0 * 10f64.log2();
+        assert!((generated.entropy_bits - expected).abs() < 1e-9);
+    }
+}
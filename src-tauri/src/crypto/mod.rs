@@ -1,3 +1,18 @@
 pub mod encryption;
+pub mod envelope;
+pub mod generator;
+pub mod ssh;
+pub mod stream;
+pub mod strength;
+pub mod totp;
 
-pub use encryption::{decrypt_data, encrypt_data, hash_password, verify_password};
+pub use encryption::{
+    DATA_KEY_LEN, decrypt_data, decrypt_with_key, encrypt_versioned, encrypt_versioned_with_key,
+    generate_data_key, hash_password_with_params, password_hash_params,
+    unwrap_data_key, verify_password, wrap_data_key, wrapped_key_params,
+};
+pub use envelope::{KdfParams, calibrate_kdf_params, is_envelope};
+pub use stream::{
+    DecryptReader, EncryptWriter, decrypt_stream, decrypt_stream_with_key,
+    encrypt_stream_with_key, is_encrypted_file,
+};
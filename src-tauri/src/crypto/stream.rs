@@ -0,0 +1,708 @@
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use argon2::password_hash::rand_core::RngCore;
+use rand::{Rng, distributions::Alphanumeric};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::encryption::{DATA_KEY_LEN, derive_key};
+
+/// File signature of an encrypted stream
+const MAGIC: &[u8; 4] = b"PMSE";
+const VERSION: u8 = 1;
+/// Version of streams whose chunks also authenticate a caller-supplied context
+const VERSION_BOUND: u8 = 2;
+
+/// Plaintext size of every chunk except the last one
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+const NONCE_PREFIX_LEN: usize = 7;
+
+// Stream layout:
+//   header: MAGIC | VERSION | salt_len (u8) | salt | nonce_prefix (7) | chunk_size (u32 LE)
+//   (salt_len 0 means the stream is encrypted with a raw key, e.g. a project data key)
+//   chunk:  last (u8) | len (u32 LE) | AES-256-GCM ciphertext
+// Each chunk nonce is nonce_prefix | counter (u32 BE) | last, and the whole header
+// is authenticated as AAD, so chunks cannot be reordered, dropped or truncated.
+// Version 2 streams append a context to the AAD that is not stored in the stream,
+// e.g. the ids of the vault attachment, so a blob only decrypts where it belongs.
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// Read until `buf` is full or EOF, returning the number of bytes read
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Check whether a file starts with the encrypted stream signature
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| &magic == MAGIC)
+        .unwrap_or(false)
+}
+
+fn random_salt() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+/// Encrypt everything from `reader` into `writer` with an already derived key,
+/// such as a project data key, bound to `context` (see `decrypt_stream_with_key`).
+/// Returns the number of plaintext bytes processed.
+pub fn encrypt_stream_with_key<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    key: &[u8; DATA_KEY_LEN],
+    context: &[u8],
+) -> Result<u64, String> {
+    copy_encrypted(reader, EncryptWriter::with_key(writer, key, context)?)
+}
+
+fn copy_encrypted<R: Read, W: Write>(
+    mut reader: R,
+    mut encryptor: EncryptWriter<W>,
+) -> Result<u64, String> {
+    let mut buf = Zeroizing::new(vec![0u8; CHUNK_SIZE]);
+    loop {
+        let n = read_full(&mut reader, &mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        encryptor
+            .write_all(&buf[..n])
+            .map_err(|e| format!("Failed to write chunk: {}", e))?;
+    }
+    encryptor.finish().map(|(_, total)| total)
+}
+
+/// Encrypts whatever is written to it, for producers that write instead of being read
+/// such as `ZipWriter`. `finish` must be called to write the last chunk; a stream left
+/// without it is rejected as truncated.
+pub struct EncryptWriter<W: Write> {
+    writer: W,
+    cipher: Aes256Gcm,
+    /// Header followed by the context
+    aad: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    buffer: Zeroizing<Vec<u8>>,
+    counter: u32,
+    total: u64,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// Start a stream encrypted with a key derived from `password` and a fresh salt
+    pub fn new(writer: W, password: &str) -> Result<Self, String> {
+        let salt = random_salt();
+        let key = Zeroizing::new(derive_key(password, &salt)?);
+        Self::start(writer, &salt, &key, &[])
+    }
+
+    /// Start a stream encrypted with an already derived key. A non-empty `context`
+    /// is authenticated with every chunk and must be passed again to decrypt.
+    pub fn with_key(writer: W, key: &[u8; DATA_KEY_LEN], context: &[u8]) -> Result<Self, String> {
+        Self::start(writer, "", key, context)
+    }
+
+    fn start(
+        mut writer: W,
+        salt: &str,
+        key: &[u8; DATA_KEY_LEN],
+        context: &[u8],
+    ) -> Result<Self, String> {
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.push(if context.is_empty() { VERSION } else { VERSION_BOUND });
+        header.push(salt.len() as u8);
+        header.extend_from_slice(salt.as_bytes());
+        header.extend_from_slice(&nonce_prefix);
+        header.extend_from_slice(&(CHUNK_SIZE as u32).to_le_bytes());
+
+        writer
+            .write_all(&header)
+            .map_err(|e| format!("Failed to write header: {}", e))?;
+
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+        let mut aad = header;
+        aad.extend_from_slice(context);
+        Ok(Self {
+            writer,
+            cipher,
+            aad,
+            nonce_prefix,
+            buffer: Zeroizing::new(Vec::with_capacity(CHUNK_SIZE)),
+            counter: 0,
+            total: 0,
+        })
+    }
+
+    fn write_chunk(&mut self, last: bool) -> Result<(), String> {
+        let nonce_bytes = chunk_nonce(&self.nonce_prefix, self.counter, last);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: &self.buffer,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        self.writer
+            .write_all(&[last as u8])
+            .and_then(|_| self.writer.write_all(&(ciphertext.len() as u32).to_le_bytes()))
+            .and_then(|_| self.writer.write_all(&ciphertext))
+            .map_err(|e| format!("Failed to write chunk: {}", e))?;
+
+        self.total += self.buffer.len() as u64;
+        self.buffer.zeroize();
+        if !last {
+            self.counter = self
+                .counter
+                .checked_add(1)
+                .ok_or("Stream too large to encrypt")?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered rest as the last chunk and flush.
+    /// Returns the inner writer and the number of plaintext bytes written.
+    pub fn finish(mut self) -> Result<(W, u64), String> {
+        self.write_chunk(true)?;
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok((self.writer, self.total))
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // 缓冲区已满且还有数据时，才能确定这一块不是最后一块
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk(false).map_err(io::Error::other)?;
+        }
+        let n = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decrypt a stream produced by `EncryptWriter::new`.
+/// Returns the number of plaintext bytes written.
+pub fn decrypt_stream<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    password: &str,
+) -> Result<u64, String> {
+    decrypt_stream_inner(reader, writer, &[], |salt| {
+        if salt.is_empty() {
+            return Err("Stream is encrypted with a key, not a password".to_string());
+        }
+        derive_key(password, salt).map(Zeroizing::new)
+    })
+}
+
+/// Decrypt a stream produced by `encrypt_stream_with_key` with the same `context`.
+/// Version 1 streams predate contexts and decrypt with any.
+/// Returns the number of plaintext bytes written.
+pub fn decrypt_stream_with_key<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    key: &[u8; DATA_KEY_LEN],
+    context: &[u8],
+) -> Result<u64, String> {
+    decrypt_stream_inner(reader, writer, context, |salt| {
+        if !salt.is_empty() {
+            return Err("Stream is encrypted with a password, not a key".to_string());
+        }
+        Ok(Zeroizing::new(*key))
+    })
+}
+
+struct StreamHeader {
+    /// Raw header bytes, authenticated as AAD of every chunk
+    bytes: Vec<u8>,
+    version: u8,
+    salt: String,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    chunk_size: usize,
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<StreamHeader, String> {
+    let mut fixed = [0u8; 6];
+    reader
+        .read_exact(&mut fixed)
+        .map_err(|_| "Not an encrypted stream".to_string())?;
+    if &fixed[..4] != MAGIC {
+        return Err("Not an encrypted stream".to_string());
+    }
+    if fixed[4] != VERSION && fixed[4] != VERSION_BOUND {
+        return Err(format!("Unsupported stream version: {}", fixed[4]));
+    }
+
+    let mut rest = vec![0u8; fixed[5] as usize + NONCE_PREFIX_LEN + 4];
+    reader
+        .read_exact(&mut rest)
+        .map_err(|_| "Stream header is truncated".to_string())?;
+
+    let mut header = fixed.to_vec();
+    header.extend_from_slice(&rest);
+
+    let salt_len = fixed[5] as usize;
+    let salt = std::str::from_utf8(&rest[..salt_len])
+        .map_err(|_| "Invalid salt in stream header".to_string())?
+        .to_string();
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    nonce_prefix.copy_from_slice(&rest[salt_len..salt_len + NONCE_PREFIX_LEN]);
+    let mut chunk_size_bytes = [0u8; 4];
+    chunk_size_bytes.copy_from_slice(&rest[salt_len + NONCE_PREFIX_LEN..]);
+
+    Ok(StreamHeader {
+        bytes: header,
+        version: fixed[4],
+        salt,
+        nonce_prefix,
+        chunk_size: u32::from_le_bytes(chunk_size_bytes) as usize,
+    })
+}
+
+/// Parse the header, obtain the key for its salt from `key_for_salt`, then decrypt all chunks
+fn decrypt_stream_inner<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    context: &[u8],
+    key_for_salt: impl FnOnce(&str) -> Result<Zeroizing<[u8; DATA_KEY_LEN]>, String>,
+) -> Result<u64, String> {
+    let StreamHeader {
+        bytes: mut aad,
+        version,
+        salt,
+        nonce_prefix,
+        chunk_size,
+    } = read_header(&mut reader)?;
+    if version == VERSION_BOUND {
+        aad.extend_from_slice(context);
+    }
+    // GCM tag is 16 bytes
+    let max_chunk_len = chunk_size + 16;
+
+    let key = key_for_salt(&salt)?;
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref())
+        .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+    let mut counter: u32 = 0;
+    let mut total: u64 = 0;
+
+    loop {
+        let mut frame = [0u8; 5];
+        reader
+            .read_exact(&mut frame)
+            .map_err(|_| "Encrypted stream is truncated".to_string())?;
+        let last = match frame[0] {
+            0 => false,
+            1 => true,
+            _ => return Err("Corrupted chunk header".to_string()),
+        };
+        let len = u32::from_le_bytes([frame[1], frame[2], frame[3], frame[4]]) as usize;
+        if len > max_chunk_len {
+            return Err("Corrupted chunk length".to_string());
+        }
+
+        let mut ciphertext = vec![0u8; len];
+        reader
+            .read_exact(&mut ciphertext)
+            .map_err(|_| "Encrypted stream is truncated".to_string())?;
+
+        let nonce_bytes = chunk_nonce(&nonce_prefix, counter, last);
+        let mut plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|e| format!("Decryption failed: {}", e))?;
+
+        writer
+            .write_all(&plaintext)
+            .map_err(|e| format!("Failed to write plaintext: {}", e))?;
+        total += plaintext.len() as u64;
+        plaintext.zeroize();
+
+        if last {
+            break;
+        }
+        counter = counter
+            .checked_add(1)
+            .ok_or("Corrupted stream: too many chunks")?;
+    }
+
+    // 最后一块之后不应再有数据
+    let mut trailing = [0u8; 1];
+    if read_full(&mut reader, &mut trailing).map_err(|e| e.to_string())? != 0 {
+        return Err("Unexpected data after final chunk".to_string());
+    }
+
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(total)
+}
+
+/// Random-access reader over a password-encrypted stream, so that formats which seek,
+/// such as zip, can be read without decrypting everything first. Each chunk is
+/// authenticated when it is read and only the current one is kept in memory.
+pub struct DecryptReader<R: Read + Seek> {
+    reader: R,
+    cipher: Aes256Gcm,
+    header: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    chunk_size: u64,
+    chunks: u64,
+    len: u64,
+    pos: u64,
+    chunk: Option<(u64, Zeroizing<Vec<u8>>)>,
+}
+
+impl<R: Read + Seek> DecryptReader<R> {
+    /// Parse the header and authenticate the last chunk, which fails for a wrong
+    /// password or a truncated stream
+    pub fn new(mut reader: R, password: &str) -> Result<Self, String> {
+        reader.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+        let header = read_header(&mut reader)?;
+        if header.salt.is_empty() {
+            return Err("Stream is encrypted with a key, not a password".to_string());
+        }
+        if header.chunk_size == 0 {
+            return Err("Corrupted chunk size".to_string());
+        }
+
+        let key = Zeroizing::new(derive_key(password, &header.salt)?);
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref())
+            .map_err(|e| format!("Failed to create cipher: {}", e))?;
+
+        // 除最后一块外每块都是完整的，可以直接算出块数和明文长度
+        let data_start = header.bytes.len() as u64;
+        let end = reader.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        let chunk_size = header.chunk_size as u64;
+        let frame_len = 5 + chunk_size + 16;
+        let data_len = end.saturating_sub(data_start);
+        let chunks = data_len.div_ceil(frame_len);
+        let last_frame = data_len.saturating_sub(chunks.saturating_sub(1) * frame_len);
+        if chunks == 0 || last_frame < 5 + 16 {
+            return Err("Encrypted stream is truncated".to_string());
+        }
+        if chunks - 1 > u32::MAX as u64 {
+            return Err("Corrupted stream: too many chunks".to_string());
+        }
+
+        let mut decryptor = Self {
+            reader,
+            cipher,
+            header: header.bytes,
+            nonce_prefix: header.nonce_prefix,
+            chunk_size,
+            chunks,
+            len: (chunks - 1) * chunk_size + last_frame - 5 - 16,
+            pos: 0,
+            chunk: None,
+        };
+        decryptor.load_chunk(chunks - 1)?;
+        Ok(decryptor)
+    }
+
+    fn load_chunk(&mut self, index: u64) -> Result<(), String> {
+        if matches!(&self.chunk, Some((i, _)) if *i == index) {
+            return Ok(());
+        }
+
+        let last = index + 1 == self.chunks;
+        let plain_len = if last {
+            self.len - index * self.chunk_size
+        } else {
+            self.chunk_size
+        };
+        let offset = self.header.len() as u64 + index * (5 + self.chunk_size + 16);
+
+        let mut frame = [0u8; 5];
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.reader.read_exact(&mut frame))
+            .map_err(|_| "Encrypted stream is truncated".to_string())?;
+        let len = u32::from_le_bytes([frame[1], frame[2], frame[3], frame[4]]) as u64;
+        if frame[0] != last as u8 || len != plain_len + 16 {
+            return Err("Corrupted chunk header".to_string());
+        }
+
+        let mut ciphertext = vec![0u8; len as usize];
+        self.reader
+            .read_exact(&mut ciphertext)
+            .map_err(|_| "Encrypted stream is truncated".to_string())?;
+
+        let nonce_bytes = chunk_nonce(&self.nonce_prefix, index as u32, last);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce_bytes),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &self.header,
+                },
+            )
+            .map_err(|e| format!("Decryption failed: {}", e))?;
+
+        self.chunk = Some((index, Zeroizing::new(plaintext)));
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+
+        let index = self.pos / self.chunk_size;
+        self.load_chunk(index)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let Some((_, plaintext)) = &self.chunk else {
+            return Ok(0);
+        };
+
+        let offset = (self.pos - index * self.chunk_size) as usize;
+        let n = buf.len().min(plaintext.len() - offset);
+        buf[..n].copy_from_slice(&plaintext[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for DecryptReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek to a negative position")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt_stream<R: Read, W: Write>(
+        reader: R,
+        writer: W,
+        password: &str,
+    ) -> Result<u64, String> {
+        copy_encrypted(reader, EncryptWriter::new(writer, password)?)
+    }
+
+    fn roundtrip(len: usize) {
+        let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let mut encrypted = Vec::new();
+        encrypt_stream(plaintext.as_slice(), &mut encrypted, "stream_password").unwrap();
+
+        let mut decrypted = Vec::new();
+        let written = decrypt_stream(encrypted.as_slice(), &mut decrypted, "stream_password").unwrap();
+
+        assert_eq!(written as usize, len);
+        assert_eq!(plaintext, decrypted);
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        roundtrip(0);
+        roundtrip(10);
+        roundtrip(CHUNK_SIZE);
+        roundtrip(CHUNK_SIZE * 2 + 17);
+    }
+
+    #[test]
+    fn test_stream_wrong_password() {
+        let mut encrypted = Vec::new();
+        encrypt_stream(&b"binary \x00\xff data"[..], &mut encrypted, "right").unwrap();
+
+        let result = decrypt_stream(encrypted.as_slice(), Vec::new(), "wrong");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stream_truncation_detected() {
+        let plaintext = vec![7u8; CHUNK_SIZE + 100];
+        let mut encrypted = Vec::new();
+        encrypt_stream(plaintext.as_slice(), &mut encrypted, "pw").unwrap();
+
+        // 去掉最后一块，剩余数据本身仍然可以逐块解密
+        let first_chunk_end = encrypted.len() - (1 + 4 + 100 + 16);
+        let result = decrypt_stream(&encrypted[..first_chunk_end], Vec::new(), "pw");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stream_with_key_binary_roundtrip() {
+        let key = [9u8; DATA_KEY_LEN];
+        // 非 UTF-8 的二进制内容
+        let plaintext: Vec<u8> = (0..CHUNK_SIZE + 3).map(|i| (i * 7 % 256) as u8).collect();
+        let mut encrypted = Vec::new();
+        encrypt_stream_with_key(plaintext.as_slice(), &mut encrypted, &key, b"ctx").unwrap();
+
+        let mut decrypted = Vec::new();
+        decrypt_stream_with_key(encrypted.as_slice(), &mut decrypted, &key, b"ctx").unwrap();
+        assert_eq!(plaintext, decrypted);
+
+        // 密钥流不能当作密码流解密，反之亦然
+        assert!(decrypt_stream(encrypted.as_slice(), Vec::new(), "pw").is_err());
+        let other_key = [8u8; DATA_KEY_LEN];
+        assert!(decrypt_stream_with_key(encrypted.as_slice(), Vec::new(), &other_key, b"ctx").is_err());
+    }
+
+    #[test]
+    fn test_stream_with_key_is_bound_to_context() {
+        let key = [9u8; DATA_KEY_LEN];
+        let mut encrypted = Vec::new();
+        encrypt_stream_with_key(&b"attachment"[..], &mut encrypted, &key, b"entry 1/a").unwrap();
+        assert_eq!(encrypted[4], VERSION_BOUND);
+
+        assert!(decrypt_stream_with_key(encrypted.as_slice(), Vec::new(), &key, b"entry 2/a").is_err());
+        assert!(decrypt_stream_with_key(encrypted.as_slice(), Vec::new(), &key, b"").is_err());
+
+        // 没有上下文的旧版本流仍然可以解密
+        let mut legacy = Vec::new();
+        encrypt_stream_with_key(&b"attachment"[..], &mut legacy, &key, b"").unwrap();
+        assert_eq!(legacy[4], VERSION);
+        let mut decrypted = Vec::new();
+        decrypt_stream_with_key(legacy.as_slice(), &mut decrypted, &key, b"entry 1/a").unwrap();
+        assert_eq!(decrypted, b"attachment");
+
+        // 版本号属于 AAD，改成旧版本也无法绕过上下文
+        encrypted[4] = VERSION;
+        assert!(decrypt_stream_with_key(encrypted.as_slice(), Vec::new(), &key, b"").is_err());
+    }
+
+    #[test]
+    fn test_encrypt_writer_matches_stream_format() {
+        let plaintext: Vec<u8> = (0..CHUNK_SIZE * 2 + 5).map(|i| (i % 13) as u8).collect();
+        let mut encryptor = EncryptWriter::new(Vec::new(), "pw").unwrap();
+        // 以不规则的大小分多次写入
+        for piece in plaintext.chunks(CHUNK_SIZE / 3 + 1) {
+            encryptor.write_all(piece).unwrap();
+        }
+        let (encrypted, total) = encryptor.finish().unwrap();
+        assert_eq!(total as usize, plaintext.len());
+
+        let mut decrypted = Vec::new();
+        decrypt_stream(encrypted.as_slice(), &mut decrypted, "pw").unwrap();
+        assert_eq!(plaintext, decrypted);
+
+        // 没有调用 finish 的流缺少最后一块
+        let mut unfinished = EncryptWriter::new(Vec::new(), "pw").unwrap();
+        unfinished.write_all(&plaintext).unwrap();
+        let partial = unfinished.writer.clone();
+        assert!(decrypt_stream(partial.as_slice(), Vec::new(), "pw").is_err());
+    }
+
+    #[test]
+    fn test_decrypt_reader_random_access() {
+        for len in [0, 10, CHUNK_SIZE, CHUNK_SIZE * 2 + 17] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let mut encrypted = Vec::new();
+            encrypt_stream(plaintext.as_slice(), &mut encrypted, "pw").unwrap();
+
+            let mut reader = DecryptReader::new(io::Cursor::new(&encrypted), "pw").unwrap();
+            assert_eq!(reader.seek(SeekFrom::End(0)).unwrap() as usize, len);
+            reader.seek(SeekFrom::Start(0)).unwrap();
+
+            let mut all = Vec::new();
+            reader.read_to_end(&mut all).unwrap();
+            assert_eq!(all, plaintext);
+
+            if len > CHUNK_SIZE {
+                // 跨块读取，再回到开头
+                let mut buf = vec![0u8; 40];
+                reader.seek(SeekFrom::Start(CHUNK_SIZE as u64 - 20)).unwrap();
+                reader.read_exact(&mut buf).unwrap();
+                assert_eq!(buf, plaintext[CHUNK_SIZE - 20..CHUNK_SIZE + 20]);
+
+                reader.seek(SeekFrom::End(-5)).unwrap();
+                let mut tail = Vec::new();
+                reader.read_to_end(&mut tail).unwrap();
+                assert_eq!(tail, plaintext[len - 5..]);
+
+                reader.seek(SeekFrom::Start(0)).unwrap();
+                reader.read_exact(&mut buf).unwrap();
+                assert_eq!(buf, plaintext[..40]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decrypt_reader_rejects_wrong_password_and_truncation() {
+        let plaintext = vec![7u8; CHUNK_SIZE + 100];
+        let mut encrypted = Vec::new();
+        encrypt_stream(plaintext.as_slice(), &mut encrypted, "pw").unwrap();
+
+        assert!(DecryptReader::new(io::Cursor::new(&encrypted), "wrong").is_err());
+
+        let first_chunk_end = encrypted.len() - (1 + 4 + 100 + 16);
+        let truncated = &encrypted[..first_chunk_end];
+        assert!(DecryptReader::new(io::Cursor::new(truncated), "pw").is_err());
+
+        let mut key_stream = Vec::new();
+        encrypt_stream_with_key(&b"data"[..], &mut key_stream, &[1u8; DATA_KEY_LEN], b"").unwrap();
+        assert!(DecryptReader::new(io::Cursor::new(&key_stream), "pw").is_err());
+    }
+
+    #[test]
+    fn test_decrypt_reader_detects_tampered_chunk() {
+        let plaintext = vec![3u8; CHUNK_SIZE * 2];
+        let mut encrypted = Vec::new();
+        encrypt_stream(plaintext.as_slice(), &mut encrypted, "pw").unwrap();
+
+        // 篡改第一块的密文：打开时只验证最后一块，读到第一块时报错
+        let header_len = encrypted.len() - 2 * (5 + CHUNK_SIZE + 16);
+        encrypted[header_len + 10] ^= 1;
+        let mut reader = DecryptReader::new(io::Cursor::new(&encrypted), "pw").unwrap();
+        let mut buf = [0u8; 16];
+        assert!(reader.read_exact(&mut buf).is_err());
+    }
+}
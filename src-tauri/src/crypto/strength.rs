@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+use super::generator::wordlist;
+
+/// A few of the most common leaked passwords; any of them scores 0
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "123456789", "12345678", "password", "qwerty", "12345", "1234567", "111111",
+    "123123", "1234567890", "000000", "abc123", "password1", "iloveyou", "qwerty123",
+    "1q2w3e4r", "admin", "admin123", "root", "toor", "letmein", "welcome", "monkey", "dragon",
+    "football", "baseball", "sunshine", "princess", "master", "shadow", "superman", "trustno1",
+    "passw0rd", "p@ssw0rd", "changeme", "secret", "test", "test123", "guest", "default",
+    "qwertyuiop", "asdfghjkl", "zxcvbnm", "654321", "666666", "888888", "987654321", "mysql",
+    "postgres", "oracle",
+];
+
+/// Keyboard rows used to spot walks like `qwer` or `asdf`
+const KEYBOARD_ROWS: &[&str] = &["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Dictionary words shorter than this are not worth penalising
+const MIN_WORD_LEN: usize = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrengthReport {
+    /// 0 (very weak) to 4 (very strong), on the same scale as zxcvbn
+    pub score: u8,
+    pub entropy_bits: f64,
+    pub warnings: Vec<String>,
+}
+
+fn pool_size(value: &str) -> usize {
+    let mut lower = false;
+    let mut upper = false;
+    let mut digit = false;
+    let mut symbol = false;
+    let mut other = false;
+    for c in value.chars() {
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            c if c.is_ascii_graphic() || c == ' ' => symbol = true,
+            _ => other = true,
+        }
+    }
+    [(lower, 26), (upper, 26), (digit, 10), (symbol, 33), (other, 100)]
+        .iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, size)| size)
+        .sum()
+}
+
+fn is_keyboard_step(a: char, b: char) -> bool {
+    KEYBOARD_ROWS.iter().any(|row| {
+        row.find(a)
+            .zip(row.find(b))
+            .is_some_and(|(i, j)| i.abs_diff(j) == 1)
+    })
+}
+
+fn score_for(entropy_bits: f64) -> u8 {
+    match entropy_bits {
+        e if e < 28.0 => 0,
+        e if e < 36.0 => 1,
+        e if e < 60.0 => 2,
+        e if e < 80.0 => 3,
+        _ => 4,
+    }
+}
+
+/// Estimate the strength of a secret.
+/// Starts from `length * log2(pool)` and discounts characters that follow a
+/// pattern (repeats, alphabet/digit sequences, keyboard walks) and dictionary words,
+/// which an attacker would guess as a whole rather than character by character.
+pub fn estimate_strength(value: &str) -> StrengthReport {
+    let mut warnings = Vec::new();
+    let lower = value.to_lowercase();
+    let chars: Vec<char> = lower.chars().collect();
+
+    if chars.is_empty() {
+        return StrengthReport {
+            score: 0,
+            entropy_bits: 0.0,
+            warnings: vec!["Empty value".to_string()],
+        };
+    }
+
+    if COMMON_PASSWORDS.contains(&lower.as_str()) {
+        return StrengthReport {
+            score: 0,
+            entropy_bits: (COMMON_PASSWORDS.len() as f64).log2(),
+            warnings: vec!["This is a very common password".to_string()],
+        };
+    }
+
+    let bits_per_char = (pool_size(value) as f64).log2();
+
+    // Characters continuing a repeat/sequence/keyboard walk only count a quarter
+    let mut effective_len = 1.0;
+    let (mut repeats, mut sequences) = (0, 0);
+    for pair in chars.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a == b {
+            repeats += 1;
+            effective_len += 0.25;
+        } else if (b as i64 - a as i64).abs() == 1 || is_keyboard_step(a, b) {
+            sequences += 1;
+            effective_len += 0.25;
+        } else {
+            effective_len += 1.0;
+        }
+    }
+    let mut entropy_bits = effective_len * bits_per_char;
+
+    // A dictionary word costs about log2(wordlist size) to guess, not its length in characters
+    let words = wordlist();
+    let word_bits = (words.len() as f64).log2();
+    let mut found_word = false;
+    for word in words.iter().filter(|w| w.len() >= MIN_WORD_LEN) {
+        if lower.contains(word) {
+            found_word = true;
+            entropy_bits -= (word.len() as f64 * bits_per_char - word_bits).max(0.0);
+        }
+    }
+    let entropy_bits = entropy_bits.max(0.0);
+
+    if chars.len() < 12 {
+        warnings.push("Shorter than 12 characters".to_string());
+    }
+    if pool_size(value) <= 26 {
+        warnings.push("Uses a single character type".to_string());
+    }
+    if repeats * 4 >= chars.len() {
+        warnings.push("Contains repeated characters".to_string());
+    }
+    if sequences * 4 >= chars.len() {
+        warnings.push("Contains sequences or keyboard patterns".to_string());
+    }
+    if found_word {
+        warnings.push("Contains dictionary words".to_string());
+    }
+
+    StrengthReport {
+        score: score_for(entropy_bits),
+        entropy_bits,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_and_patterned_passwords_are_weak() {
+        assert_eq!(estimate_strength("password").score, 0);
+        assert_eq!(estimate_strength("P@ssw0rd").score, 0);
+        assert!(estimate_strength("aaaaaaaaaaaa").score <= 1);
+        assert!(estimate_strength("abcdefghijkl").score <= 1);
+        assert!(estimate_strength("qwertyuiop12").score <= 1);
+        assert_eq!(estimate_strength("").score, 0);
+    }
+
+    #[test]
+    fn test_random_passwords_are_strong() {
+        let report = estimate_strength("x7#Kq9!vR2m$Lp4&");
+        assert_eq!(report.score, 4);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_dictionary_words_are_discounted() {
+        let words = estimate_strength("tigerrobotpiano");
+        let random = estimate_strength("tqzvkwyrpbnmxjh");
+        assert!(words.entropy_bits < random.entropy_bits);
+        assert!(words.warnings.iter().any(|w| w.contains("dictionary")));
+    }
+}
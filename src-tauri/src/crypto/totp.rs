@@ -0,0 +1,306 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroize;
+
+pub const DEFAULT_DIGITS: u32 = 6;
+pub const DEFAULT_PERIOD: u64 = 30;
+
+/// HMAC hash function used by a TOTP generator (RFC 6238 section 1.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_uppercase().as_str() {
+            "SHA1" => Ok(Self::Sha1),
+            "SHA256" => Ok(Self::Sha256),
+            "SHA512" => Ok(Self::Sha512),
+            other => Err(format!("Unsupported TOTP algorithm: {}", other)),
+        }
+    }
+}
+
+/// A TOTP generator parsed from an `otpauth://totp/...` URI or a bare base32 secret
+#[derive(Debug, Clone)]
+pub struct TotpConfig {
+    pub secret: Vec<u8>,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+impl Drop for TotpConfig {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpCode {
+    pub code: String,
+    /// Seconds until the code changes
+    pub remaining: u64,
+    pub period: u64,
+    pub digits: u32,
+    pub issuer: Option<String>,
+    pub account: Option<String>,
+}
+
+/// Decode RFC 4648 base32; spaces, dashes and `=` padding are ignored, case-insensitive
+pub fn base32_decode(input: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u8 - b'A',
+            c @ '2'..='7' => c as u8 - b'2' + 26,
+            _ => return Err(format!("Invalid base32 character: {}", c)),
+        };
+        buffer = (buffer << 5) | u64::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    buffer.zeroize();
+    Ok(out)
+}
+
+fn percent_decode(input: &str) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = (bytes[i] == b'%' && i + 2 < bytes.len())
+            .then(|| Some(hex(bytes[i + 1])? << 4 | hex(bytes[i + 2])?))
+            .flatten();
+        match decoded {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parse an `otpauth://totp/Issuer:account?secret=...&algorithm=SHA256&digits=8&period=60`
+/// URI or a bare base32 secret (which uses SHA1, 6 digits and a 30 second period)
+pub fn parse_totp(input: &str) -> Result<TotpConfig, String> {
+    let input = input.trim();
+
+    let Some(rest) = input.strip_prefix("otpauth://") else {
+        let secret = base32_decode(input)?;
+        if secret.is_empty() {
+            return Err("TOTP secret is empty".to_string());
+        }
+        return Ok(TotpConfig {
+            secret,
+            algorithm: TotpAlgorithm::Sha1,
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+            issuer: None,
+            account: None,
+        });
+    };
+
+    let (kind_and_label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (kind, label) = kind_and_label.split_once('/').unwrap_or((kind_and_label, ""));
+    if !kind.eq_ignore_ascii_case("totp") {
+        return Err(format!("Unsupported OTP type: {}", kind));
+    }
+
+    let label = percent_decode(label);
+    let (mut issuer, account) = match label.split_once(':') {
+        Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim().to_string()),
+        None => (None, label.trim().to_string()),
+    };
+
+    let mut secret = None;
+    let mut algorithm = TotpAlgorithm::Sha1;
+    let mut digits = DEFAULT_DIGITS;
+    let mut period = DEFAULT_PERIOD;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match name.to_lowercase().as_str() {
+            "secret" => secret = Some(base32_decode(&value)?),
+            "algorithm" => algorithm = TotpAlgorithm::parse(&value)?,
+            "digits" => digits = value.parse().map_err(|_| "Invalid TOTP digits")?,
+            "period" => period = value.parse().map_err(|_| "Invalid TOTP period")?,
+            "issuer" => issuer = Some(value),
+            _ => {}
+        }
+    }
+
+    let secret = secret
+        .filter(|s| !s.is_empty())
+        .ok_or("otpauth URI has no secret")?;
+    if !(6..=8).contains(&digits) {
+        return Err("TOTP digits must be between 6 and 8".to_string());
+    }
+    if period == 0 {
+        return Err("TOTP period must be greater than 0".to_string());
+    }
+
+    Ok(TotpConfig {
+        secret,
+        algorithm,
+        digits,
+        period,
+        issuer: issuer.filter(|i| !i.is_empty()),
+        account: (!account.is_empty()).then_some(account),
+    })
+}
+
+fn hmac_digest(algorithm: TotpAlgorithm, key: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+    fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+        let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key)
+            .map_err(|e| format!("Invalid HMAC key: {}", e))?;
+        mac.update(message);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    match algorithm {
+        TotpAlgorithm::Sha1 => mac::<Hmac<Sha1>>(key, message),
+        TotpAlgorithm::Sha256 => mac::<Hmac<Sha256>>(key, message),
+        TotpAlgorithm::Sha512 => mac::<Hmac<Sha512>>(key, message),
+    }
+}
+
+/// HOTP value (RFC 4226) for `counter`, zero padded to `digits`
+pub fn hotp(
+    secret: &[u8],
+    counter: u64,
+    algorithm: TotpAlgorithm,
+    digits: u32,
+) -> Result<String, String> {
+    let mut digest = hmac_digest(algorithm, secret, &counter.to_be_bytes())?;
+
+    // Dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = (u32::from(digest[offset] & 0x7f) << 24)
+        | (u32::from(digest[offset + 1]) << 16)
+        | (u32::from(digest[offset + 2]) << 8)
+        | u32::from(digest[offset + 3]);
+    digest.zeroize();
+
+    let code = u64::from(binary) % 10u64.pow(digits);
+    Ok(format!("{:0width$}", code, width = digits as usize))
+}
+
+/// TOTP code (RFC 6238) at `unix_time`
+pub fn totp_at(config: &TotpConfig, unix_time: u64) -> Result<TotpCode, String> {
+    let counter = unix_time / config.period;
+    Ok(TotpCode {
+        code: hotp(&config.secret, counter, config.algorithm, config.digits)?,
+        remaining: config.period - unix_time % config.period,
+        period: config.period,
+        digits: config.digits,
+        issuer: config.issuer.clone(),
+        account: config.account.clone(),
+    })
+}
+
+/// TOTP code for the current system time
+pub fn totp_now(config: &TotpConfig) -> Result<TotpCode, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    totp_at(config, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc_config(algorithm: TotpAlgorithm) -> TotpConfig {
+        // RFC 6238 Appendix B seeds: ASCII "1234567890" repeated to the hash length
+        let seed_len = match algorithm {
+            TotpAlgorithm::Sha1 => 20,
+            TotpAlgorithm::Sha256 => 32,
+            TotpAlgorithm::Sha512 => 64,
+        };
+        TotpConfig {
+            secret: b"1234567890".iter().cycle().take(seed_len).copied().collect(),
+            algorithm,
+            digits: 8,
+            period: 30,
+            issuer: None,
+            account: None,
+        }
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let vectors: [(u64, &str, &str, &str); 6] = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+
+        let sha1 = rfc_config(TotpAlgorithm::Sha1);
+        let sha256 = rfc_config(TotpAlgorithm::Sha256);
+        let sha512 = rfc_config(TotpAlgorithm::Sha512);
+        for (time, expected1, expected256, expected512) in vectors {
+            assert_eq!(totp_at(&sha1, time).unwrap().code, expected1);
+            assert_eq!(totp_at(&sha256, time).unwrap().code, expected256);
+            assert_eq!(totp_at(&sha512, time).unwrap().code, expected512);
+        }
+    }
+
+    #[test]
+    fn test_remaining_seconds() {
+        let config = rfc_config(TotpAlgorithm::Sha1);
+        assert_eq!(totp_at(&config, 59).unwrap().remaining, 1);
+        assert_eq!(totp_at(&config, 60).unwrap().remaining, 30);
+    }
+
+    #[test]
+    fn test_parse_otpauth_uri() {
+        // "12345678901234567890" in base32
+        let uri = "otpauth://totp/ACME%20Co:alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60";
+        let config = parse_totp(uri).unwrap();
+        assert_eq!(config.secret, b"12345678901234567890");
+        assert_eq!(config.algorithm, TotpAlgorithm::Sha256);
+        assert_eq!(config.digits, 8);
+        assert_eq!(config.period, 60);
+        assert_eq!(config.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(config.account.as_deref(), Some("alice@example.com"));
+
+        assert!(parse_totp("otpauth://hotp/x?secret=GEZDGNBV").is_err());
+        assert!(parse_totp("otpauth://totp/x?secret=GEZDGNBV&digits=9").is_err());
+    }
+
+    #[test]
+    fn test_parse_base32_secret() {
+        let config = parse_totp("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(config.secret, b"12345678901234567890");
+        assert_eq!(config.digits, 6);
+        assert_eq!(totp_at(&config, 59).unwrap().code, "287082");
+        assert!(parse_totp("not base32!").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+// Rust-side access to the SQLite database managed by tauri-plugin-sql.
+// The plugin preloads `DB_URL` (see `plugins.sql.preload` in tauri.conf.json) and runs
+// the migrations, so commands share its connection pool instead of opening their own.
+
+use sqlx::SqlitePool;
+use tauri::Manager;
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+pub const DB_URL: &str = "sqlite:pomo.db";
+
+/// Connection pool of the app database
+pub async fn pool(app: &tauri::AppHandle) -> Result<SqlitePool, String> {
+    let state = app
+        .try_state::<DbInstances>()
+        .ok_or("Database plugin is not initialized")?;
+    let instances = state.inner().0.read().await;
+
+    match instances.get(DB_URL) {
+        Some(DbPool::Sqlite(pool)) => Ok(pool.clone()),
+        _ => Err(format!("Database {} is not loaded", DB_URL)),
+    }
+}
+
+/// Fresh in-memory database with every migration applied, for repository tests
+#[cfg(test)]
+pub(crate) async fn memory_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        // 每个连接都是独立的内存数据库，只保留一个且不回收
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+    files.sort();
+    for file in files {
+        let sql = std::fs::read_to_string(&file).unwrap();
+        sqlx::raw_sql(&sql).execute(&pool).await.unwrap();
+        // 005 只在列已改名的数据库上成功（它从旧表读取 param_key/encrypted_value），
+        // 这里按它的前提先改名
+        if file.ends_with("003_add_vault.sql") {
+            sqlx::raw_sql(
+                "ALTER TABLE vault_entries RENAME COLUMN username TO param_key; \
+                 ALTER TABLE vault_entries RENAME COLUMN encrypted_password TO encrypted_value;",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+    }
+    pool
+}
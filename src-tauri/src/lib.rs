@@ -3,27 +3,29 @@ use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri_plugin_sql::{Migration, MigrationKind};
 
-use std::path::Path;
-
 mod commands;
 mod crypto;
+mod db;
+mod migrator;
+#[cfg(test)]
+mod test_support;
 
-fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
-    if !dst.exists() {
-        std::fs::create_dir_all(dst)?;
+/// 从托盘恢复主窗口
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
     }
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-        let metadata = entry.metadata()?;
-        if metadata.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
-        } else if !dst_path.exists() {
-            std::fs::copy(&src_path, &dst_path)?;
-        }
+}
+
+/// 隐藏主窗口到托盘；隐藏前先锁定所有保险箱，密钥不会在后台留在内存中
+#[tauri::command]
+fn hide_main_window(app: tauri::AppHandle) -> Result<(), String> {
+    commands::vault::lock_all_sessions(&app);
+    match app.get_webview_window("main") {
+        Some(window) => window.hide().map_err(|e| e.to_string()),
+        None => Ok(()),
     }
-    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -32,13 +34,14 @@ pub fn run() {
         // 单实例插件必须最先注册
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             // 当尝试打开第二个实例时，显示并聚焦现有窗口
+            show_main_window(app);
             if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
                 let _ = window.unminimize();
-                let _ = window.set_focus();
             }
         }))
+        .manage(commands::vault::VaultSessions::default())
         .invoke_handler(tauri::generate_handler![
+            hide_main_window,
             commands::terminal::open_terminal,
             commands::folder::open_in_file_explorer,
             commands::git::git_is_repository,
@@ -46,17 +49,54 @@ pub fn run() {
             commands::git::git_get_branches,
             commands::git::git_get_commits,
             commands::git::git_get_commit_detail,
-            commands::git::git_checkout_branch,
             commands::git::git_get_status,
             commands::git::git_get_remotes,
             commands::git::git_stage_files,
             commands::git::git_unstage_files,
             commands::git::git_commit,
+            commands::git_branch::git_create_branch,
+            commands::git_branch::git_delete_branch,
+            commands::git_branch::git_rename_branch,
+            commands::git_branch::git_set_upstream,
+            commands::git_branch::git_checkout_branch,
+            commands::git_branch::git_checkout_remote_branch,
+            commands::git_stash::git_stash_save,
+            commands::git_stash::git_stash_list,
+            commands::git_stash::git_stash_apply,
+            commands::git_stash::git_stash_pop,
+            commands::git_stash::git_stash_drop,
+            commands::git_stash::git_stash_show,
+            commands::git_remote::git_fetch,
+            commands::git_remote::git_pull,
+            commands::git_remote::git_push,
             commands::vault::vault_hash_password,
             commands::vault::vault_verify_master,
             commands::vault::vault_encrypt_entry,
             commands::vault::vault_decrypt_entry,
+            commands::vault::vault_rotate_master,
+            commands::vault::vault_calibrate_kdf,
+            commands::vault::vault_unlock,
+            commands::vault::vault_lock,
+            commands::vault::vault_lock_all,
+            commands::vault::vault_session_encrypt_entry,
+            commands::vault::vault_session_decrypt_entry,
+            commands::vault::vault_session_encrypt_totp,
+            commands::vault::vault_totp_code,
+            commands::vault_attachments::vault_attach_file,
+            commands::vault_attachments::vault_list_attachments,
+            commands::vault_attachments::vault_extract_attachment,
+            commands::vault_attachments::vault_delete_attachment,
+            commands::vault_audit::vault_audit,
+            commands::vault_export::vault_export_env,
+            commands::vault_history::vault_update_entry,
+            commands::vault_history::vault_list_entry_versions,
+            commands::vault_history::vault_decrypt_entry_version,
+            commands::vault_import::vault_import_preview,
+            commands::vault_import::vault_import_entries,
+            commands::vault_ssh::vault_generate_ssh_key,
+            commands::vault_ssh::vault_write_ssh_key,
             commands::vault::vault_generate_password,
+            commands::vault::vault_generate,
             commands::editor::open_in_editor,
             commands::documents::create_document_folder,
             commands::documents::read_document_content,
@@ -65,7 +105,23 @@ pub fn run() {
             commands::documents::save_document_image,
             commands::documents::get_document_images_path,
             commands::documents::copy_document_images,
+            commands::migration::get_migration_report,
             commands::handover::export_project_handover,
+            commands::handover::read_handover_info,
+            commands::handover::import_project_handover,
+            commands::handover::decrypt_handover_package,
+            // Project commands
+            commands::projects::get_all_projects,
+            commands::projects::get_project,
+            commands::projects::add_project,
+            commands::projects::update_project,
+            commands::projects::delete_project,
+            commands::projects::update_project_access_time,
+            commands::project_settings::get_project_settings,
+            commands::project_settings::update_project_settings,
+            commands::project_discovery::discover_projects,
+            commands::project_analysis::analyze_project,
+            commands::project_analysis::get_project_analysis,
             // DocVault commands
             commands::docvault::get_docvault_path,
             commands::docvault::init_docvault,
@@ -95,180 +151,17 @@ pub fn run() {
         .plugin(tauri_plugin_os::init())
         // --- 托盘配置开始 ---
         .setup(|app| {
-            // --- 数据迁移逻辑开始 ---
-            // 迁移旧的 pro-manager 数据到新的 pomo 目录
             let handle = app.handle();
-            // 修正：发现应用实际使用 Roaming 目录，而不是 Local
-            // 我们需要获取 Roaming 目录下的数据路径
-            let app_local_data = app.path().app_local_data_dir().unwrap();
-            let target_dir = if let Some(parent) = app_local_data.parent() {
-                if let Some(app_data_root) = parent.parent() {
-                    app_data_root.join("Roaming").join("github.motoryang.pomo")
-                } else {
-                    app_local_data.clone()
-                }
-            } else {
-                app_local_data.clone()
-            };
-
-            println!("[Migration] Target Dir: {:?}", target_dir);
-            
-            // 获取旧的数据目录路径（包括可能被错误迁移到 Local 的数据）
-            if let Some(parent) = app_local_data.parent() {
-                let mut candidate_dirs = Vec::new();
-
-                // 0. Local/pomo (检查是否是上次错误迁移的数据)
-                // 如果这里有数据，且比目标目录的数据大/新，则应该恢复
-                candidate_dirs.push(app_local_data.clone());
-                
-                // 1. Local (优先检查，因为 Tauri 默认使用 Local，且用户之前的应用名为 promanager)
-                candidate_dirs.push(parent.join("github.motoryang.pro-manager"));
-                candidate_dirs.push(parent.join("github.motoryang.pm-app"));
-
-                // 2. Roaming (次要检查)
-                if let Some(app_data_root) = parent.parent() {
-                    let roaming = app_data_root.join("Roaming");
-                    candidate_dirs.push(roaming.join("github.motoryang.pro-manager"));
-                    candidate_dirs.push(roaming.join("github.motoryang.pm-app"));
-                }
-
-                'migration_loop: for old_dir in candidate_dirs {
-                    if !old_dir.exists() { continue; }
-                    // 跳过自身
-                    if old_dir == target_dir { continue; }
-                    
-                    // 检查数据库文件
-                    let db_candidates = vec!["pomo.db", "pm-app.db"];
-                    for db_name in db_candidates {
-                        let db_path = old_dir.join(db_name);
-                        if db_path.exists() {
-                            let metadata = std::fs::metadata(&db_path).unwrap();
-                            let size = metadata.len();
-                            // 忽略空数据库或极小的数据库（假设小于 20KB 为空库）
-                            if size < 20 * 1024 {
-                                println!("[Migration] Skipping small/empty DB in {:?} (size: {})", old_dir, size);
-                                continue;
-                            }
-
-                            println!("[Migration] Found valid database in {:?} (size: {}), starting migration...", old_dir, size);
-                            
-                            // 确保目标目录存在
-                            if !target_dir.exists() {
-                                let _ = std::fs::create_dir_all(&target_dir);
-                            }
 
-                            // 如果目标数据库已存在，检查是否需要覆盖
-                            let target_db = target_dir.join("pomo.db");
-                            if target_db.exists() {
-                                let target_meta = std::fs::metadata(&target_db).unwrap();
-                                if target_meta.len() < size {
-                                    println!("[Migration] Target DB exists but is smaller, overwriting...");
-                                    let _ = std::fs::remove_file(&target_db);
-                                    let _ = std::fs::remove_file(target_dir.join("pomo.db-shm"));
-                                    let _ = std::fs::remove_file(target_dir.join("pomo.db-wal"));
-                                } else {
-                                    println!("[Migration] Target DB exists and is larger/same, skipping migration from this source.");
-                                    continue;
-                                }
-                            }
-
-                            // 移动所有文件
-                            if let Ok(entries) = std::fs::read_dir(&old_dir) {
-                                for entry in entries.flatten() {
-                                    let path = entry.path();
-                                    let file_name_os = entry.file_name();
-                                    let file_name = file_name_os.to_string_lossy();
-                                    
-                                    // 处理文件名映射
-                                    let dest_name = if file_name == "pm-app.db" {
-                                        "pomo.db".to_string()
-                                    } else if file_name == "pm-app.db-wal" {
-                                        "pomo.db-wal".to_string()
-                                    } else if file_name == "pm-app.db-shm" {
-                                        "pomo.db-shm".to_string()
-                                    } else {
-                                        file_name.to_string()
-                                    };
-                                    
-                                    let dest = target_dir.join(&dest_name);
-                                    
-                                    // 移动或覆盖
-                                    println!("[Migration] Moving {:?} to {:?}", file_name, dest_name);
-                                    // 先尝试删除目标（如果存在）
-                                    if dest.exists() {
-                                        let _ = std::fs::remove_file(&dest); //如果是目录remove_file会失败，但不影响
-                                        let _ = std::fs::remove_dir_all(&dest);
-                                    }
-
-                                    if let Err(e) = std::fs::rename(&path, &dest) {
-                                        println!("[Migration] Failed to move file: {}", e);
-                                        // 跨分区或失败时尝试复制
-                                        if let Ok(_) = std::fs::copy(&path, &dest) {
-                                            // 复制成功后，如果是文件则删除源（如果是目录copy会失败，需递归，这里简化处理）
-                                            // 对于目录，std::fs::copy 不工作。
-                                            // 简单起见，我们假设主要是文件。对于 data 目录，需要 rename。
-                                            // rename 应该能处理目录移动（在同一卷上）。
-                                        }
-                                        // 再次尝试 rename (data 目录)
-                                        // 如果 rename 失败，通常是因为跨卷。
-                                        // 这里简化：如果 rename 失败且是目录，可能需要递归复制。
-                                        // 但通常 Local 和 Roaming 在同一卷 C:。
-                                    }
-                                }
-                            }
-                            
-                            // 尝试清理旧目录
-                            let _ = std::fs::remove_dir_all(&old_dir);
-                            
-                            println!("[Migration] Migration completed from {:?}", old_dir);
-                            // 成功迁移一次后退出，避免覆盖
-                            break 'migration_loop; 
-                        }
-                    }
-                }
-            }
-
-            if let Ok(app_data_dir) = app.path().app_data_dir() {
-                if let Some(parent) = app_data_dir.parent() {
-                    if let Some(root) = parent.parent() {
-                        let old_base = root.join("Roaming").join("github.motoryang.pro-manager").join("data");
-                        let new_base = app_data_dir.join("data");
-
-                        let old_docvaults = old_base.join("docvaults");
-                        let new_docvaults = new_base.join("docvaults");
-                        if old_docvaults.exists() {
-                            if let Ok(entries) = std::fs::read_dir(&old_docvaults) {
-                                for entry in entries.flatten() {
-                                    let src = entry.path();
-                                    if src.is_dir() {
-                                        let name = entry.file_name();
-                                        let dest = new_docvaults.join(&name);
-                                        let _ = copy_dir_recursive(&src, &dest);
-                                    }
-                                }
-                            }
-                        }
-
-                        let old_documents = old_base.join("documents");
-                        let new_documents = new_base.join("documents");
-                        if old_documents.exists() {
-                            if let Ok(entries) = std::fs::read_dir(&old_documents) {
-                                for entry in entries.flatten() {
-                                    let src = entry.path();
-                                    if src.is_dir() {
-                                        let name = entry.file_name();
-                                        let dest = new_documents.join(&name);
-                                        let _ = copy_dir_recursive(&src, &dest);
-                                    }
-                                }
-                            }
-                        }
-                    }
+            // 迁移旧版本（pro-manager / pm-app）留下的数据，必须在 SQL 插件打开数据库之前执行
+            match migrator::MigrationLayout::for_app(handle) {
+                Ok(layout) => {
+                    let report = migrator::migrate(&layout);
+                    println!("[Migration] {:?}", report.status);
                 }
+                Err(e) => println!("[Migration] {}", e),
             }
 
-            // --- 数据迁移逻辑结束 ---
-
             // 手动注册 SQL 插件
             let migrations = vec![
                 Migration {
@@ -307,14 +200,53 @@ pub fn run() {
                     sql: include_str!("../migrations/006_document_types.sql"),
                     kind: MigrationKind::Up,
                 },
+                Migration {
+                    version: 7,
+                    description: "add vault data key",
+                    sql: include_str!("../migrations/007_vault_data_key.sql"),
+                    kind: MigrationKind::Up,
+                },
+                Migration {
+                    version: 8,
+                    description: "add vault kdf params",
+                    sql: include_str!("../migrations/008_vault_kdf_params.sql"),
+                    kind: MigrationKind::Up,
+                },
+                Migration {
+                    version: 9,
+                    description: "add vault totp",
+                    sql: include_str!("../migrations/009_vault_totp.sql"),
+                    kind: MigrationKind::Up,
+                },
+                Migration {
+                    version: 10,
+                    description: "add vault ssh keys",
+                    sql: include_str!("../migrations/010_vault_ssh_keys.sql"),
+                    kind: MigrationKind::Up,
+                },
+                Migration {
+                    version: 11,
+                    description: "add vault entry history",
+                    sql: include_str!("../migrations/011_vault_entry_history.sql"),
+                    kind: MigrationKind::Up,
+                },
+                Migration {
+                    version: 12,
+                    description: "add project analysis cache",
+                    sql: include_str!("../migrations/012_project_analysis.sql"),
+                    kind: MigrationKind::Up,
+                },
             ];
 
             handle.plugin(
                 tauri_plugin_sql::Builder::new()
-                    .add_migrations("sqlite:pomo.db", migrations)
+                    .add_migrations(db::DB_URL, migrations)
                     .build(),
             )?;
 
+            // 空闲超时后自动锁定保险箱
+            commands::vault::spawn_idle_watcher(app.handle().clone());
+
             // 1. 创建菜单项
             let quit_i = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
             let show_i = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
@@ -331,13 +263,12 @@ pub fn run() {
                 .show_menu_on_left_click(false) // 点击左键是否直接弹出菜单
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "quit" => {
+                        // 退出前清除内存中的保险箱密钥
+                        commands::vault::lock_all_sessions(app);
                         app.exit(0);
                     }
                     "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
+                        show_main_window(app);
                     }
                     _ => {}
                 })
@@ -348,11 +279,7 @@ pub fn run() {
                         ..
                     } = event
                     {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
+                        show_main_window(tray.app_handle());
                     }
                 })
                 .build(app)?;
@@ -0,0 +1,549 @@
+// Migration of data left by older installs (pro-manager / pm-app, and pomo data that an
+// earlier version moved to the Local instead of the Roaming directory on Windows).
+//
+// Runs in `setup` before the SQL plugin opens the database. Sources are never deleted:
+// a migrated directory gets a marker file so later launches skip it, an existing target
+// database is moved to a backup directory before it is replaced, and every run that did
+// something is appended to `migration-log.json` for `get_migration_report`.
+
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::Connection;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::Manager;
+
+/// Identifiers the app was published under before it was renamed to pomo
+const LEGACY_IDENTIFIERS: &[&str] = &["github.motoryang.pro-manager", "github.motoryang.pm-app"];
+/// Database file names, newest name first
+const DB_NAMES: &[&str] = &["pomo.db", "pm-app.db"];
+const TARGET_DB: &str = "pomo.db";
+/// SQLite keeps uncheckpointed data in the WAL, so these always travel with the database
+const DB_SIDECARS: &[&str] = &["", "-wal", "-shm"];
+
+const MARKER_FILE: &str = ".pomo-migrated";
+const LOG_FILE: &str = "migration-log.json";
+const MAX_LOG_ENTRIES: usize = 20;
+
+/// Where legacy data is looked for and where it goes
+#[derive(Debug, Clone)]
+pub struct MigrationLayout {
+    /// Directory tauri-plugin-sql opens `pomo.db` in (the app config directory)
+    pub db_dir: PathBuf,
+    /// App data directory; files live under its `data` subdirectory
+    pub data_dir: PathBuf,
+    /// Directories of older installs, in tie-break order
+    pub legacy_dirs: Vec<PathBuf>,
+}
+
+impl MigrationLayout {
+    pub fn for_app(app: &tauri::AppHandle) -> Result<Self, String> {
+        let path = app.path();
+        let err = |e: tauri::Error| format!("Failed to resolve app directory: {}", e);
+        let db_dir = path.app_config_dir().map_err(err)?;
+        let data_dir = path.app_data_dir().map_err(err)?;
+        let local_dir = path.app_local_data_dir().map_err(err)?;
+
+        let mut legacy_dirs = vec![local_dir.clone()];
+        // Old identifiers next to each of the app directories (Local and Roaming on Windows)
+        for parent in [&local_dir, &data_dir, &db_dir].iter().filter_map(|d| d.parent()) {
+            for id in LEGACY_IDENTIFIERS {
+                legacy_dirs.push(parent.join(id));
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        legacy_dirs.retain(|d| *d != db_dir && seen.insert(d.clone()));
+
+        Ok(Self { db_dir, data_dir, legacy_dirs })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStatus {
+    /// No unmigrated legacy database with projects was found
+    NothingToMigrate,
+    Migrated,
+    /// The current database already has projects; the legacy data was left alone
+    Skipped,
+    /// The database was copied but some data files were not; the source is not
+    /// marked as migrated
+    Partial,
+    /// Copying the database failed and the previous target was restored
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub started_at: String,
+    pub status: MigrationStatus,
+    pub source: Option<String>,
+    pub target: String,
+    /// Directory the replaced target database was moved to
+    pub backup: Option<String>,
+    pub copied_files: usize,
+    /// Data files already present in the target, left untouched
+    pub skipped_files: usize,
+    pub steps: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+impl MigrationReport {
+    fn step(&mut self, message: String) {
+        println!("[Migration] {}", message);
+        self.steps.push(message);
+    }
+
+    fn error(&mut self, message: String) {
+        println!("[Migration] {}", message);
+        self.errors.push(message);
+    }
+}
+
+struct Candidate {
+    dir: PathBuf,
+    db: PathBuf,
+    projects: i64,
+    modified: SystemTime,
+}
+
+/// Number of rows in `projects`; `None` if the file is not a readable pomo database
+fn project_count(db: &Path) -> Option<i64> {
+    let options = SqliteConnectOptions::new().filename(db).read_only(true);
+    tauri::async_runtime::block_on(async {
+        let mut conn = SqliteConnection::connect_with(&options).await.ok()?;
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+            .fetch_one(&mut conn)
+            .await
+            .ok();
+        let _ = conn.close().await;
+        count
+    })
+}
+
+/// `pm-app.db-wal` -> `pomo.db-wal`
+fn sidecar(db: &Path, suffix: &str, name: &str) -> PathBuf {
+    db.with_file_name(format!("{}{}", name, suffix))
+}
+
+fn find_candidates(layout: &MigrationLayout, report: &mut MigrationReport) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    for dir in layout.legacy_dirs.iter().filter(|d| d.is_dir()) {
+        if dir.join(MARKER_FILE).exists() {
+            continue;
+        }
+        for name in DB_NAMES {
+            let db = dir.join(name);
+            if !db.is_file() {
+                continue;
+            }
+            match project_count(&db) {
+                Some(projects) => {
+                    report.step(format!("Found {} with {} projects", db.display(), projects));
+                    let modified = fs::metadata(&db)
+                        .and_then(|m| m.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+                    candidates.push(Candidate { dir: dir.clone(), db, projects, modified });
+                }
+                None => report.step(format!("Ignoring {}: not a readable pomo database", db.display())),
+            }
+        }
+    }
+    candidates
+}
+
+/// Move a file, copying when `rename` fails (e.g. across volumes)
+fn move_file(src: &Path, dst: &Path) -> io::Result<()> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    fs::copy(src, dst)?;
+    fs::remove_file(src)
+}
+
+/// Copy through a temporary name in the destination directory and rename it into place,
+/// so an interrupted copy never leaves a truncated file under the final name
+fn copy_file_atomic(src: &Path, dst: &Path) -> io::Result<()> {
+    let name = dst.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = dst.with_file_name(format!(".{}.migrating", name));
+    fs::copy(src, &tmp)?;
+    fs::rename(&tmp, dst).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Recursively copy `src` into `dst` without overwriting existing files.
+/// Copies work across volumes; `rename` is never used on directories.
+fn copy_dir_merge(src: &Path, dst: &Path, report: &mut MigrationReport) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            copy_dir_merge(&src_path, &dst_path, report)?;
+        } else if dst_path.exists() {
+            report.skipped_files += 1;
+        } else {
+            copy_file_atomic(&src_path, &dst_path)?;
+            report.copied_files += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Move the target database and its sidecars into a fresh backup directory
+fn backup_target(layout: &MigrationLayout, report: &mut MigrationReport) -> io::Result<Option<PathBuf>> {
+    let target = layout.db_dir.join(TARGET_DB);
+    let existing: Vec<PathBuf> = DB_SIDECARS
+        .iter()
+        .map(|s| sidecar(&target, s, TARGET_DB))
+        .filter(|p| p.exists())
+        .collect();
+    if existing.is_empty() {
+        return Ok(None);
+    }
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut backup = layout.db_dir.join("backups").join(format!("migration-{}", stamp));
+    let mut n = 1;
+    while backup.exists() {
+        backup = layout.db_dir.join("backups").join(format!("migration-{}-{}", stamp, n));
+        n += 1;
+    }
+    fs::create_dir_all(&backup)?;
+
+    for file in existing {
+        let dest = backup.join(file.file_name().unwrap_or_default());
+        move_file(&file, &dest)?;
+    }
+    report.step(format!("Backed up the existing database to {}", backup.display()));
+    Ok(Some(backup))
+}
+
+/// Put a backup made by `backup_target` back in place after a failed copy
+fn restore_backup(layout: &MigrationLayout, backup: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(backup)? {
+        let entry = entry?;
+        move_file(&entry.path(), &layout.db_dir.join(entry.file_name()))?;
+    }
+    fs::remove_dir(backup)
+}
+
+fn copy_database(source: &Path, layout: &MigrationLayout, report: &mut MigrationReport) -> io::Result<()> {
+    let source_name = source.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let target = layout.db_dir.join(TARGET_DB);
+
+    // A stale WAL next to the new database would be replayed into it
+    for suffix in DB_SIDECARS {
+        let dst = sidecar(&target, suffix, TARGET_DB);
+        if dst.exists() {
+            fs::remove_file(&dst)?;
+        }
+    }
+    for suffix in DB_SIDECARS {
+        let src = sidecar(source, suffix, &source_name);
+        if src.exists() {
+            copy_file_atomic(&src, &sidecar(&target, suffix, TARGET_DB))?;
+            report.copied_files += 1;
+        }
+    }
+    Ok(())
+}
+
+fn write_marker(dir: &Path, layout: &MigrationLayout, status: MigrationStatus) -> io::Result<()> {
+    let marker = serde_json::json!({
+        "status": status,
+        "target": layout.db_dir,
+        "at": chrono::Local::now().to_rfc3339(),
+    });
+    fs::write(dir.join(MARKER_FILE), marker.to_string())
+}
+
+/// Migrate the legacy database with the most projects into `layout.db_dir`, then merge
+/// its `data` directory. Safe to run on every launch.
+pub fn migrate(layout: &MigrationLayout) -> MigrationReport {
+    let mut report = MigrationReport {
+        started_at: chrono::Local::now().to_rfc3339(),
+        status: MigrationStatus::NothingToMigrate,
+        source: None,
+        target: layout.db_dir.join(TARGET_DB).to_string_lossy().into_owned(),
+        backup: None,
+        copied_files: 0,
+        skipped_files: 0,
+        steps: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // 以项目数量（而不是文件大小）判断哪个旧数据库有数据，数量相同时取最近修改的
+    let Some(source) = find_candidates(layout, &mut report)
+        .into_iter()
+        .filter(|c| c.projects > 0)
+        .max_by_key(|c| (c.projects, c.modified))
+    else {
+        return report;
+    };
+    report.source = Some(source.db.to_string_lossy().into_owned());
+
+    let target = layout.db_dir.join(TARGET_DB);
+    let target_projects = if target.exists() { project_count(&target) } else { None };
+    if let Some(count) = target_projects.filter(|c| *c > 0) {
+        report.step(format!(
+            "{} already has {} projects; keeping it and leaving {} in place",
+            target.display(),
+            count,
+            source.dir.display()
+        ));
+        report.status = MigrationStatus::Skipped;
+    } else {
+        migrate_from(&source, layout, &mut report);
+    }
+
+    if !matches!(report.status, MigrationStatus::Failed | MigrationStatus::Partial)
+        && let Err(e) = write_marker(&source.dir, layout, report.status)
+    {
+        report.error(format!("Failed to mark {} as migrated: {}", source.dir.display(), e));
+    }
+    if let Err(e) = append_log(&layout.db_dir, &report) {
+        report.error(format!("Failed to write the migration log: {}", e));
+    }
+    report
+}
+
+fn migrate_from(source: &Candidate, layout: &MigrationLayout, report: &mut MigrationReport) {
+    if let Err(e) = fs::create_dir_all(&layout.db_dir) {
+        report.error(format!("Failed to create {}: {}", layout.db_dir.display(), e));
+        report.status = MigrationStatus::Failed;
+        return;
+    }
+
+    let backup = match backup_target(layout, report) {
+        Ok(backup) => backup,
+        Err(e) => {
+            report.error(format!("Failed to back up the existing database: {}", e));
+            report.status = MigrationStatus::Failed;
+            return;
+        }
+    };
+    report.backup = backup.as_ref().map(|b| b.to_string_lossy().into_owned());
+
+    if let Err(e) = copy_database(&source.db, layout, report) {
+        report.error(format!("Failed to copy {}: {}", source.db.display(), e));
+        report.status = MigrationStatus::Failed;
+        if let Some(backup) = &backup {
+            match restore_backup(layout, backup) {
+                Ok(()) => report.step("Restored the previous database".to_string()),
+                Err(e) => report.error(format!(
+                    "Failed to restore the backup, it is kept in {}: {}",
+                    backup.display(),
+                    e
+                )),
+            }
+        }
+        return;
+    }
+    report.step(format!("Copied {} to {}", source.db.display(), report.target));
+
+    // 文档、文档库等文件按原有目录结构合并，已存在的文件不覆盖
+    let data = source.dir.join("data");
+    if data.is_dir() {
+        let dest = layout.data_dir.join("data");
+        let before = report.copied_files;
+        match copy_dir_merge(&data, &dest, report) {
+            Ok(()) => report.step(format!(
+                "Copied {} data files to {} ({} already present)",
+                report.copied_files - before,
+                dest.display(),
+                report.skipped_files
+            )),
+            Err(e) => {
+                report.error(format!("Failed to copy {}: {}", data.display(), e));
+                report.status = MigrationStatus::Partial;
+                return;
+            }
+        }
+    }
+    report.status = MigrationStatus::Migrated;
+}
+
+/// Reports of previous migrations, oldest first
+pub fn read_log(db_dir: &Path) -> Vec<MigrationReport> {
+    fs::read_to_string(db_dir.join(LOG_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn append_log(db_dir: &Path, report: &MigrationReport) -> io::Result<()> {
+    let mut log = read_log(db_dir);
+    log.push(report.clone());
+    let excess = log.len().saturating_sub(MAX_LOG_ENTRIES);
+    log.drain(..excess);
+
+    fs::create_dir_all(db_dir)?;
+    let json = serde_json::to_string_pretty(&log).map_err(io::Error::other)?;
+    fs::write(db_dir.join(LOG_FILE), json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+
+    fn create_db(path: &Path, projects: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        tauri::async_runtime::block_on(async {
+            let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+            sqlx::query("CREATE TABLE projects (id INTEGER PRIMARY KEY, name TEXT)")
+                .execute(&mut conn)
+                .await
+                .unwrap();
+            for i in 0..projects {
+                sqlx::query("INSERT INTO projects (name) VALUES (?)")
+                    .bind(format!("project {}", i))
+                    .execute(&mut conn)
+                    .await
+                    .unwrap();
+            }
+            conn.close().await.unwrap();
+        });
+    }
+
+    fn layout(root: &Path) -> MigrationLayout {
+        MigrationLayout {
+            db_dir: root.join("Roaming/github.motoryang.pomo"),
+            data_dir: root.join("Roaming/github.motoryang.pomo"),
+            legacy_dirs: vec![
+                root.join("Local/github.motoryang.pomo"),
+                root.join("Local/github.motoryang.pro-manager"),
+                root.join("Roaming/github.motoryang.pro-manager"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_migrates_database_with_most_projects() {
+        let root = temp_dir("migrator", "most");
+        let layout = layout(&root);
+        // A larger but empty database must not win over one with data
+        create_db(&layout.legacy_dirs[1].join("pm-app.db"), 0);
+        fs::write(layout.legacy_dirs[1].join("padding"), vec![0u8; 64 * 1024]).unwrap();
+        create_db(&layout.legacy_dirs[2].join("pm-app.db"), 3);
+        fs::create_dir_all(layout.legacy_dirs[2].join("data/documents/1")).unwrap();
+        fs::write(layout.legacy_dirs[2].join("data/documents/1/a.md"), "hello").unwrap();
+
+        let report = migrate(&layout);
+        assert_eq!(report.status, MigrationStatus::Migrated, "{:?}", report);
+        assert_eq!(project_count(&layout.db_dir.join(TARGET_DB)), Some(3));
+        assert_eq!(
+            fs::read_to_string(layout.data_dir.join("data/documents/1/a.md")).unwrap(),
+            "hello"
+        );
+        // The source is kept
+        assert!(layout.legacy_dirs[2].join("pm-app.db").exists());
+        assert_eq!(read_log(&layout.db_dir).len(), 1);
+
+        // Second run finds nothing and does not log again
+        let again = migrate(&layout);
+        assert_eq!(again.status, MigrationStatus::NothingToMigrate);
+        assert_eq!(read_log(&layout.db_dir).len(), 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_failed_data_copy_is_partial_and_leaves_source_unmarked() {
+        let root = temp_dir("migrator", "partial");
+        let layout = layout(&root);
+        create_db(&layout.legacy_dirs[0].join("pm-app.db"), 2);
+        fs::create_dir_all(layout.legacy_dirs[0].join("data/documents/1")).unwrap();
+        fs::write(layout.legacy_dirs[0].join("data/documents/1/a.md"), "hello").unwrap();
+        // 目标中同名路径是文件，合并目录时失败
+        fs::create_dir_all(layout.data_dir.join("data")).unwrap();
+        fs::write(layout.data_dir.join("data/documents"), "not a directory").unwrap();
+
+        let report = migrate(&layout);
+        assert_eq!(report.status, MigrationStatus::Partial, "{:?}", report);
+        assert_eq!(project_count(&layout.db_dir.join(TARGET_DB)), Some(2));
+        assert!(!report.errors.is_empty());
+        assert!(!layout.legacy_dirs[0].join(MARKER_FILE).exists());
+        assert_eq!(read_log(&layout.db_dir)[0].status, MigrationStatus::Partial);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_keeps_target_with_projects() {
+        let root = temp_dir("migrator", "keep");
+        let layout = layout(&root);
+        create_db(&layout.db_dir.join(TARGET_DB), 1);
+        create_db(&layout.legacy_dirs[0].join("pomo.db"), 5);
+
+        let report = migrate(&layout);
+        assert_eq!(report.status, MigrationStatus::Skipped);
+        assert_eq!(project_count(&layout.db_dir.join(TARGET_DB)), Some(1));
+        assert!(layout.legacy_dirs[0].join(MARKER_FILE).exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_backs_up_empty_target_before_overwriting() {
+        let root = temp_dir("migrator", "backup");
+        let layout = layout(&root);
+        create_db(&layout.db_dir.join(TARGET_DB), 0);
+        fs::write(layout.db_dir.join("pomo.db-wal"), "stale").unwrap();
+        create_db(&layout.legacy_dirs[0].join("pomo.db"), 2);
+
+        let report = migrate(&layout);
+        assert_eq!(report.status, MigrationStatus::Migrated, "{:?}", report);
+        let backup = PathBuf::from(report.backup.unwrap());
+        assert_eq!(project_count(&backup.join(TARGET_DB)), Some(0));
+        assert!(backup.join("pomo.db-wal").exists());
+        assert!(!layout.db_dir.join("pomo.db-wal").exists());
+        assert_eq!(project_count(&layout.db_dir.join(TARGET_DB)), Some(2));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_copy_dir_merge_does_not_overwrite() {
+        let root = temp_dir("migrator", "merge");
+        let src = root.join("src");
+        let dst = root.join("dst");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "new").unwrap();
+        fs::write(src.join("nested/b.txt"), "b").unwrap();
+        fs::write(dst.join("a.txt"), "existing").unwrap();
+
+        let mut report = MigrationReport {
+            started_at: String::new(),
+            status: MigrationStatus::NothingToMigrate,
+            source: None,
+            target: String::new(),
+            backup: None,
+            copied_files: 0,
+            skipped_files: 0,
+            steps: Vec::new(),
+            errors: Vec::new(),
+        };
+        copy_dir_merge(&src, &dst, &mut report).unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "existing");
+        assert_eq!(fs::read_to_string(dst.join("nested/b.txt")).unwrap(), "b");
+        assert_eq!((report.copied_files, report.skipped_files), (1, 1));
+        assert!(!dst.join("nested/.b.txt.migrating").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,60 @@
+// 单元测试共用的辅助函数：临时目录与 git 仓库夹具
+
+use git2::{Oid, Repository, Signature};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Empty `pomo-<prefix>-<name>-<pid>-<n>` directory under the system temp dir;
+/// tests remove it themselves when they finish
+pub(crate) fn temp_dir(prefix: &str, name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "pomo-{}-{}-{}-{}",
+        prefix,
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub(crate) fn signature() -> Signature<'static> {
+    Signature::now("Test", "test@example.com").unwrap()
+}
+
+/// Repository at `path` with a test identity and, if given, an `origin` at `remote`
+pub(crate) fn init_repo(path: &Path, remote: Option<&Path>) -> Repository {
+    let repo = Repository::init(path).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Test").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    if let Some(remote) = remote {
+        repo.remote("origin", &format!("file://{}", remote.display()))
+            .unwrap();
+    }
+    repo
+}
+
+/// Working repository `dir/work` with an `origin` pointing at the bare `dir/remote.git`
+pub(crate) fn init_with_remote(dir: &Path) -> Repository {
+    let bare = dir.join("remote.git");
+    Repository::init_bare(&bare).unwrap();
+    init_repo(&dir.join("work"), Some(&bare))
+}
+
+/// Write `name`, stage it and commit it on HEAD with `name` as the message
+pub(crate) fn commit_file(repo: &Repository, name: &str, content: &str) -> Oid {
+    fs::write(repo.workdir().unwrap().join(name), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(name)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = signature();
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, name, &tree, &parents)
+        .unwrap()
+}
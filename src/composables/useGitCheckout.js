@@ -0,0 +1,34 @@
+import { useGitStore } from '@/stores/git'
+import { useConfirm } from '@/composables/useConfirm.js'
+
+// 切换分支：被本地改动阻止时询问是否自动暂存，并提示冲突文件
+export function useGitCheckout() {
+    const gitStore = useGitStore()
+    const confirmDialog = useConfirm()
+
+    const listFiles = (paths) => {
+        const shown = paths.slice(0, 10).join('\n')
+        return paths.length > 10 ? `${shown}\n…（共 ${paths.length} 个文件）` : shown
+    }
+
+    return async function checkout(branchName) {
+        let result = await gitStore.checkoutBranch(branchName, 'carry')
+
+        if (result.status === 'blocked') {
+            const stash = await confirmDialog({
+                title: '无法直接切换分支',
+                message: `以下文件的本地改动会被 ${branchName} 覆盖：\n${listFiles(result.conflicts)}\n\n是否先暂存改动，切换后再恢复？`,
+                confirmText: '暂存并切换'
+            })
+            if (!stash) return result
+            result = await gitStore.checkoutBranch(branchName, 'auto_stash')
+        }
+
+        if (result.status === 'blocked') {
+            alert(`无法切换到 ${branchName}，以下文件冲突：\n${listFiles(result.conflicts)}`)
+        } else if (result.status === 'stash_conflicts') {
+            alert(`已切换到 ${branchName}，但恢复暂存的改动时产生冲突：\n${listFiles(result.conflicts)}\n\n改动仍保存在 stash 中，请解决冲突后手动删除。`)
+        }
+        return result
+    }
+}
@@ -2,6 +2,7 @@ import { ref, computed } from 'vue'
 import { defineStore } from 'pinia'
 import { readDir, stat } from '@tauri-apps/plugin-fs'
 import { join } from '@tauri-apps/api/path'
+import { invoke } from '@tauri-apps/api/core'
 
 export const useFilesStore = defineStore('files', () => {
   const loading = ref(false)
@@ -9,6 +10,9 @@ export const useFilesStore = defineStore('files', () => {
   const fileStats = ref([])
   const scanProgress = ref(0)
   const totalFiles = ref(0)
+  // Rust 端 analyze_project 的结果：代码行数、构建工具、框架和依赖
+  const projectSummary = ref(null)
+  const summaryLoading = ref(false)
 
   // 文件类型映射
   const fileTypeMap = {
@@ -215,12 +219,29 @@ export const useFilesStore = defineStore('files', () => {
     }
   }
 
+  // 先显示缓存的分析结果，再增量刷新
+  async function loadProjectSummary(projectPath) {
+    if (!projectPath) return
+
+    summaryLoading.value = true
+    try {
+      const cached = await invoke('get_project_analysis', { path: projectPath })
+      if (cached) projectSummary.value = cached
+      projectSummary.value = await invoke('analyze_project', { path: projectPath })
+    } catch (e) {
+      console.error('Failed to analyze project summary:', e)
+    } finally {
+      summaryLoading.value = false
+    }
+  }
+
   // 清除统计数据
   function clearStats() {
     fileStats.value = []
     scanProgress.value = 0
     totalFiles.value = 0
     error.value = null
+    projectSummary.value = null
   }
 
   return {
@@ -230,6 +251,8 @@ export const useFilesStore = defineStore('files', () => {
     fileStats,
     scanProgress,
     totalFiles,
+    projectSummary,
+    summaryLoading,
 
     // Computed
     fileTypeStats,
@@ -238,6 +261,7 @@ export const useFilesStore = defineStore('files', () => {
 
     // Actions
     analyzeProject,
+    loadProjectSummary,
     clearStats,
     formatSize,
   }
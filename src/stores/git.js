@@ -1,6 +1,7 @@
 import { ref, computed } from 'vue'
 import { defineStore } from 'pinia'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 
 export const useGitStore = defineStore('git', () => {
   const loading = ref(false)
@@ -11,10 +12,22 @@ export const useGitStore = defineStore('git', () => {
   const currentCommit = ref(null)
   const status = ref(null)
   const remotes = ref([])
+  const stashes = ref([])
+  // 正在进行的远程操作：'fetch' | 'pull' | 'push' | null
+  const remoteAction = ref(null)
+  const remoteProgress = ref({ progress: 0, message: '', failed: false })
 
   // 当前项目路径
   const projectPath = ref('')
 
+  // fetch / pull / push 的传输进度由 Rust 推送
+  listen('git-progress', (event) => {
+    const { progress, message, failed } = event.payload
+    remoteProgress.value = { progress, message, failed }
+    // 失败的终止事件携带错误信息，不能当作完成
+    if (failed) error.value = message
+  })
+
   // 本地分支列表
   const localBranches = computed(() => {
     return branches.value.filter(b => !b.is_remote)
@@ -106,8 +119,9 @@ export const useGitStore = defineStore('git', () => {
     }
   }
 
-  // 切换分支
-  async function checkoutBranch(branchName) {
+  // 切换分支；mode 为 'strict' | 'carry' | 'auto_stash'
+  // 返回 { branch, status, conflicts, stashed }，status 为 'blocked' 时没有切换
+  async function checkoutBranch(branchName, mode = 'strict') {
     if (!projectPath.value) {
       throw new Error('No project path set')
     }
@@ -116,18 +130,24 @@ export const useGitStore = defineStore('git', () => {
     error.value = null
 
     try {
-      await invoke('git_checkout_branch', {
+      const result = await invoke('git_checkout_branch', {
         path: projectPath.value,
-        branchName
+        branchName,
+        mode
       })
 
-      // 刷新数据
-      await Promise.all([
-        getCurrentBranch(),
-        getCommitHistory()
-      ])
+      // 刷新数据（检出远程分支时会新建本地分支，自动暂存可能留下 stash）
+      if (result.status !== 'blocked') {
+        await Promise.all([
+          getCurrentBranch(),
+          getAllBranches(),
+          getCommitHistory(),
+          getStatus(),
+          getStashes()
+        ])
+      }
 
-      return true
+      return result
     } catch (e) {
       error.value = e || '切换分支失败'
       throw e
@@ -136,6 +156,51 @@ export const useGitStore = defineStore('git', () => {
     }
   }
 
+  // 分支操作完成后刷新分支列表和当前分支
+  async function runBranchAction(command, args, fallback) {
+    if (!projectPath.value) {
+      throw new Error('No project path set')
+    }
+
+    try {
+      const result = await invoke(command, { path: projectPath.value, ...args })
+      await Promise.all([
+        getCurrentBranch(),
+        getAllBranches(),
+        getCommitHistory(),
+        getStatus()
+      ])
+      return result
+    } catch (e) {
+      console.error(`${fallback}:`, e)
+      throw e
+    }
+  }
+
+  // 创建分支，startPoint 可以是提交哈希、分支或标签
+  async function createBranch(name, { startPoint = null, checkout = false } = {}) {
+    return runBranchAction('git_create_branch', { name, startPoint, checkout }, 'Failed to create branch')
+  }
+
+  // 删除本地分支；未合并的分支需要 force
+  async function deleteBranch(name, force = false) {
+    return runBranchAction('git_delete_branch', { name, force }, 'Failed to delete branch')
+  }
+
+  async function renameBranch(oldName, newName, force = false) {
+    return runBranchAction('git_rename_branch', { oldName, newName, force }, 'Failed to rename branch')
+  }
+
+  // upstream 为 null 时取消跟踪
+  async function setUpstream(name, upstream) {
+    return runBranchAction('git_set_upstream', { name, upstream }, 'Failed to set upstream')
+  }
+
+  // 将远程分支检出为本地跟踪分支
+  async function checkoutRemoteBranch(remoteBranch, localName = null, mode = 'strict') {
+    return runBranchAction('git_checkout_remote_branch', { remoteBranch, localName, mode }, 'Failed to checkout remote branch')
+  }
+
   // 获取仓库状态
   async function getStatus() {
     if (!projectPath.value) return null
@@ -190,7 +255,8 @@ export const useGitStore = defineStore('git', () => {
         getAllBranches(),
         getCommitHistory(),
         getStatus(),
-        getRemotes()
+        getRemotes(),
+        getStashes()
       ])
 
       return true
@@ -211,6 +277,7 @@ export const useGitStore = defineStore('git', () => {
     currentCommit.value = null
     status.value = null
     remotes.value = []
+    stashes.value = []
     error.value = null
     projectPath.value = ''
   }
@@ -289,6 +356,136 @@ export const useGitStore = defineStore('git', () => {
     }
   }
 
+  // 获取 stash 列表
+  async function getStashes() {
+    if (!projectPath.value) return []
+
+    try {
+      const list = await invoke('git_stash_list', { path: projectPath.value })
+      stashes.value = list
+      return list
+    } catch (e) {
+      console.error('Failed to list stashes:', e)
+      stashes.value = []
+      return []
+    }
+  }
+
+  // 贮藏工作区更改
+  async function stashSave({ message = null, includeUntracked = false, keepIndex = false } = {}) {
+    if (!projectPath.value) {
+      throw new Error('No project path set')
+    }
+
+    try {
+      const entry = await invoke('git_stash_save', {
+        path: projectPath.value,
+        message,
+        includeUntracked,
+        keepIndex
+      })
+      await Promise.all([getStatus(), getStashes()])
+      return entry
+    } catch (e) {
+      console.error('Failed to save stash:', e)
+      throw e
+    }
+  }
+
+  // 应用 stash；pop 为 true 时无冲突则删除该 stash
+  // 返回 { index, conflicts, dropped }
+  async function stashApply(index, { pop = false, reinstateIndex = false } = {}) {
+    if (!projectPath.value) {
+      throw new Error('No project path set')
+    }
+
+    try {
+      const result = await invoke(pop ? 'git_stash_pop' : 'git_stash_apply', {
+        path: projectPath.value,
+        index,
+        reinstateIndex
+      })
+      await Promise.all([getStatus(), getStashes()])
+      return result
+    } catch (e) {
+      console.error('Failed to apply stash:', e)
+      throw e
+    }
+  }
+
+  async function stashDrop(index) {
+    if (!projectPath.value) {
+      throw new Error('No project path set')
+    }
+
+    try {
+      await invoke('git_stash_drop', { path: projectPath.value, index })
+      await getStashes()
+      return true
+    } catch (e) {
+      console.error('Failed to drop stash:', e)
+      throw e
+    }
+  }
+
+  // 像提交一样在详情面板中查看 stash
+  async function showStash(index) {
+    if (!projectPath.value) return null
+
+    try {
+      const detail = await invoke('git_stash_show', { path: projectPath.value, index })
+      currentCommit.value = detail
+      return detail
+    } catch (e) {
+      console.error('Failed to show stash:', e)
+      throw e
+    }
+  }
+
+  // 执行远程操作，完成后刷新分支、提交和状态
+  async function runRemoteAction(action, command, args) {
+    if (!projectPath.value) {
+      throw new Error('No project path set')
+    }
+    if (remoteAction.value) {
+      throw new Error('已有远程操作正在进行')
+    }
+
+    remoteAction.value = action
+    remoteProgress.value = { progress: 0, message: '', failed: false }
+    try {
+      const result = await invoke(command, { path: projectPath.value, ...args })
+      await Promise.all([
+        getCurrentBranch(),
+        getAllBranches(),
+        getCommitHistory(),
+        getStatus()
+      ])
+      return result
+    } catch (e) {
+      console.error(`Failed to ${action}:`, e)
+      throw e
+    } finally {
+      remoteAction.value = null
+    }
+  }
+
+  // 获取远程更新
+  async function fetchRemote(remote = null) {
+    return runRemoteAction('fetch', 'git_fetch', { remote })
+  }
+
+  // 拉取：mode 为 'fast_forward' 或 'rebase'
+  async function pull(mode = 'fast_forward', remote = null) {
+    return runRemoteAction('pull', 'git_pull', { mode, remote })
+  }
+
+  // 推送当前分支；没有上游时自动设置
+  async function push({ remote = null, branch = null, force = false } = {}) {
+    const setUpstream = status.value?.head_state === 'no_upstream'
+    return runRemoteAction('push', 'git_push', { remote, branch, setUpstream, force })
+  }
+
   // 格式化日期
   function formatDate(timestamp) {
     const date = new Date(timestamp * 1000)
@@ -331,6 +528,9 @@ export const useGitStore = defineStore('git', () => {
     currentCommit,
     status,
     remotes,
+    stashes,
+    remoteAction,
+    remoteProgress,
     projectPath,
 
     // Computed
@@ -344,11 +544,24 @@ export const useGitStore = defineStore('git', () => {
     getCommitHistory,
     getCommitDetail,
     checkoutBranch,
+    createBranch,
+    deleteBranch,
+    renameBranch,
+    setUpstream,
+    checkoutRemoteBranch,
     getStatus,
     getRemotes,
     stageFiles,
     unstageFiles,
     commit,
+    getStashes,
+    stashSave,
+    stashApply,
+    stashDrop,
+    showStash,
+    fetchRemote,
+    pull,
+    push,
     clearData,
     formatDate,
   }
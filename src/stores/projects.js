@@ -1,7 +1,7 @@
 // Projects Store - Manage project list and active project
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
-import Database from '@tauri-apps/plugin-sql'
+import { invoke } from '@tauri-apps/api/core'
 
 export const useProjectsStore = defineStore('projects', () => {
   // State
@@ -19,13 +19,9 @@ export const useProjectsStore = defineStore('projects', () => {
 
   const activeProjectName = computed(() => activeProject.value?.name || '未选择项目')
 
-  // Database connection
-  let db = null
-  async function getDb() {
-    if (!db) {
-      db = await Database.load('sqlite:pomo.db')
-    }
-    return db
+  // Rust commands reject with a plain string
+  function toError(e) {
+    return e instanceof Error ? e : new Error(String(e))
   }
 
   // Actions
@@ -33,8 +29,7 @@ export const useProjectsStore = defineStore('projects', () => {
     loading.value = true
     error.value = null
     try {
-      const database = await getDb()
-      const result = await database.select('SELECT * FROM projects ORDER BY last_accessed DESC')
+      const result = await invoke('get_all_projects')
 
       // 应用自定义排序
       const savedOrder = localStorage.getItem('pm-app-projects-order')
@@ -57,7 +52,7 @@ export const useProjectsStore = defineStore('projects', () => {
 
       projects.value = result
     } catch (e) {
-      error.value = e.message || 'Failed to load projects'
+      error.value = toError(e).message || 'Failed to load projects'
       console.error('Failed to load projects:', e)
     } finally {
       loading.value = false
@@ -82,21 +77,23 @@ export const useProjectsStore = defineStore('projects', () => {
     loading.value = true
     error.value = null
     try {
-      const database = await getDb()
-      const color = projectData.color || '#FF6B9D'
-      const description = projectData.description || ''
-
-      const result = await database.execute(
-        'INSERT INTO projects (name, path, description, color) VALUES (?, ?, ?, ?)',
-        [projectData.name, projectData.path, description, color]
-      )
+      // 路径存在性和唯一性由 Rust 端校验
+      const project = await invoke('add_project', {
+        project: {
+          name: projectData.name,
+          path: projectData.path,
+          description: projectData.description || null,
+          color: projectData.color || null
+        }
+      })
 
-      await loadProjects() // Reload to get the full project data
-      return result.lastInsertId
+      await loadProjects()
+      return project.id
     } catch (e) {
-      error.value = e.message || 'Failed to add project'
+      const err = toError(e)
+      error.value = err.message || 'Failed to add project'
       console.error('Failed to add project:', e)
-      throw e
+      throw err
     } finally {
       loading.value = false
     }
@@ -106,16 +103,20 @@ export const useProjectsStore = defineStore('projects', () => {
     loading.value = true
     error.value = null
     try {
-      const database = await getDb()
-      await database.execute('DELETE FROM projects WHERE id = ?', [id])
+      // 同时清理文档库目录和保险箱数据
+      const cleanup = await invoke('delete_project', { id })
+      if (cleanup.failed.length > 0) {
+        console.warn('Failed to remove project files:', cleanup.failed)
+      }
       projects.value = projects.value.filter(p => p.id !== id)
       if (activeProjectId.value === id) {
         activeProjectId.value = null
       }
     } catch (e) {
-      error.value = e.message || 'Failed to delete project'
+      const err = toError(e)
+      error.value = err.message || 'Failed to delete project'
       console.error('Failed to delete project:', e)
-      throw e
+      throw err
     } finally {
       loading.value = false
     }
@@ -125,44 +126,28 @@ export const useProjectsStore = defineStore('projects', () => {
     loading.value = true
     error.value = null
     try {
-      const database = await getDb()
-      const fields = []
-      const values = []
-
-      if (projectData.name !== undefined) {
-        fields.push('name = ?')
-        values.push(projectData.name)
-      }
-      if (projectData.path !== undefined) {
-        fields.push('path = ?')
-        values.push(projectData.path)
-      }
-      if (projectData.description !== undefined) {
-        fields.push('description = ?')
-        values.push(projectData.description)
-      }
-      if (projectData.color !== undefined) {
-        fields.push('color = ?')
-        values.push(projectData.color)
-      }
-
-      if (fields.length > 0) {
-        values.push(id)
-        await database.execute(
-          `UPDATE projects SET ${fields.join(', ')} WHERE id = ?`,
-          values
-        )
-
-        // 更新本地数据
-        const project = projects.value.find(p => p.id === id)
-        if (project) {
-          Object.assign(project, projectData)
+      const updated = await invoke('update_project', {
+        id,
+        update: {
+          name: projectData.name ?? null,
+          path: projectData.path ?? null,
+          // 传入 null 表示清空描述，未传入则保持不变
+          description: 'description' in projectData ? (projectData.description || '') : null,
+          color: projectData.color ?? null,
+          settings: projectData.settings ?? null
         }
+      })
+
+      // 更新本地数据
+      const project = projects.value.find(p => p.id === id)
+      if (project) {
+        Object.assign(project, updated)
       }
     } catch (e) {
-      error.value = e.message || 'Failed to update project'
+      const err = toError(e)
+      error.value = err.message || 'Failed to update project'
       console.error('Failed to update project:', e)
-      throw e
+      throw err
     } finally {
       loading.value = false
     }
@@ -172,11 +157,7 @@ export const useProjectsStore = defineStore('projects', () => {
     activeProjectId.value = id
     if (id) {
       try {
-        const database = await getDb()
-        await database.execute(
-            'UPDATE projects SET last_accessed = CURRENT_TIMESTAMP WHERE id = ?',
-            [id]
-        )
+        await invoke('update_project_access_time', { id })
         // 只更新当前项目的 last_accessed，不重新排序整个列表
         const project = projects.value.find(p => p.id === id)
         if (project) {
@@ -188,6 +169,33 @@ export const useProjectsStore = defineStore('projects', () => {
     }
   }
 
+  // 扫描目录查找项目，已添加的项目带有 registered_id
+  async function discoverProjects(roots, maxDepth = 3) {
+    try {
+      return await invoke('discover_projects', { roots, maxDepth })
+    } catch (e) {
+      throw toError(e)
+    }
+  }
+
+  async function getProjectSettings(id) {
+    return await invoke('get_project_settings', { projectId: id })
+  }
+
+  // 设置不合法时 Rust 端会直接拒绝，不会写入
+  async function updateProjectSettings(id, settings) {
+    try {
+      const saved = await invoke('update_project_settings', { projectId: id, settings })
+      const project = projects.value.find(p => p.id === id)
+      if (project) {
+        project.settings = saved
+      }
+      return saved
+    } catch (e) {
+      throw toError(e)
+    }
+  }
+
   function clearError() {
     error.value = null
   }
@@ -211,6 +219,9 @@ export const useProjectsStore = defineStore('projects', () => {
     updateProject,
     reorderProjects,
     setActiveProject,
+    discoverProjects,
+    getProjectSettings,
+    updateProjectSettings,
     clearError
   }
 })
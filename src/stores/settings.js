@@ -27,6 +27,7 @@ export const useSettingsStore = defineStore('settings', () => {
   const exportProjectBehavior = ref('ignore-plugin-directory') // 'ignore-plugin-directory' | 'all-directory'
   const themeColor = ref('pink') // 默认主题色
   const imageAttachmentPath = ref('.attachments') // 图片保存位置，默认 .attachments
+  const vaultAutoLockMinutes = ref(5) // 保险箱空闲自动锁定时间（分钟）
   const loading = ref(false)
   const error = ref(null)
 
@@ -77,6 +78,12 @@ export const useSettingsStore = defineStore('settings', () => {
       )
       imageAttachmentPath.value = imagePathResult.length > 0 ? imagePathResult[0].value : '.attachments'
 
+      // vaultAutoLockMinutes
+      const autoLockResult = await db.value.select(
+          "SELECT value FROM app_settings WHERE key = 'vault_auto_lock_minutes'"
+      )
+      vaultAutoLockMinutes.value = autoLockResult.length > 0 ? Number(autoLockResult[0].value) || 5 : 5
+
     } catch (err) {
       error.value = err.message
       console.error('Failed to load settings:', err)
@@ -180,6 +187,21 @@ export const useSettingsStore = defineStore('settings', () => {
     }
   }
 
+  // Save vaultAutoLockMinutes
+  const saveVaultAutoLockMinutes = async (minutes) => {
+    try {
+      loading.value = true
+      error.value = null
+      await saveSetting('vault_auto_lock_minutes', String(minutes))
+      vaultAutoLockMinutes.value = minutes
+    } catch (err) {
+      error.value = err.message
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
   return {
     // State
     editorPath,
@@ -187,6 +209,7 @@ export const useSettingsStore = defineStore('settings', () => {
     exportProjectBehavior,
     themeColor,
     imageAttachmentPath,
+    vaultAutoLockMinutes,
     loading,
     error,
 
@@ -197,6 +220,7 @@ export const useSettingsStore = defineStore('settings', () => {
     saveExportProjectBehavior,
     saveThemeColor,
     saveImageAttachmentPath,
+    saveVaultAutoLockMinutes,
     applyThemeColor
   }
 })
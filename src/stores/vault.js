@@ -2,16 +2,25 @@ import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import Database from '@tauri-apps/plugin-sql'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { useSettingsStore } from './settings'
 
 export const useVaultStore = defineStore('vault', () => {
   const db = ref(null)
   const isUnlocked = ref(false)
-  const masterPassword = ref('')
   const entries = ref([])
   const selectedEntry = ref(null)
   const loading = ref(false)
   const error = ref(null)
   const currentProjectId = ref(null)
+  const sessionHandle = ref(null)
+
+  // Rust locks idle sessions on its own and reports it through this event
+  listen('vault-locked', (event) => {
+    if (event.payload === currentProjectId.value) {
+      resetState()
+    }
+  })
 
   // Initialize database
   const initDB = async () => {
@@ -82,9 +91,9 @@ export const useVaultStore = defineStore('vault', () => {
 
       await initDB()
 
-      // Get stored password hash
+      // Get stored password hash and wrapped data key
       const result = await db.value.select(
-        'SELECT password_hash FROM vault_master WHERE project_id = $1',
+        'SELECT password_hash, wrapped_key, kdf_params FROM vault_master WHERE project_id = $1',
         [projectId]
       )
 
@@ -92,21 +101,42 @@ export const useVaultStore = defineStore('vault', () => {
         throw new Error('保险箱未初始化')
       }
 
-      const passwordHash = result[0].password_hash
+      const { password_hash: passwordHash, wrapped_key: wrappedKey, kdf_params: kdfParams } = result[0]
+
+      // Verify password and unwrap the data key in Rust; the key stays in Rust memory
+      let unlockResult
+      try {
+        const settingsStore = useSettingsStore()
+        unlockResult = await invoke('vault_unlock', {
+          projectId,
+          masterPassword: password,
+          passwordHash,
+          wrappedKey: wrappedKey || null,
+          idleTimeoutSecs: settingsStore.vaultAutoLockMinutes * 60,
+          kdfParams: kdfParams ? JSON.parse(kdfParams) : null
+        })
+      } catch {
+        throw new Error('密码错误')
+      }
 
-      // Verify password using Rust command
-      const isValid = await invoke('vault_verify_master', {
-        projectId,
-        masterPassword: password,
-        passwordHash
-      })
+      // First unlock after upgrading or changing KDF params: persist the new data key wrap
+      if (unlockResult.new_wrapped_key) {
+        await db.value.execute(
+          'UPDATE vault_master SET wrapped_key = $1 WHERE project_id = $2',
+          [unlockResult.new_wrapped_key, projectId]
+        )
+      }
 
-      if (!isValid) {
-        throw new Error('密码错误')
+      // Master hash re-created with the configured KDF params
+      if (unlockResult.new_password_hash) {
+        const [newHash, newSalt] = unlockResult.new_password_hash
+        await db.value.execute(
+          'UPDATE vault_master SET password_hash = $1, salt = $2 WHERE project_id = $3',
+          [newHash, newSalt, projectId]
+        )
       }
 
-      // Store master password in memory for encryption/decryption
-      masterPassword.value = password
+      sessionHandle.value = unlockResult.session
       currentProjectId.value = projectId
       isUnlocked.value = true
       // Load entries
@@ -123,8 +153,15 @@ export const useVaultStore = defineStore('vault', () => {
 
   // Lock vault
   const lockVault = () => {
+    if (sessionHandle.value) {
+      invoke('vault_lock', { session: sessionHandle.value }).catch(() => {})
+    }
+    resetState()
+  }
+
+  const resetState = () => {
+    sessionHandle.value = null
     isUnlocked.value = false
-    masterPassword.value = ''
     currentProjectId.value = null
     entries.value = []
     selectedEntry.value = null
@@ -140,7 +177,8 @@ export const useVaultStore = defineStore('vault', () => {
 
       const result = await db.value.select(
         `SELECT id, project_id, title, param_key, encrypted_value, encrypted_notes,
-         url, category, salt, nonce, created_at, updated_at
+         url, category, salt, nonce, encrypted_totp, ssh_public_key, ssh_fingerprint,
+         created_at, updated_at
          FROM vault_entries WHERE project_id = $1 ORDER BY created_at DESC`,
         [projectId]
       )
@@ -166,21 +204,28 @@ export const useVaultStore = defineStore('vault', () => {
 
       await initDB()
 
-      // Encrypt paramValue and notes using Rust command
+      // Encrypt paramValue and notes with the unlocked data key
       const [encryptedValue, nonce, encryptedNotes, , salt] = await invoke(
-        'vault_encrypt_entry',
+        'vault_session_encrypt_entry',
         {
+          session: sessionHandle.value,
           paramValue: entryData.paramValue,
-          notes: entryData.notes || null,
-          masterPassword: masterPassword.value
+          notes: entryData.notes || null
         }
       )
 
+      const encryptedTotp = entryData.totp
+        ? await invoke('vault_session_encrypt_totp', {
+            session: sessionHandle.value,
+            totp: entryData.totp
+          })
+        : null
+
       // Insert into database
       await db.value.execute(
         `INSERT INTO vault_entries
-         (project_id, title, param_key, encrypted_value, encrypted_notes, url, category, salt, nonce)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)`,
+         (project_id, title, param_key, encrypted_value, encrypted_notes, url, category, salt, nonce, encrypted_totp)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)`,
         [
           currentProjectId.value,
           entryData.title,
@@ -190,7 +235,8 @@ export const useVaultStore = defineStore('vault', () => {
           entryData.url || null,
           entryData.category || 'general',
           salt,
-          nonce
+          nonce,
+          encryptedTotp
         ]
       )
 
@@ -218,34 +264,21 @@ export const useVaultStore = defineStore('vault', () => {
 
       await initDB()
 
-      // Encrypt paramValue and notes using Rust command
-      const [encryptedValue, nonce, encryptedNotes, , salt] = await invoke(
-        'vault_encrypt_entry',
-        {
-          paramValue: entryData.paramValue,
+      // Rust re-encrypts the entry, archives the previous version and saves both
+      // in one transaction; totp: undefined keeps the stored secret, '' removes it
+      await invoke('vault_update_entry', {
+        session: sessionHandle.value,
+        entryId,
+        update: {
+          title: entryData.title,
+          param_key: entryData.paramKey || null,
+          param_value: entryData.paramValue,
           notes: entryData.notes || null,
-          masterPassword: masterPassword.value
+          url: entryData.url || null,
+          category: entryData.category || 'general',
+          totp: entryData.totp === undefined ? null : entryData.totp
         }
-      )
-
-      // Update in database
-      await db.value.execute(
-        `UPDATE vault_entries
-         SET title = $1, param_key = $2, encrypted_value = $3, encrypted_notes = $4,
-             url = $5, category = $6, salt = $7, nonce = $8, updated_at = CURRENT_TIMESTAMP
-         WHERE id = $9`,
-        [
-          entryData.title,
-          entryData.paramKey || null,
-          encryptedValue,
-          encryptedNotes || null,
-          entryData.url || null,
-          entryData.category || 'general',
-          salt,
-          nonce,
-          entryId
-        ]
-      )
+      })
 
       // Reload entries
       await loadEntries(currentProjectId.value)
@@ -265,8 +298,20 @@ export const useVaultStore = defineStore('vault', () => {
       loading.value = true
       error.value = null
 
+      if (!isUnlocked.value) {
+        throw new Error('保险箱已锁定')
+      }
+
       await initDB()
 
+      // Remove the entry's encrypted attachments first; deleting them needs the session
+      await invoke('vault_delete_attachment', {
+        session: sessionHandle.value,
+        entryId,
+        attachmentId: null
+      })
+
+      await db.value.execute('DELETE FROM vault_entry_history WHERE entry_id = $1', [entryId])
       await db.value.execute('DELETE FROM vault_entries WHERE id = $1', [entryId])
 
       // Reload entries
@@ -288,13 +333,13 @@ export const useVaultStore = defineStore('vault', () => {
         throw new Error('保险箱已锁定')
       }
 
-      // Decrypt using Rust command
-      const [decryptedValue, notes] = await invoke('vault_decrypt_entry', {
+      // Decrypt using the unlocked vault session
+      const [decryptedValue, notes] = await invoke('vault_session_decrypt_entry', {
+        session: sessionHandle.value,
         encryptedValue: entry.encrypted_value,
         nonce: entry.nonce,
         encryptedNotes: entry.encrypted_notes || null,
-        salt: entry.salt,
-        masterPassword: masterPassword.value
+        salt: entry.salt
       })
 
       return {
@@ -315,6 +360,280 @@ export const useVaultStore = defineStore('vault', () => {
     }
   }
 
+  // Audit strength, reuse and rotation age of the current project's entries.
+  // Entries of other projects are compared too when their vault is unlocked.
+  const auditVault = async (password, rotationDays = 90) => {
+    try {
+      loading.value = true
+      error.value = null
+
+      await initDB()
+
+      const projectId = currentProjectId.value
+      const master = await db.value.select(
+        'SELECT password_hash, wrapped_key FROM vault_master WHERE project_id = $1',
+        [projectId]
+      )
+      if (master.length === 0) {
+        throw new Error('保险箱未初始化')
+      }
+
+      const otherRows = await db.value.select(
+        `SELECT e.id, e.project_id, e.title, e.param_key, e.encrypted_value, e.encrypted_notes,
+         e.url, e.category, e.salt, e.nonce, e.created_at, e.updated_at, p.name AS project_name
+         FROM vault_entries e JOIN projects p ON p.id = e.project_id
+         WHERE e.project_id != $1`,
+        [projectId]
+      )
+      const otherProjects = {}
+      otherRows.forEach(({ project_name, ...entry }) => {
+        if (!otherProjects[entry.project_id]) {
+          otherProjects[entry.project_id] = {
+            project_id: entry.project_id,
+            project_name,
+            entries: []
+          }
+        }
+        otherProjects[entry.project_id].entries.push(entry)
+      })
+
+      return await invoke('vault_audit', {
+        projectId,
+        masterPassword: password,
+        passwordHash: master[0].password_hash,
+        wrappedKey: master[0].wrapped_key || null,
+        entries: entries.value,
+        otherProjects: Object.values(otherProjects),
+        rotationDays
+      })
+    } catch (err) {
+      error.value = err.message
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
+  // Dry-run an import from a .env or CSV file, reporting keys that already exist
+  const previewImport = async (sourcePath, { format = null, category = null } = {}) => {
+    return await invoke('vault_import_preview', {
+      sourcePath,
+      format,
+      projectId: currentProjectId.value,
+      category,
+      existingKeys: entries.value.map(e => e.param_key).filter(Boolean)
+    })
+  }
+
+  // Import a .env or CSV file: rows are encrypted in Rust, then inserted here
+  const importEntries = async (sourcePath, { format = null, category = null, skipDuplicates = true } = {}) => {
+    try {
+      loading.value = true
+      error.value = null
+
+      if (!isUnlocked.value) {
+        throw new Error('保险箱已锁定')
+      }
+
+      await initDB()
+
+      const result = await invoke('vault_import_entries', {
+        session: sessionHandle.value,
+        sourcePath,
+        format,
+        category,
+        existingKeys: entries.value.map(e => e.param_key).filter(Boolean),
+        skipDuplicates
+      })
+
+      for (const entry of result.entries) {
+        await db.value.execute(
+          `INSERT INTO vault_entries
+           (project_id, title, param_key, encrypted_value, encrypted_notes, url, category, salt, nonce)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)`,
+          [
+            currentProjectId.value,
+            entry.title,
+            entry.param_key,
+            entry.encrypted_value,
+            entry.encrypted_notes,
+            entry.url,
+            entry.category,
+            entry.salt,
+            entry.nonce
+          ]
+        )
+      }
+
+      // Reload entries
+      await loadEntries(currentProjectId.value)
+
+      return result
+    } catch (err) {
+      error.value = err.message
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
+  // Export entries as environment variables
+  // format: 'dotenv' | 'json' | 'shell'; without outputPath the rendered text is returned
+  const exportEnv = async ({ format = 'dotenv', categories = null, outputPath = null } = {}) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    return await invoke('vault_export_env', {
+      session: sessionHandle.value,
+      entries: entries.value,
+      format,
+      categories,
+      outputPath
+    })
+  }
+
+  // Encrypt a file from disk and attach it to a vault entry
+  const attachFile = async (entryId, sourcePath) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    return await invoke('vault_attach_file', {
+      session: sessionHandle.value,
+      entryId,
+      sourcePath
+    })
+  }
+
+  // List attachments of a vault entry (file names are decrypted in Rust)
+  const listAttachments = async (entryId) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    return await invoke('vault_list_attachments', {
+      session: sessionHandle.value,
+      entryId
+    })
+  }
+
+  // Decrypt an attachment to a file chosen by the user
+  const extractAttachment = async (entryId, attachmentId, targetPath) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    return await invoke('vault_extract_attachment', {
+      session: sessionHandle.value,
+      entryId,
+      attachmentId,
+      targetPath
+    })
+  }
+
+  // Delete a single attachment of a vault entry
+  const deleteAttachment = async (entryId, attachmentId) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    await invoke('vault_delete_attachment', {
+      session: sessionHandle.value,
+      entryId,
+      attachmentId
+    })
+  }
+
+  // Number of versions kept per entry (0 = history disabled)
+  const getHistoryRetention = async (projectId) => {
+    await initDB()
+    const result = await db.value.select(
+      'SELECT history_retention FROM vault_master WHERE project_id = $1',
+      [projectId]
+    )
+    return result.length > 0 && result[0].history_retention != null ? result[0].history_retention : 10
+  }
+
+  const saveHistoryRetention = async (projectId, retention) => {
+    await initDB()
+    await db.value.execute(
+      'UPDATE vault_master SET history_retention = $1 WHERE project_id = $2',
+      [retention, projectId]
+    )
+  }
+
+  // Versions of an entry, newest first: [{ id, version, archived_at, has_notes, has_totp }]
+  const listEntryVersions = async (entryId) => {
+    return await invoke('vault_list_entry_versions', {
+      projectId: currentProjectId.value,
+      entryId
+    })
+  }
+
+  // Decrypt a historical version with the master password; returns { paramValue, notes }
+  const decryptEntryVersion = async (versionId, password) => {
+    try {
+      const [paramValue, notes] = await invoke('vault_decrypt_entry_version', {
+        projectId: currentProjectId.value,
+        versionId,
+        masterPassword: password
+      })
+      return { paramValue, notes }
+    } catch (err) {
+      error.value = err.message
+      throw err
+    }
+  }
+
+  // Get the Argon2 params configured for a project vault (null = defaults)
+  const getKdfParams = async (projectId) => {
+    await initDB()
+    const result = await db.value.select(
+      'SELECT kdf_params FROM vault_master WHERE project_id = $1',
+      [projectId]
+    )
+    return result.length > 0 && result[0].kdf_params ? JSON.parse(result[0].kdf_params) : null
+  }
+
+  // Save Argon2 params for a project vault; the master hash is upgraded on the next unlock
+  const saveKdfParams = async (projectId, params) => {
+    await initDB()
+    await db.value.execute(
+      'UPDATE vault_master SET kdf_params = $1 WHERE project_id = $2',
+      [params ? JSON.stringify(params) : null, projectId]
+    )
+  }
+
+  // Benchmark this machine and suggest Argon2 params for the target unlock time
+  const calibrateKdf = async (targetMs = 500) => {
+    try {
+      return await invoke('vault_calibrate_kdf', { targetMs })
+    } catch (err) {
+      error.value = err.message
+      throw err
+    }
+  }
+
+  // Change the master password: Rust re-encrypts the vault and saves it in one
+  // transaction, then locks the project's sessions so it is unlocked with the new one
+  const rotateMaster = async (projectId, oldPassword, newPassword) => {
+    try {
+      loading.value = true
+      error.value = null
+
+      const rotated = await invoke('vault_rotate_master', {
+        projectId,
+        oldMaster: oldPassword,
+        newMaster: newPassword
+      })
+      if (currentProjectId.value === projectId) {
+        resetState()
+      }
+      return rotated
+    } catch (err) {
+      error.value = err.message
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
   // Generate a random password
   const generatePassword = async (options = {}) => {
     const {
@@ -340,6 +659,102 @@ export const useVaultStore = defineStore('vault', () => {
     }
   }
 
+  // Generate with a specific mode; options.mode: 'policy' | 'diceware' | 'pronounceable' | 'pattern'
+  // Returns { password, entropy_bits }
+  const generateSecret = async (options) => {
+    try {
+      return await invoke('vault_generate', { options })
+    } catch (err) {
+      error.value = err.message
+      throw err
+    }
+  }
+
+  // Generate an SSH key pair and store it as an 'ssh' entry
+  // options: { title, algorithm: 'ed25519' | 'rsa', comment, rsaBits, notes }
+  const generateSshKey = async (options) => {
+    try {
+      loading.value = true
+      error.value = null
+
+      if (!isUnlocked.value) {
+        throw new Error('保险箱已锁定')
+      }
+
+      await initDB()
+
+      const key = await invoke('vault_generate_ssh_key', {
+        session: sessionHandle.value,
+        algorithm: options.algorithm || 'ed25519',
+        comment: options.comment || null,
+        rsaBits: options.rsaBits || null,
+        notes: options.notes || null
+      })
+
+      await db.value.execute(
+        `INSERT INTO vault_entries
+         (project_id, title, param_key, encrypted_value, encrypted_notes, category, salt, nonce,
+          ssh_public_key, ssh_fingerprint)
+         VALUES ($1, $2, $3, $4, $5, 'ssh', $6, $7, $8, $9)`,
+        [
+          currentProjectId.value,
+          options.title,
+          options.comment || null,
+          key.encrypted_value,
+          key.encrypted_notes,
+          key.salt,
+          key.nonce,
+          key.public_key,
+          key.fingerprint
+        ]
+      )
+
+      await loadEntries(currentProjectId.value)
+
+      return key
+    } catch (err) {
+      error.value = err.message
+      throw err
+    } finally {
+      loading.value = false
+    }
+  }
+
+  // Write an 'ssh' entry's private key (0600) and <path>.pub to disk
+  const writeSshKey = async (entry, path, overwrite = false) => {
+    try {
+      if (!isUnlocked.value) {
+        throw new Error('保险箱已锁定')
+      }
+
+      return await invoke('vault_write_ssh_key', {
+        session: sessionHandle.value,
+        entry,
+        path,
+        overwrite
+      })
+    } catch (err) {
+      error.value = err.message
+      throw err
+    }
+  }
+
+  // Current TOTP code of an entry; { code, remaining, period, ... }
+  const getTotpCode = async (entry) => {
+    if (!isUnlocked.value) {
+      throw new Error('保险箱已锁定')
+    }
+    if (!entry.encrypted_totp) {
+      return null
+    }
+
+    return await invoke('vault_totp_code', {
+      session: sessionHandle.value,
+      encryptedTotp: entry.encrypted_totp,
+      salt: entry.salt
+    })
+  }
+
   // Computed properties
   const entriesByCategory = computed(() => {
     const categories = {}
@@ -376,6 +791,26 @@ export const useVaultStore = defineStore('vault', () => {
     deleteEntry,
     decryptEntry,
     generatePassword,
+    generateSecret,
+    getTotpCode,
+    generateSshKey,
+    writeSshKey,
+    auditVault,
+    previewImport,
+    importEntries,
+    exportEnv,
+    attachFile,
+    listAttachments,
+    extractAttachment,
+    deleteAttachment,
+    listEntryVersions,
+    decryptEntryVersion,
+    getHistoryRetention,
+    saveHistoryRetention,
+    getKdfParams,
+    saveKdfParams,
+    calibrateKdf,
+    rotateMaster,
 
     // Computed
     entriesByCategory,